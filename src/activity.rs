@@ -2,7 +2,7 @@
 
 use crate::config::Config;
 use crate::error::Result;
-use crate::models::ActivityType;
+use crate::models::{ActivityEvent, ActivityType};
 use crate::session::SessionManager;
 use colored::Colorize;
 
@@ -37,30 +37,7 @@ impl ActivityManager {
             println!("  {}", "No activities recorded".yellow());
         } else {
             for activity in activities {
-                let icon = match activity.event_type {
-                    ActivityType::SessionCreated => "🆕",
-                    ActivityType::ContextEdited => "📝",
-                    ActivityType::AiLaunched => "🚀",
-                    ActivityType::CommitMade => "💾",
-                    ActivityType::PrCreated => "🔗",
-                    ActivityType::PrSynced => "🔄",
-                    ActivityType::ShepherdRun => "🐕",
-                    ActivityType::Cascaded => "⬇️",
-                    ActivityType::Gathered => "⬆️",
-                    ActivityType::Integrated => "✅",
-                    _ => "•",
-                };
-
-                println!(
-                    "  {} {} - {}",
-                    icon,
-                    activity
-                        .timestamp
-                        .format("%Y-%m-%d %H:%M:%S")
-                        .to_string()
-                        .dimmed(),
-                    activity.details
-                );
+                print_activity(activity);
             }
         }
 
@@ -68,6 +45,34 @@ impl ActivityManager {
         Ok(())
     }
 
+    /// Show the activity log, then print new events as they are logged
+    pub fn follow_activity(&self, session_name: &str, limit: Option<usize>) -> Result<()> {
+        self.show_activity(session_name, limit)?;
+        println!("{}", "Following new activity (Ctrl+C to exit)...".dimmed());
+
+        let mut seen = self
+            .session_mgr
+            .load_session(session_name)?
+            .activity_log
+            .len();
+        loop {
+            std::thread::sleep(std::time::Duration::from_secs(1));
+
+            // The session file may be mid-write; try again next tick
+            let Ok(session) = self.session_mgr.load_session(session_name) else {
+                continue;
+            };
+            // A shorter log was rewritten, so show it from the start
+            if session.activity_log.len() < seen {
+                seen = 0;
+            }
+            for activity in &session.activity_log[seen..] {
+                print_activity(activity);
+            }
+            seen = session.activity_log.len();
+        }
+    }
+
     /// Show metrics for a session
     pub fn show_metrics(&self, session_name: &str) -> Result<()> {
         let session = self.session_mgr.load_session(session_name)?;
@@ -143,6 +148,35 @@ impl ActivityManager {
     }
 }
 
+/// Print one activity log line
+fn print_activity(activity: &ActivityEvent) {
+    let icon = match activity.event_type {
+        ActivityType::SessionCreated => "🆕",
+        ActivityType::ContextEdited => "📝",
+        ActivityType::AiLaunched => "🚀",
+        ActivityType::AiExited => "🏁",
+        ActivityType::CommitMade => "💾",
+        ActivityType::PrCreated | ActivityType::PrUpdated => "🔗",
+        ActivityType::PrSynced => "🔄",
+        ActivityType::ShepherdRun => "🐕",
+        ActivityType::Cascaded => "⬇️",
+        ActivityType::Gathered => "⬆️",
+        ActivityType::Integrated => "✅",
+        _ => "•",
+    };
+
+    println!(
+        "  {} {} - {}",
+        icon,
+        activity
+            .timestamp
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+            .dimmed(),
+        activity.details
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,444 @@
+//! AI tool adapters
+//!
+//! Each supported AI tool reads session context in its own way: a custom
+//! slash command, an instructions file such as `CLAUDE.md` or `AGENTS.md`,
+//! a command-line flag, or an environment variable. An adapter knows where
+//! its tool looks, and `prepare` puts the rendered context there using
+//! absolute paths. Files written into the workbox are cleaned up when the
+//! returned `PreparedContext` is dropped, or, for tools that outlive hp,
+//! when the process registry sees them exit; in instructions files only the
+//! marked context block is removed, keeping the tool's own edits.
+
+use crate::config::ContextStrategy;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MARKER_START: &str = "<!-- hp:context:start -->";
+const MARKER_END: &str = "<!-- hp:context:end -->";
+
+/// How one AI tool consumes context
+#[derive(Debug)]
+pub struct ToolAdapter {
+    pub name: &'static str,
+    /// Command names this adapter handles
+    pub commands: &'static [&'static str],
+    /// Instructions file the tool reads from the workbox root
+    pub instructions_file: Option<&'static str>,
+    /// Custom slash command directory, relative to the workbox
+    pub commands_dir: Option<&'static str>,
+    /// Flag that takes a context file path
+    pub context_flag: Option<&'static str>,
+    /// Built-in headless argument template
+    pub headless_args: Option<&'static [&'static str]>,
+}
+
+const ADAPTERS: &[ToolAdapter] = &[
+    ToolAdapter {
+        name: "claude",
+        commands: &["claude", "claude-code"],
+        instructions_file: Some("CLAUDE.md"),
+        commands_dir: Some(".claude/commands"),
+        context_flag: None,
+        headless_args: Some(&["-p", "{prompt}"]),
+    },
+    ToolAdapter {
+        name: "codex",
+        commands: &["codex"],
+        instructions_file: Some("AGENTS.md"),
+        commands_dir: None,
+        context_flag: None,
+        headless_args: Some(&["exec", "{prompt}"]),
+    },
+    ToolAdapter {
+        name: "aider",
+        commands: &["aider"],
+        instructions_file: None,
+        commands_dir: None,
+        context_flag: Some("--read"),
+        headless_args: Some(&["--yes-always", "--message-file", "{prompt_file}"]),
+    },
+    ToolAdapter {
+        name: "gemini",
+        commands: &["gemini"],
+        instructions_file: Some("GEMINI.md"),
+        commands_dir: None,
+        context_flag: None,
+        headless_args: Some(&["-p", "{prompt}"]),
+    },
+    ToolAdapter {
+        name: "cursor",
+        commands: &["cursor", "cursor-agent"],
+        instructions_file: Some("AGENTS.md"),
+        commands_dir: None,
+        context_flag: None,
+        headless_args: None,
+    },
+];
+
+/// Adapter for tools we know nothing about
+const GENERIC: ToolAdapter = ToolAdapter {
+    name: "generic",
+    commands: &[],
+    instructions_file: Some("AGENTS.md"),
+    commands_dir: None,
+    context_flag: Some("--context"),
+    headless_args: None,
+};
+
+/// Find the adapter for a tool command (a name or a path to one)
+pub fn for_command(command: &str) -> &'static ToolAdapter {
+    let name = Path::new(command)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(command);
+    ADAPTERS
+        .iter()
+        .find(|a| a.commands.contains(&name))
+        .unwrap_or(&GENERIC)
+}
+
+/// How to undo one file written into the workbox
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Undo {
+    /// Put back the original content, or delete the file if `None`
+    Replace {
+        path: PathBuf,
+        original: Option<String>,
+    },
+    /// Remove the context block, deleting the file if hp created it
+    RemoveBlock { path: PathBuf, created: bool },
+}
+
+/// Context files to remove once the tool is done with them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContextCleanup {
+    /// Files to clean up, in the order they were written
+    restore: Vec<Undo>,
+    /// Directories created for the files, removed again when empty
+    created_dirs: Vec<PathBuf>,
+}
+
+impl ContextCleanup {
+    pub fn is_empty(&self) -> bool {
+        self.restore.is_empty() && self.created_dirs.is_empty()
+    }
+
+    /// Undo the writes, newest first
+    pub fn run(&mut self) -> Result<()> {
+        for undo in self.restore.drain(..).rev() {
+            match undo {
+                Undo::Replace {
+                    path,
+                    original: Some(content),
+                } => fs::write(&path, content)?,
+                Undo::Replace {
+                    path,
+                    original: None,
+                } if path.exists() => fs::remove_file(&path)?,
+                Undo::Replace { .. } => {}
+                Undo::RemoveBlock { path, created } => {
+                    // The tool may have edited or removed the file meanwhile
+                    let Ok(current) = fs::read_to_string(&path) else {
+                        continue;
+                    };
+                    let rest = remove_block(&current);
+                    if created && rest.trim().is_empty() {
+                        fs::remove_file(&path)?;
+                    } else if rest != current {
+                        fs::write(&path, rest)?;
+                    }
+                }
+            }
+        }
+        for dir in self.created_dirs.drain(..).rev() {
+            // Only removes directories that are still empty
+            let _ = fs::remove_dir(&dir);
+        }
+        Ok(())
+    }
+}
+
+/// Context handed to a tool, with any workbox files it needed
+#[derive(Debug, Default)]
+pub struct PreparedContext {
+    /// Arguments to add to the tool command
+    pub args: Vec<String>,
+    /// Environment variables to set for the tool
+    pub env: HashMap<String, String>,
+    /// Where the context went, for output
+    pub summary: String,
+    /// How to undo the files written for the tool
+    cleanup: ContextCleanup,
+}
+
+impl PreparedContext {
+    /// Leave the workbox files in place, for tools that outlive this
+    /// process, returning what has to run once the tool exits
+    pub fn persist(mut self) -> ContextCleanup {
+        std::mem::take(&mut self.cleanup)
+    }
+
+    /// Write `content` to `path`, remembering how to undo it
+    fn write(&mut self, workbox: &Path, path: &Path, content: String) -> Result<()> {
+        self.create_parents(workbox, path)?;
+        let original = fs::read_to_string(path).ok();
+        fs::write(path, content)?;
+        self.cleanup.restore.push(Undo::Replace {
+            path: path.to_path_buf(),
+            original,
+        });
+        Ok(())
+    }
+
+    /// Put `context` in a marked block in `path`, replacing any earlier block
+    fn write_block(&mut self, workbox: &Path, path: &Path, context: &str) -> Result<()> {
+        self.create_parents(workbox, path)?;
+        let block = format!("{}\n{}\n{}\n", MARKER_START, context.trim_end(), MARKER_END);
+        let existing = fs::read_to_string(path).ok();
+        let content = match existing.as_deref().map(remove_block) {
+            Some(rest) if !rest.trim().is_empty() => format!("{}\n\n{}", rest.trim_end(), block),
+            _ => block,
+        };
+        fs::write(path, content)?;
+        self.cleanup.restore.push(Undo::RemoveBlock {
+            path: path.to_path_buf(),
+            created: existing.is_none(),
+        });
+        Ok(())
+    }
+
+    /// Create the missing directories above `path`, remembering them
+    fn create_parents(&mut self, workbox: &Path, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            let mut missing = Vec::new();
+            let mut dir = parent;
+            while !dir.exists() && dir.starts_with(workbox) {
+                missing.push(dir.to_path_buf());
+                match dir.parent() {
+                    Some(up) => dir = up,
+                    None => break,
+                }
+            }
+            fs::create_dir_all(parent)?;
+            // Innermost last, so they're removed first
+            self.cleanup.created_dirs.extend(missing.into_iter().rev());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PreparedContext {
+    fn drop(&mut self) {
+        let _ = self.cleanup.run();
+    }
+}
+
+impl ToolAdapter {
+    /// Strategy actually used for `strategy`, falling back to one this tool
+    /// supports. Slash commands need an interactive tool.
+    pub fn resolve(&self, strategy: &ContextStrategy, headless: bool) -> ContextStrategy {
+        let mut strategy = strategy.clone();
+        if matches!(strategy, ContextStrategy::SlashCommand)
+            && (headless || self.commands_dir.is_none())
+        {
+            strategy = ContextStrategy::File;
+        }
+        if matches!(strategy, ContextStrategy::File) && self.instructions_file.is_none() {
+            strategy = ContextStrategy::Flag;
+        }
+        if matches!(strategy, ContextStrategy::Flag) && self.context_flag.is_none() {
+            strategy = if self.instructions_file.is_some() {
+                ContextStrategy::File
+            } else {
+                ContextStrategy::Env
+            };
+        }
+        strategy
+    }
+
+    /// Strategy that leaves the workbox alone, for tools whose exit hp
+    /// never sees
+    pub fn without_files(&self) -> ContextStrategy {
+        if self.context_flag.is_some() {
+            ContextStrategy::Flag
+        } else {
+            ContextStrategy::Env
+        }
+    }
+
+    /// Hand the rendered context at `rendered` to the tool running in `workbox`
+    pub fn prepare(
+        &self,
+        strategy: &ContextStrategy,
+        workbox: &Path,
+        rendered: &Path,
+        headless: bool,
+    ) -> Result<PreparedContext> {
+        let rendered = absolute(rendered)?;
+        let rendered_str = rendered.to_string_lossy().to_string();
+        let mut prepared = PreparedContext::default();
+
+        match self.resolve(strategy, headless) {
+            ContextStrategy::SlashCommand => {
+                let dir = self.commands_dir.unwrap_or(".claude/commands");
+                let path = workbox.join(dir).join("hp_context.md");
+                prepared.write(
+                    workbox,
+                    &path,
+                    format!(
+                        "Read the hupasiya context file at {} and use it to guide your work on this session.\n",
+                        rendered_str
+                    ),
+                )?;
+                prepared.summary = format!("slash command /hp_context ({})", path.display());
+            }
+            ContextStrategy::File => {
+                let name = self.instructions_file.unwrap_or("AGENTS.md");
+                let path = workbox.join(name);
+                let context = fs::read_to_string(&rendered)?;
+                prepared.write_block(workbox, &path, &context)?;
+                prepared.summary = format!("{} in the workbox", name);
+            }
+            ContextStrategy::Flag => {
+                let flag = self.context_flag.unwrap_or("--context");
+                prepared.args = vec![flag.to_string(), rendered_str.clone()];
+                prepared.summary = format!("{} {}", flag, rendered_str);
+            }
+            ContextStrategy::Env => {
+                prepared
+                    .env
+                    .insert("CONTEXT_FILE".to_string(), rendered_str.clone());
+                prepared.summary = format!("CONTEXT_FILE={}", rendered_str);
+            }
+        }
+
+        Ok(prepared)
+    }
+}
+
+/// `content` without its hp context block and the blank lines around it
+fn remove_block(content: &str) -> String {
+    let (Some(start), Some(end)) = (content.find(MARKER_START), content.find(MARKER_END)) else {
+        return content.to_string();
+    };
+    if end < start {
+        return content.to_string();
+    }
+
+    let before = content[..start].trim_end();
+    let after = content[end + MARKER_END.len()..].trim_start_matches(['\r', '\n']);
+    match (before.is_empty(), after.is_empty()) {
+        (true, _) => after.to_string(),
+        (false, true) => format!("{}\n", before),
+        (false, false) => format!("{}\n\n{}", before, after),
+    }
+}
+
+/// Absolute form of `path`, which the tool may read from another directory
+pub fn absolute(path: &Path) -> Result<PathBuf> {
+    if path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+    path.canonicalize().or_else(|_| {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .map_err(|e| Error::Other(format!("Failed to resolve {}: {}", path.display(), e)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_file_strategy_appends_and_restores() {
+        let temp = TempDir::new().unwrap();
+        let workbox = temp.path().join("wb");
+        fs::create_dir(&workbox).unwrap();
+        fs::write(workbox.join("CLAUDE.md"), "# Project rules\n").unwrap();
+        let rendered = temp.path().join("rendered.md");
+        fs::write(&rendered, "Session goal\n").unwrap();
+
+        let adapter = for_command("/usr/bin/claude");
+        let prepared = adapter
+            .prepare(&ContextStrategy::File, &workbox, &rendered, false)
+            .unwrap();
+        let content = fs::read_to_string(workbox.join("CLAUDE.md")).unwrap();
+        assert!(content.starts_with("# Project rules\n\n"));
+        assert!(content.contains("Session goal"));
+
+        drop(prepared);
+        assert_eq!(
+            fs::read_to_string(workbox.join("CLAUDE.md")).unwrap(),
+            "# Project rules\n"
+        );
+
+        // A block left by an earlier run is replaced, and the tool's own
+        // edits survive cleanup
+        let stale = adapter
+            .prepare(&ContextStrategy::File, &workbox, &rendered, false)
+            .unwrap();
+        stale.persist();
+        fs::write(&rendered, "New goal\n").unwrap();
+        let prepared = adapter
+            .prepare(&ContextStrategy::File, &workbox, &rendered, false)
+            .unwrap();
+        let content = fs::read_to_string(workbox.join("CLAUDE.md")).unwrap();
+        assert_eq!(content.matches(MARKER_START).count(), 1);
+        assert!(content.contains("New goal") && !content.contains("Session goal"));
+        fs::write(
+            workbox.join("CLAUDE.md"),
+            content.replace("# Project rules", "# Project rules\n\nUse tabs"),
+        )
+        .unwrap();
+        drop(prepared);
+        assert_eq!(
+            fs::read_to_string(workbox.join("CLAUDE.md")).unwrap(),
+            "# Project rules\n\nUse tabs\n"
+        );
+
+        // A file created for the context is removed again
+        let prepared = for_command("codex")
+            .prepare(&ContextStrategy::File, &workbox, &rendered, false)
+            .unwrap();
+        assert!(workbox.join("AGENTS.md").exists());
+        drop(prepared);
+        assert!(!workbox.join("AGENTS.md").exists());
+
+        // Slash commands are written in the workbox and removed with their dirs
+        let prepared = adapter
+            .prepare(&ContextStrategy::SlashCommand, &workbox, &rendered, false)
+            .unwrap();
+        let command = workbox.join(".claude/commands/hp_context.md");
+        assert!(fs::read_to_string(&command)
+            .unwrap()
+            .contains(&rendered.to_string_lossy().to_string()));
+        drop(prepared);
+        assert!(!workbox.join(".claude").exists());
+    }
+
+    #[test]
+    fn test_strategy_fallbacks() {
+        let codex = for_command("codex");
+        assert!(matches!(
+            codex.resolve(&ContextStrategy::SlashCommand, false),
+            ContextStrategy::File
+        ));
+        assert!(matches!(
+            for_command("claude").resolve(&ContextStrategy::SlashCommand, true),
+            ContextStrategy::File
+        ));
+
+        let aider = for_command("aider");
+        assert!(matches!(
+            aider.resolve(&ContextStrategy::File, false),
+            ContextStrategy::Flag
+        ));
+        assert_eq!(for_command("my-agent").name, "generic");
+    }
+}
@@ -1,16 +1,283 @@
 //! AI tool integration for hupasiya
 //!
-//! Handles launching AI tools with session context, opening shells in workboxes,
-//! and executing commands in session environments.
+//! Handles launching AI tools with session context, running them headless
+//! with captured output, opening shells in workboxes, and executing commands
+//! in session environments.
 
-use crate::config::{AiToolConfig, Config, ContextStrategy, LaunchMethod};
+use crate::adapters::{self, ContextCleanup};
+use crate::config::{AiToolConfig, Config, LaunchMethod};
+use crate::context::ContextManager;
 use crate::error::{Error, Result};
 use crate::hn_client::HnClient;
+use crate::models::{ActivityType, Session, WorkboxInfo};
+use crate::processes::{AgentProcess, ProcessManager, ProcessTarget};
 use crate::session::SessionManager;
+use crate::workspace::shell_quote;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env;
-use std::path::PathBuf;
-use std::process::Command;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs the tool given as arguments, then reports its exit code to hp from
+/// the repository root
+const EXIT_HOOK: &str =
+    r#""$@"; code=$?; cd "$HP_ROOT" && "$HP_BIN" agent-exited "$HP_AGENT_ID" "$code""#;
+
+/// How long to wait for the output readers once the tool has exited;
+/// background processes it left behind can hold the pipes open
+const READER_GRACE: Duration = Duration::from_secs(2);
+
+/// Options for `AiTool::run`
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Override AI tool command
+    pub tool: Option<String>,
+    /// Configuration profile to use
+    pub profile: Option<String>,
+    /// Override `ai_tool.timeout_secs`
+    pub timeout_secs: Option<u64>,
+    /// Print the tool's output as it arrives
+    pub echo: bool,
+}
+
+/// A non-interactive AI tool run with its output captured to `run_dir`
+pub struct HeadlessRun<'a> {
+    pub command: &'a str,
+    /// Argument template; without `{prompt}` or `{prompt_file}` the prompt
+    /// goes on stdin
+    pub args: Vec<String>,
+    pub prompt: &'a str,
+    pub workdir: &'a Path,
+    /// Receives prompt.md, stdout.log, stderr.log, transcript.log and run.json
+    pub run_dir: &'a Path,
+    pub env: HashMap<String, String>,
+    pub timeout: Duration,
+    pub echo: bool,
+}
+
+/// Outcome of a headless run, saved as run.json in its run directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub command: String,
+    /// Arguments as templated, before the prompt is substituted
+    pub args: Vec<String>,
+    pub workdir: PathBuf,
+    pub run_dir: PathBuf,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_secs: u64,
+    /// `None` when the tool was killed by a signal or timed out
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+}
+
+impl RunRecord {
+    /// True if the tool finished in time and exited 0
+    pub fn success(&self) -> bool {
+        !self.timed_out && self.exit_code == Some(0)
+    }
+
+    /// Short description of how the run ended
+    pub fn describe(&self) -> String {
+        if self.timed_out {
+            format!("timed out after {}s", self.duration_secs)
+        } else {
+            match self.exit_code {
+                Some(code) => format!("exited {} after {}s", code, self.duration_secs),
+                None => format!("killed by a signal after {}s", self.duration_secs),
+            }
+        }
+    }
+}
+
+impl HeadlessRun<'_> {
+    /// Run the tool to completion or until the timeout, capturing its output
+    pub fn run(&self) -> Result<RunRecord> {
+        fs::create_dir_all(self.run_dir)?;
+        let run_dir = self.run_dir.canonicalize()?;
+        let prompt_file = run_dir.join("prompt.md");
+        fs::write(&prompt_file, self.prompt)?;
+
+        let prompt_path = prompt_file.to_string_lossy();
+        let stdin_prompt = !self
+            .args
+            .iter()
+            .any(|a| a.contains("{prompt}") || a.contains("{prompt_file}"));
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|a| {
+                a.replace("{prompt_file}", &prompt_path)
+                    .replace("{prompt}", self.prompt)
+            })
+            .collect();
+
+        let mut cmd = Command::new(self.command);
+        cmd.args(&args)
+            .current_dir(self.workdir)
+            .envs(&self.env)
+            .env("HP_RUN_DIR", &run_dir)
+            .stdin(if stdin_prompt {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // Own process group, so a timeout also stops the tool's children
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+        let started_at = Utc::now();
+        let start = Instant::now();
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| Error::AiToolFailed(format!("Failed to run {}: {}", self.command, e)))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            let prompt = self.prompt.to_string();
+            thread::spawn(move || {
+                let _ = stdin.write_all(prompt.as_bytes());
+            });
+        }
+
+        let transcript = Arc::new(Mutex::new(File::create(run_dir.join("transcript.log"))?));
+        let mut readers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(capture(
+                stdout,
+                File::create(run_dir.join("stdout.log"))?,
+                transcript.clone(),
+                self.echo
+                    .then(|| Box::new(io::stdout()) as Box<dyn Write + Send>),
+            ));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(capture(
+                stderr,
+                File::create(run_dir.join("stderr.log"))?,
+                transcript,
+                self.echo
+                    .then(|| Box::new(io::stderr()) as Box<dyn Write + Send>),
+            ));
+        }
+
+        let mut timed_out = false;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if start.elapsed() >= self.timeout {
+                timed_out = true;
+                kill_tree(&mut child);
+                break child.wait()?;
+            }
+            thread::sleep(Duration::from_millis(100));
+        };
+        // Stop whatever still holds the pipes, then give up on the readers
+        if !wait_for(&readers, READER_GRACE) {
+            kill_group(child.id());
+            wait_for(&readers, READER_GRACE);
+        }
+        for reader in readers.into_iter().filter(|r| r.is_finished()) {
+            let _ = reader.join();
+        }
+
+        let record = RunRecord {
+            command: self.command.to_string(),
+            args: self.args.clone(),
+            workdir: self.workdir.to_path_buf(),
+            run_dir: run_dir.clone(),
+            started_at,
+            finished_at: Utc::now(),
+            duration_secs: start.elapsed().as_secs(),
+            exit_code: if timed_out { None } else { status.code() },
+            timed_out,
+        };
+        fs::write(
+            run_dir.join("run.json"),
+            serde_json::to_string_pretty(&record)?,
+        )?;
+
+        Ok(record)
+    }
+}
+
+/// Copy `source` line by line into `log` and the shared transcript
+fn capture(
+    source: impl Read + Send + 'static,
+    mut log: File,
+    transcript: Arc<Mutex<File>>,
+    mut echo: Option<Box<dyn Write + Send>>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(source);
+        let mut line = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+            let _ = log.write_all(&line);
+            if let Ok(mut transcript) = transcript.lock() {
+                let _ = transcript.write_all(&line);
+            }
+            if let Some(out) = echo.as_mut() {
+                let _ = out.write_all(&line);
+                let _ = out.flush();
+            }
+            line.clear();
+        }
+    })
+}
+
+/// Wait up to `limit` for all `readers` to finish
+fn wait_for(readers: &[thread::JoinHandle<()>], limit: Duration) -> bool {
+    let start = Instant::now();
+    while !readers.iter().all(|r| r.is_finished()) {
+        if start.elapsed() >= limit {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    true
+}
+
+/// Stop a timed-out tool along with anything it started
+fn kill_tree(child: &mut Child) {
+    kill_group(child.id());
+    let _ = child.kill();
+}
+
+/// Kill the process group a headless tool runs in
+fn kill_group(pgid: u32) {
+    #[cfg(unix)]
+    {
+        let _ = Command::new("kill")
+            .args(["-KILL", "--", &format!("-{}", pgid)])
+            .stderr(Stdio::null())
+            .status();
+    }
+    #[cfg(not(unix))]
+    let _ = pgid;
+}
+
+/// Directory for a new headless run under `context_dir`: a timestamp, an
+/// optional label and a random suffix, so runs in the same second differ
+pub fn new_run_dir(context_dir: &Path, label: Option<&str>) -> PathBuf {
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let mut name = Utc::now().format("%Y%m%d_%H%M%S").to_string();
+    if let Some(label) = label {
+        name.push('_');
+        name.push_str(label);
+    }
+    name.push('_');
+    name.push_str(&suffix[..6]);
+    context_dir.join("runs").join(name)
+}
 
 /// AI tool launcher
 pub struct AiTool {
@@ -61,41 +328,34 @@ impl AiTool {
 
         // Set up environment variables
         let mut env_vars = ai_config.env.clone();
-        env_vars.insert("HP_SESSION".to_string(), session_name.clone());
-        env_vars.insert(
-            "HP_CONTEXT".to_string(),
-            format!(".hp/contexts/{}", session_name),
-        );
-        env_vars.insert(
-            "HP_WORKBOX".to_string(),
-            workbox_info.path.to_string_lossy().to_string(),
-        );
-        env_vars.insert("HP_VCS".to_string(), workbox_info.vcs_type.clone());
+        env_vars.extend(self.session_env(&session, &workbox_info)?);
+
+        // Expand global context and includes into rendered.md
+        let context_mgr = ContextManager::new(self.config.clone())?;
+        let rendered_path = context_mgr.write_rendered_context(&session)?;
+
+        // Already inside tmux (e.g. a `hp workspace` pane), run in place
+        // rather than nesting another tmux session
+        let launch_method = match ai_config.launch_method {
+            LaunchMethod::Tmux if env::var_os("TMUX").is_some() => LaunchMethod::Exec,
+            method => method,
+        };
+
+        // Hand the context over the way this tool reads it. The shell
+        // wrapper runs the tool after hp exits, so nothing could clean up
+        // files written for it.
+        let adapter = adapters::for_command(&tool_command);
+        let strategy = match launch_method {
+            LaunchMethod::ShellFunction => adapter.without_files(),
+            _ => ai_config.context_strategy.clone(),
+        };
+        let context = adapter.prepare(&strategy, &workbox_info.path, &rendered_path, false)?;
+        println!("📝 Context ({} adapter): {}", adapter.name, context.summary);
+        env_vars.extend(context.env.clone());
 
         // Build command args
         let mut args = ai_config.extra_args.clone();
-
-        // Add context based on strategy
-        match ai_config.context_strategy {
-            ContextStrategy::SlashCommand => {
-                // For slash command strategy, create a global command file
-                self.create_slash_command(&session_name)?;
-                println!("📝 Created slash command for context");
-            }
-            ContextStrategy::Flag => {
-                args.push("--context".to_string());
-                args.push(format!(".hp/contexts/{}/context.md", session_name));
-            }
-            ContextStrategy::Env => {
-                env_vars.insert(
-                    "CONTEXT_FILE".to_string(),
-                    format!(".hp/contexts/{}/context.md", session_name),
-                );
-            }
-            ContextStrategy::File => {
-                // Context file is already in place, tool will read it
-            }
-        }
+        args.extend(context.args.clone());
 
         // Add extra args from command line
         args.extend(extra_args);
@@ -109,13 +369,22 @@ impl AiTool {
 
         // Launch based on method
         let workbox_path = workbox_info.path.to_string_lossy().to_string();
-        match ai_config.launch_method {
+        match launch_method {
             LaunchMethod::Exec => {
-                self.launch_exec(&tool_command, &args, &env_vars, &workbox_path)?;
+                // Context files are restored when `context` drops after this
+                self.launch_exec(
+                    &tool_command,
+                    &args,
+                    &env_vars,
+                    &workbox_path,
+                    &session_name,
+                )?;
             }
             LaunchMethod::ShellFunction => {
                 self.launch_shell_function(&tool_command, &args, &env_vars, &workbox_path)?;
             }
+            // The tool outlives this process; its context files are
+            // cleaned up when the registry sees it exit
             LaunchMethod::Tmux => {
                 self.launch_tmux(
                     &tool_command,
@@ -123,6 +392,7 @@ impl AiTool {
                     &env_vars,
                     &workbox_path,
                     &session_name,
+                    context.persist(),
                 )?;
             }
             LaunchMethod::Screen => {
@@ -132,6 +402,7 @@ impl AiTool {
                     &env_vars,
                     &workbox_path,
                     &session_name,
+                    context.persist(),
                 )?;
             }
         }
@@ -139,6 +410,78 @@ impl AiTool {
         Ok(())
     }
 
+    /// Run the AI tool headless on `prompt` in the session's workbox
+    ///
+    /// Output is captured under the session's context dir in
+    /// `runs/<timestamp>/`, and the outcome is logged to the session activity.
+    pub fn run(&self, session_name: &str, prompt: &str, options: &RunOptions) -> Result<RunRecord> {
+        let mut session = self.session_mgr.load_session(session_name)?;
+        let workbox_info = self.hn_client.get_workbox_info(&session.workbox_name)?;
+
+        let ai_config = if let Some(profile_name) = &options.profile {
+            self.get_profile_ai_config(profile_name)?
+        } else {
+            self.config.hp.ai_tool.clone()
+        };
+        let tool_command = options
+            .tool
+            .clone()
+            .unwrap_or_else(|| ai_config.command.clone());
+
+        let mut env_vars = ai_config.env.clone();
+        env_vars.extend(self.session_env(&session, &workbox_info)?);
+
+        let context_mgr = ContextManager::new(self.config.clone())?;
+        let rendered_path = context_mgr.write_rendered_context(&session)?;
+        let adapter = adapters::for_command(&tool_command);
+        let context = adapter.prepare(
+            &ai_config.context_strategy,
+            &workbox_info.path,
+            &rendered_path,
+            true,
+        )?;
+        env_vars.extend(context.env.clone());
+
+        let mut args = ai_config.extra_args.clone();
+        args.extend(context.args.clone());
+        args.extend(
+            ai_config
+                .headless_template(&tool_command)
+                .unwrap_or_default(),
+        );
+
+        let run_dir = new_run_dir(&session.context_dir, None);
+        let timeout = options.timeout_secs.unwrap_or(ai_config.timeout_secs);
+
+        println!(
+            "🤖 Running {} headless for session '{}'",
+            tool_command, session_name
+        );
+        println!("📂 Workbox: {}", workbox_info.path.display());
+        println!("📝 Context ({} adapter): {}", adapter.name, context.summary);
+
+        let record = HeadlessRun {
+            command: &tool_command,
+            args,
+            prompt,
+            workdir: &workbox_info.path,
+            run_dir: &run_dir,
+            env: env_vars,
+            timeout: Duration::from_secs(timeout),
+            echo: options.echo,
+        }
+        .run()?;
+        drop(context);
+
+        session.log_activity(
+            ActivityType::AiLaunched,
+            format!("Headless {} run {}", tool_command, record.describe()),
+        );
+        self.session_mgr.save_session(&session)?;
+
+        Ok(record)
+    }
+
     /// Launch shell in session workbox
     pub fn shell(&self, session_name: Option<String>, command: Option<Vec<String>>) -> Result<()> {
         // Determine session name
@@ -151,17 +494,7 @@ impl AiTool {
         let workbox_info = self.hn_client.get_workbox_info(&session.workbox_name)?;
 
         // Set up environment variables
-        let mut env_vars = HashMap::new();
-        env_vars.insert("HP_SESSION".to_string(), session_name.clone());
-        env_vars.insert(
-            "HP_CONTEXT".to_string(),
-            format!(".hp/contexts/{}", session_name),
-        );
-        env_vars.insert(
-            "HP_WORKBOX".to_string(),
-            workbox_info.path.to_string_lossy().to_string(),
-        );
-        env_vars.insert("HP_VCS".to_string(), workbox_info.vcs_type.clone());
+        let env_vars = self.session_env(&session, &workbox_info)?;
 
         let workbox_path = workbox_info.path.to_string_lossy().to_string();
         if let Some(cmd) = command {
@@ -226,20 +559,25 @@ impl AiTool {
             .unwrap_or(self.config.hp.ai_tool.clone()))
     }
 
-    fn create_slash_command(&self, session_name: &str) -> Result<()> {
-        // Create .claude/commands/hp_context.md for Claude Code
-        let commands_dir = PathBuf::from(".claude/commands");
-        std::fs::create_dir_all(&commands_dir)?;
-
-        let context_path = format!(".hp/contexts/{}/context.md", session_name);
-        let slash_command = format!(
-            "Read the hupasiya context file at {} and use it to guide your work on this session.",
-            context_path
-        );
-
-        std::fs::write(commands_dir.join("hp_context.md"), slash_command)?;
-
-        Ok(())
+    /// Variables identifying the session to the tool, with absolute paths
+    fn session_env(
+        &self,
+        session: &Session,
+        workbox_info: &WorkboxInfo,
+    ) -> Result<HashMap<String, String>> {
+        let context_file = adapters::absolute(&session.context_dir.join("context.md"))?;
+        Ok(HashMap::from([
+            ("HP_SESSION".to_string(), session.name.clone()),
+            (
+                "HP_CONTEXT".to_string(),
+                context_file.to_string_lossy().to_string(),
+            ),
+            (
+                "HP_WORKBOX".to_string(),
+                workbox_info.path.to_string_lossy().to_string(),
+            ),
+            ("HP_VCS".to_string(), workbox_info.vcs_type.clone()),
+        ]))
     }
 
     fn launch_exec(
@@ -248,11 +586,18 @@ impl AiTool {
         args: &[String],
         env_vars: &HashMap<String, String>,
         workdir: &str,
+        session_name: &str,
     ) -> Result<()> {
         let mut cmd = Command::new(command);
         cmd.args(args).current_dir(workdir).envs(env_vars);
 
-        let status = cmd.status()?;
+        let mut child = cmd.spawn()?;
+        let processes = ProcessManager::new(self.config.clone())?;
+        let process = AgentProcess::new(session_name, command, ProcessTarget::pid(child.id()));
+        processes.register(&process)?;
+
+        let status = child.wait()?;
+        processes.finish(&process.id, status.code())?;
 
         if !status.success() {
             return Err(Error::AiToolFailed(format!(
@@ -264,6 +609,35 @@ impl AiTool {
         Ok(())
     }
 
+    /// Register a tool that will run detached, along with the context
+    /// files to remove when it exits, adding the variables the exit hook
+    /// needs to report back
+    fn track_detached(
+        &self,
+        session_name: &str,
+        command: &str,
+        target: ProcessTarget,
+        env_vars: &HashMap<String, String>,
+        cleanup: ContextCleanup,
+    ) -> Result<(ProcessManager, AgentProcess, HashMap<String, String>)> {
+        let processes = ProcessManager::new(self.config.clone())?;
+        let mut process = AgentProcess::new(session_name, command, target);
+        process.cleanup = cleanup;
+        processes.register(&process)?;
+
+        let mut env_vars = env_vars.clone();
+        env_vars.insert("HP_AGENT_ID".to_string(), process.id.clone());
+        env_vars.insert(
+            "HP_BIN".to_string(),
+            env::current_exe()?.to_string_lossy().to_string(),
+        );
+        env_vars.insert(
+            "HP_ROOT".to_string(),
+            env::current_dir()?.to_string_lossy().to_string(),
+        );
+        Ok((processes, process, env_vars))
+    }
+
     fn launch_shell_function(
         &self,
         command: &str,
@@ -289,28 +663,40 @@ impl AiTool {
         env_vars: &HashMap<String, String>,
         workdir: &str,
         session_name: &str,
+        cleanup: ContextCleanup,
     ) -> Result<()> {
         // Create new tmux session
         let tmux_session_name = format!("hp-{}", session_name);
+        let (processes, process, env_vars) = self.track_detached(
+            session_name,
+            command,
+            ProcessTarget::Tmux {
+                session: tmux_session_name.clone(),
+            },
+            env_vars,
+            cleanup,
+        )?;
 
         let mut cmd = Command::new("tmux");
         cmd.arg("new-session")
+            .arg("-d")
             .arg("-s")
             .arg(&tmux_session_name)
             .arg("-c")
             .arg(workdir);
 
         // Set environment variables
-        for (key, value) in env_vars {
+        for (key, value) in &env_vars {
             cmd.arg("-e").arg(format!("{}={}", key, value));
         }
 
-        // Execute command in tmux
-        cmd.arg(command).args(args);
+        // Execute command in tmux, reporting its exit
+        cmd.args(["sh", "-c", EXIT_HOOK, "sh", command]).args(args);
 
         let status = cmd.status()?;
 
         if !status.success() {
+            processes.forget(&process.id)?;
             return Err(Error::AiToolFailed(format!(
                 "tmux command exited with status: {}",
                 status
@@ -330,18 +716,21 @@ impl AiTool {
         env_vars: &HashMap<String, String>,
         workdir: &str,
         session_name: &str,
+        cleanup: ContextCleanup,
     ) -> Result<()> {
         // Create new screen session
         let screen_session_name = format!("hp-{}", session_name);
-
-        // Build command string with env vars
-        let mut cmd_parts = vec![];
-        for (key, value) in env_vars {
-            cmd_parts.push(format!("export {}={};", key, value));
-        }
-        cmd_parts.push(format!("cd {};", workdir));
-        cmd_parts.push(format!("{} {}", command, args.join(" ")));
-        let full_command = cmd_parts.join(" ");
+        let (processes, process, env_vars) = self.track_detached(
+            session_name,
+            command,
+            ProcessTarget::Screen {
+                session: screen_session_name.clone(),
+            },
+            env_vars,
+            cleanup,
+        )?;
+
+        let full_command = screen_script(command, args, &env_vars, workdir);
 
         let mut cmd = Command::new("screen");
         cmd.arg("-S")
@@ -354,6 +743,7 @@ impl AiTool {
         let status = cmd.status()?;
 
         if !status.success() {
+            processes.forget(&process.id)?;
             return Err(Error::AiToolFailed(format!(
                 "screen command exited with status: {}",
                 status
@@ -416,8 +806,7 @@ impl AiTool {
         let session = self.session_mgr.load_session(session_name)?;
         let workbox_info = self.hn_client.get_workbox_info(&session.workbox_name)?;
 
-        let mut env_vars = HashMap::new();
-        env_vars.insert("HP_SESSION".to_string(), session_name.to_string());
+        let env_vars = self.session_env(&session, &workbox_info)?;
 
         let workbox_path = workbox_info.path.to_string_lossy().to_string();
         self.run_in_workbox(&workbox_path, command, &env_vars)?;
@@ -489,6 +878,27 @@ impl AiTool {
     }
 }
 
+/// `bash -c` script running the tool in screen and reporting its exit
+fn screen_script(
+    command: &str,
+    args: &[String],
+    env_vars: &HashMap<String, String>,
+    workdir: &str,
+) -> String {
+    let mut cmd_parts = vec![];
+    for (key, value) in env_vars {
+        cmd_parts.push(format!("export {}={};", key, shell_quote(value)));
+    }
+    cmd_parts.push(format!("cd {};", shell_quote(workdir)));
+    let words: Vec<String> = std::iter::once(command)
+        .chain(args.iter().map(String::as_str))
+        .map(shell_quote)
+        .collect();
+    cmd_parts.push(format!("set -- {};", words.join(" ")));
+    cmd_parts.push(EXIT_HOOK.to_string());
+    cmd_parts.join(" ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -516,6 +926,111 @@ mod tests {
         }
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_screen_script_quotes_values() {
+        let temp = TempDir::new().unwrap();
+        let workdir = temp.path().join("it's a dir");
+        fs::create_dir(&workdir).unwrap();
+        let env_vars = HashMap::from([
+            ("HP_AGENT_ID".to_string(), "a1; rm -rf x".to_string()),
+            ("HP_BIN".to_string(), "echo".to_string()),
+            (
+                "HP_ROOT".to_string(),
+                temp.path().to_string_lossy().to_string(),
+            ),
+        ]);
+        let args = vec!["%s|".to_string(), "$HOME".to_string(), "a b".to_string()];
+        let script = screen_script("printf", &args, &env_vars, &workdir.to_string_lossy());
+
+        let output = Command::new("bash")
+            .arg("-c")
+            .arg(&script)
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "$HOME|a b|agent-exited a1; rm -rf x 0\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_headless_run_captures_output_and_times_out() {
+        let temp_dir = TempDir::new().unwrap();
+        let run = |script: &str, timeout: u64, dir: &str| {
+            HeadlessRun {
+                command: "sh",
+                args: vec!["-c".to_string(), script.to_string()],
+                prompt: "fix it",
+                workdir: temp_dir.path(),
+                run_dir: &temp_dir.path().join(dir),
+                env: HashMap::new(),
+                timeout: Duration::from_secs(timeout),
+                echo: false,
+            }
+            .run()
+            .unwrap()
+        };
+
+        let record = run(
+            "cat \"$HP_RUN_DIR/prompt.md\"; echo oops >&2; exit 3",
+            30,
+            "a",
+        );
+        assert!(!record.success());
+        assert_eq!(record.exit_code, Some(3));
+        assert_eq!(
+            fs::read_to_string(record.run_dir.join("stdout.log")).unwrap(),
+            "fix it"
+        );
+        assert_eq!(
+            fs::read_to_string(record.run_dir.join("stderr.log")).unwrap(),
+            "oops\n"
+        );
+        let saved: RunRecord =
+            serde_json::from_str(&fs::read_to_string(record.run_dir.join("run.json")).unwrap())
+                .unwrap();
+        assert_eq!(saved.exit_code, Some(3));
+
+        let record = run("sleep 30", 1, "b");
+        assert!(record.timed_out);
+        assert!(record.duration_secs < 10);
+
+        // A background child holding the pipes doesn't keep the run waiting
+        let record = run("sleep 30 & echo started", 30, "c");
+        assert_eq!(record.exit_code, Some(0));
+        assert!(record.duration_secs < 10);
+        assert_eq!(
+            fs::read_to_string(record.run_dir.join("stdout.log")).unwrap(),
+            "started\n"
+        );
+
+        let dir = new_run_dir(temp_dir.path(), Some("fix-7"));
+        assert!(dir.starts_with(temp_dir.path().join("runs")));
+        assert!(dir.to_string_lossy().contains("_fix-7_"));
+        assert_ne!(dir, new_run_dir(temp_dir.path(), Some("fix-7")));
+    }
+
+    #[test]
+    fn test_headless_template() {
+        let mut config = AiToolConfig::default();
+        assert_eq!(
+            config.headless_template("/usr/local/bin/claude"),
+            Some(vec!["-p".to_string(), "{prompt}".to_string()])
+        );
+        assert_eq!(config.headless_template("mytool"), None);
+
+        config.headless_args.insert(
+            "mytool".to_string(),
+            vec!["--task".to_string(), "{prompt_file}".to_string()],
+        );
+        assert_eq!(
+            config.headless_template("mytool").unwrap(),
+            vec!["--task", "{prompt_file}"]
+        );
+    }
+
     #[test]
     fn test_resolve_session_name_with_explicit_name() {
         // Test without creating AiTool (which requires hn)
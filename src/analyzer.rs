@@ -0,0 +1,558 @@
+//! Review comment analysis for the shepherd workflow
+//!
+//! An analyzer receives a review comment with its diff hunk and the
+//! surrounding file content, and returns a structured `ShepherdAnalysis`.
+//! Providers: OpenAI and Anthropic chat APIs, a local command speaking JSON
+//! over stdin/stdout, and a deterministic keyword stub for tests.
+
+use crate::config::{self, AnalyzerProvider, ShepherdConfig};
+use crate::error::{Error, Result};
+use crate::models::{
+    ConfidenceLevel, ReviewComment, ReviewThread, ShepherdAction, ShepherdAnalysis,
+};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const SYSTEM_PROMPT: &str = "You triage pull request review comments. \
+Reply with a single JSON object and nothing else: \
+{\"action\": \"FIX|CLARIFY|ACKNOWLEDGE|DEFER|DISAGREE\", \
+\"confidence\": \"high|medium|low\", \
+\"rationale\": \"why this action\", \
+\"response\": \"reply to post on the thread\", \
+\"changes\": \"description of the code change, or null\"}";
+
+/// Everything an analyzer sees about one review comment
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisRequest {
+    pub comment_id: u64,
+    pub path: String,
+    pub line: Option<u32>,
+    pub author: String,
+    pub body: String,
+    pub diff_hunk: Option<String>,
+    /// Numbered file lines around the comment, if the file exists
+    pub file_context: Option<String>,
+    /// Earlier replies on the thread
+    pub replies: Vec<String>,
+    pub model: String,
+}
+
+impl AnalysisRequest {
+    /// Build a request for a thread, reading file context from the workbox
+    pub fn from_thread(
+        thread: &ReviewThread,
+        comment: &ReviewComment,
+        workbox_path: &Path,
+        context_lines: usize,
+        model: &str,
+    ) -> Self {
+        // Where the code is now, which may differ from where it was reviewed
+        let (path, line) = thread.location();
+        Self {
+            comment_id: comment.id,
+            path: path.to_string(),
+            line,
+            author: comment.author.clone(),
+            body: comment.body.clone(),
+            diff_hunk: comment.diff_hunk.clone(),
+            file_context: file_excerpt(&workbox_path.join(path), line, context_lines),
+            replies: thread
+                .replies()
+                .iter()
+                .map(|c| format!("{}: {}", c.author, c.body))
+                .collect(),
+            model: model.to_string(),
+        }
+    }
+
+    /// Render the request as an LLM prompt
+    pub fn prompt(&self) -> String {
+        let mut prompt = format!("File: {}\n", self.path);
+        if let Some(line) = self.line {
+            prompt.push_str(&format!("Line: {}\n", line));
+        }
+        prompt.push_str(&format!(
+            "\nReview comment by {}:\n{}\n",
+            self.author, self.body
+        ));
+        for reply in &self.replies {
+            prompt.push_str(&format!("\nReply from {}\n", reply));
+        }
+        if let Some(hunk) = &self.diff_hunk {
+            prompt.push_str(&format!("\nDiff hunk:\n```diff\n{}\n```\n", hunk));
+        }
+        if let Some(context) = &self.file_context {
+            prompt.push_str(&format!("\nCurrent file content:\n```\n{}\n```\n", context));
+        }
+        prompt
+    }
+}
+
+/// Analyzes review comments
+pub trait Analyzer {
+    /// Short provider name for output
+    fn name(&self) -> &str;
+
+    /// Analyze a single comment
+    fn analyze(&self, request: &AnalysisRequest) -> Result<ShepherdAnalysis>;
+}
+
+/// Create the analyzer configured in `hp.pr.shepherd`
+pub fn from_config(config: &ShepherdConfig) -> Result<Box<dyn Analyzer>> {
+    let settings = &config.analyzer;
+    let model = config.analysis_model.clone();
+
+    let api_key = |default_env: &str| -> Result<String> {
+        let var = settings.api_key_env.as_deref().unwrap_or(default_env);
+        env_key(var).ok_or_else(|| {
+            Error::Other(format!(
+                "{} not set. Export it or set hp.pr.shepherd.analyzer.provider",
+                var
+            ))
+        })
+    };
+
+    Ok(match provider(config) {
+        AnalyzerProvider::Stub => Box::new(StubAnalyzer),
+        AnalyzerProvider::Command => {
+            let command = settings.command.clone().ok_or_else(|| {
+                Error::Other("hp.pr.shepherd.analyzer.command is required".to_string())
+            })?;
+            Box::new(CommandAnalyzer { command, model })
+        }
+        AnalyzerProvider::Openai => Box::new(HttpAnalyzer {
+            provider: AnalyzerProvider::Openai,
+            base_url: settings
+                .base_url
+                .clone()
+                .unwrap_or_else(|| "https://api.openai.com/v1".to_string()),
+            api_key: api_key("OPENAI_API_KEY")?,
+            model,
+        }),
+        AnalyzerProvider::Anthropic => Box::new(HttpAnalyzer {
+            provider: AnalyzerProvider::Anthropic,
+            base_url: settings
+                .base_url
+                .clone()
+                .unwrap_or_else(|| "https://api.anthropic.com".to_string()),
+            api_key: api_key("ANTHROPIC_API_KEY")?,
+            model,
+        }),
+    })
+}
+
+/// The configured provider, inferred from the command or model if unset.
+/// The keyword stub is never inferred; it has to be configured explicitly.
+pub fn provider(config: &ShepherdConfig) -> AnalyzerProvider {
+    if let Some(provider) = config.analyzer.provider {
+        provider
+    } else if config.analyzer.command.is_some() {
+        AnalyzerProvider::Command
+    } else if config.analysis_model.starts_with("claude") {
+        AnalyzerProvider::Anthropic
+    } else {
+        AnalyzerProvider::Openai
+    }
+}
+
+/// Non-empty value of an API key variable
+fn env_key(var: &str) -> Option<String> {
+    std::env::var(var).ok().filter(|k| !k.is_empty())
+}
+
+/// Whether `confidence` is at least the configured threshold
+pub fn meets_threshold(confidence: ConfidenceLevel, threshold: &config::ConfidenceLevel) -> bool {
+    let rank = match confidence {
+        ConfidenceLevel::Low => 0,
+        ConfidenceLevel::Medium => 1,
+        ConfidenceLevel::High => 2,
+    };
+    let required = match threshold {
+        config::ConfidenceLevel::Low => 0,
+        config::ConfidenceLevel::Medium => 1,
+        config::ConfidenceLevel::High => 2,
+    };
+    rank >= required
+}
+
+/// Structured analyzer output before it's attached to a comment
+#[derive(Debug, Deserialize)]
+struct RawAnalysis {
+    action: String,
+    confidence: String,
+    #[serde(default)]
+    rationale: String,
+    #[serde(default)]
+    response: String,
+    #[serde(default)]
+    changes: Option<String>,
+}
+
+/// Parse analyzer output, tolerating prose or code fences around the JSON
+pub fn parse_analysis(request: &AnalysisRequest, output: &str) -> Result<ShepherdAnalysis> {
+    let json = match (output.find('{'), output.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &output[start..=end],
+        _ => {
+            return Err(Error::Other(format!(
+                "Analyzer returned no JSON for comment {}",
+                request.comment_id
+            )))
+        }
+    };
+
+    let raw: RawAnalysis = serde_json::from_str(json).map_err(|e| {
+        Error::Other(format!(
+            "Invalid analysis for comment {}: {}",
+            request.comment_id, e
+        ))
+    })?;
+
+    let action = match raw.action.trim().to_uppercase().as_str() {
+        "FIX" => ShepherdAction::Fix,
+        "CLARIFY" => ShepherdAction::Clarify,
+        "ACKNOWLEDGE" => ShepherdAction::Acknowledge,
+        "DEFER" => ShepherdAction::Defer,
+        "DISAGREE" => ShepherdAction::Disagree,
+        other => {
+            return Err(Error::Other(format!(
+                "Unknown shepherd action '{}' for comment {}",
+                other, request.comment_id
+            )))
+        }
+    };
+    let confidence = match raw.confidence.trim().to_lowercase().as_str() {
+        "high" => ConfidenceLevel::High,
+        "medium" => ConfidenceLevel::Medium,
+        _ => ConfidenceLevel::Low,
+    };
+
+    Ok(ShepherdAnalysis {
+        comment_id: request.comment_id,
+        action,
+        confidence,
+        summary: request.body.lines().next().unwrap_or_default().to_string(),
+        assessment: raw.rationale,
+        changes: raw.changes.filter(|c| !c.trim().is_empty()),
+        response: raw.response,
+        posted: false,
+        resolved: false,
+        applied_commit: None,
+        decided_by: String::new(),
+        decided_at: None,
+        history: Vec::new(),
+    })
+}
+
+/// Numbered lines of `path` within `radius` of `line` (whole file if no line)
+pub fn file_excerpt(path: &Path, line: Option<u32>, radius: usize) -> Option<String> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let lines: Vec<&str> = content.lines().collect();
+    let (start, end) = match line {
+        Some(line) => {
+            let center = (line as usize).saturating_sub(1);
+            (
+                center.saturating_sub(radius),
+                (center + radius + 1).min(lines.len()),
+            )
+        }
+        None => (0, lines.len().min(radius * 2 + 1)),
+    };
+    if start >= end {
+        return None;
+    }
+
+    Some(
+        lines[start..end]
+            .iter()
+            .enumerate()
+            .map(|(i, l)| format!("{:>5} | {}", start + i + 1, l))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+/// OpenAI or Anthropic chat completion
+struct HttpAnalyzer {
+    provider: AnalyzerProvider,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl Analyzer for HttpAnalyzer {
+    fn name(&self) -> &str {
+        match self.provider {
+            AnalyzerProvider::Anthropic => "anthropic",
+            _ => "openai",
+        }
+    }
+
+    fn analyze(&self, request: &AnalysisRequest) -> Result<ShepherdAnalysis> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(120))
+            .build()
+            .map_err(|e| Error::Other(format!("Failed to create HTTP client: {}", e)))?;
+        let base = self.base_url.trim_end_matches('/');
+
+        let builder = match self.provider {
+            AnalyzerProvider::Anthropic => client
+                .post(format!("{}/v1/messages", base))
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", "2023-06-01")
+                .json(&serde_json::json!({
+                    "model": self.model,
+                    "max_tokens": 1024,
+                    "system": SYSTEM_PROMPT,
+                    "messages": [{ "role": "user", "content": request.prompt() }],
+                })),
+            _ => client
+                .post(format!("{}/chat/completions", base))
+                .bearer_auth(&self.api_key)
+                .json(&serde_json::json!({
+                    "model": self.model,
+                    "temperature": 0,
+                    "messages": [
+                        { "role": "system", "content": SYSTEM_PROMPT },
+                        { "role": "user", "content": request.prompt() },
+                    ],
+                })),
+        };
+
+        let response = builder
+            .send()
+            .map_err(|e| Error::Other(format!("{} request failed: {}", self.name(), e)))?;
+        let status = response.status();
+        let body: serde_json::Value = response
+            .json()
+            .map_err(|e| Error::Other(format!("Invalid {} response: {}", self.name(), e)))?;
+        if !status.is_success() {
+            return Err(Error::Other(format!(
+                "{} returned {}: {}",
+                self.name(),
+                status,
+                body["error"]["message"].as_str().unwrap_or("unknown error")
+            )));
+        }
+
+        let text = match self.provider {
+            AnalyzerProvider::Anthropic => body["content"][0]["text"].as_str(),
+            _ => body["choices"][0]["message"]["content"].as_str(),
+        }
+        .ok_or_else(|| Error::Other(format!("Empty {} response", self.name())))?;
+
+        parse_analysis(request, text)
+    }
+}
+
+/// Local command receiving the request as JSON on stdin
+struct CommandAnalyzer {
+    command: String,
+    model: String,
+}
+
+impl Analyzer for CommandAnalyzer {
+    fn name(&self) -> &str {
+        "command"
+    }
+
+    fn analyze(&self, request: &AnalysisRequest) -> Result<ShepherdAnalysis> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("HP_ANALYSIS_MODEL", &self.model)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::Other(format!("Failed to run analyzer command: {}", e)))?;
+
+        let input = serde_json::to_string(request)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // The command may exit without reading stdin
+            let _ = stdin.write_all(input.as_bytes());
+        }
+
+        let output = child
+            .wait_with_output()
+            .map_err(|e| Error::Other(format!("Analyzer command failed: {}", e)))?;
+        if !output.status.success() {
+            return Err(Error::Other(format!(
+                "Analyzer command failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        parse_analysis(request, &String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+/// Deterministic keyword analyzer for tests and offline use
+pub struct StubAnalyzer;
+
+impl Analyzer for StubAnalyzer {
+    fn name(&self) -> &str {
+        "stub"
+    }
+
+    fn analyze(&self, request: &AnalysisRequest) -> Result<ShepherdAnalysis> {
+        let body = request.body.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|w| body.contains(w));
+
+        let (action, confidence, rationale, response) =
+            if has(&["typo", "naming", "formatting", "rename"]) {
+                (
+                    ShepherdAction::Fix,
+                    ConfidenceLevel::High,
+                    "Mechanical change",
+                    "Fixed, thanks!",
+                )
+            } else if body.trim_end().ends_with('?') {
+                (
+                    ShepherdAction::Clarify,
+                    ConfidenceLevel::Medium,
+                    "Reviewer asked a question",
+                    "",
+                )
+            } else if has(&["nit", "consider", "lgtm", "nice"]) {
+                (
+                    ShepherdAction::Acknowledge,
+                    ConfidenceLevel::Medium,
+                    "Optional suggestion",
+                    "Thanks, noted.",
+                )
+            } else {
+                (
+                    ShepherdAction::Defer,
+                    ConfidenceLevel::Low,
+                    "Needs manual review",
+                    "",
+                )
+            };
+
+        let output = serde_json::json!({
+            "action": format!("{:?}", action),
+            "confidence": confidence.as_str(),
+            "rationale": rationale,
+            "response": response,
+        });
+        parse_analysis(request, &output.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn request(body: &str) -> AnalysisRequest {
+        AnalysisRequest {
+            comment_id: 7,
+            path: "src/lib.rs".to_string(),
+            line: Some(3),
+            author: "reviewer".to_string(),
+            body: body.to_string(),
+            diff_hunk: Some("@@ -1,3 +1,3 @@".to_string()),
+            file_context: None,
+            replies: Vec::new(),
+            model: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_analysis() {
+        let output = "Here you go:\n```json\n{\"action\": \"fix\", \"confidence\": \"HIGH\", \
+                      \"rationale\": \"typo\", \"response\": \"Fixed\", \"changes\": \"\"}\n```";
+        let analysis = parse_analysis(&request("Typo here"), output).unwrap();
+        assert_eq!(analysis.comment_id, 7);
+        assert_eq!(analysis.action, ShepherdAction::Fix);
+        assert_eq!(analysis.confidence, ConfidenceLevel::High);
+        assert_eq!(analysis.assessment, "typo");
+        assert_eq!(analysis.response, "Fixed");
+        assert!(analysis.changes.is_none());
+
+        assert!(parse_analysis(&request("x"), "no json").is_err());
+        assert!(parse_analysis(
+            &request("x"),
+            "{\"action\":\"ship\",\"confidence\":\"low\"}"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_stub_analyzer() {
+        let stub = StubAnalyzer;
+        let fix = stub.analyze(&request("Naming: rename this")).unwrap();
+        assert_eq!(fix.action, ShepherdAction::Fix);
+        assert_eq!(fix.confidence, ConfidenceLevel::High);
+
+        let clarify = stub.analyze(&request("Why not a map?")).unwrap();
+        assert_eq!(clarify.action, ShepherdAction::Clarify);
+
+        let defer = stub.analyze(&request("Split this module")).unwrap();
+        assert_eq!(defer.action, ShepherdAction::Defer);
+        assert_eq!(defer.confidence, ConfidenceLevel::Low);
+    }
+
+    #[test]
+    fn test_command_analyzer() {
+        let analyzer = CommandAnalyzer {
+            command: "grep -q '\"comment_id\":7' && echo '{\"action\":\"ACKNOWLEDGE\",\"confidence\":\"medium\"}'"
+                .to_string(),
+            model: "test".to_string(),
+        };
+        let analysis = analyzer.analyze(&request("Nice")).unwrap();
+        assert_eq!(analysis.action, ShepherdAction::Acknowledge);
+        assert_eq!(analysis.confidence, ConfidenceLevel::Medium);
+
+        let failing = CommandAnalyzer {
+            command: "exit 1".to_string(),
+            model: "test".to_string(),
+        };
+        assert!(failing.analyze(&request("Nice")).is_err());
+    }
+
+    #[test]
+    fn test_provider_inference_and_threshold() {
+        let mut config = ShepherdConfig::default();
+        assert_eq!(provider(&config), AnalyzerProvider::Openai);
+        config.analysis_model = "claude-3-5-sonnet".to_string();
+        assert_eq!(provider(&config), AnalyzerProvider::Anthropic);
+
+        // Without a key there is no analyzer, rather than the keyword stub
+        config.analyzer.api_key_env = Some("HP_TEST_UNSET_API_KEY".to_string());
+        let err = from_config(&config).err().unwrap();
+        assert!(err.to_string().contains("HP_TEST_UNSET_API_KEY not set"));
+
+        config.analyzer.command = Some("./analyze".to_string());
+        assert_eq!(provider(&config), AnalyzerProvider::Command);
+        config.analyzer.provider = Some(AnalyzerProvider::Stub);
+        assert_eq!(from_config(&config).unwrap().name(), "stub");
+
+        assert!(meets_threshold(
+            ConfidenceLevel::High,
+            &config::ConfidenceLevel::High
+        ));
+        assert!(!meets_threshold(
+            ConfidenceLevel::Medium,
+            &config::ConfidenceLevel::High
+        ));
+        assert!(meets_threshold(
+            ConfidenceLevel::Medium,
+            &config::ConfidenceLevel::Low
+        ));
+    }
+
+    #[test]
+    fn test_file_excerpt() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.rs");
+        std::fs::write(&path, "one\ntwo\nthree\nfour\nfive\n").unwrap();
+
+        let excerpt = file_excerpt(&path, Some(3), 1).unwrap();
+        assert_eq!(excerpt, "    2 | two\n    3 | three\n    4 | four");
+        assert!(file_excerpt(&temp_dir.path().join("missing.rs"), Some(1), 1).is_none());
+    }
+}
@@ -0,0 +1,348 @@
+//! Re-anchoring review comments to the workbox's current code
+//!
+//! Forges report where a comment was made, but later commits move the code
+//! around. A thread is re-anchored by following renames and line shifts
+//! since the comment was written (from the commit history), then confirming
+//! the position against the comment's diff hunk. Threads whose code can no
+//! longer be found are flagged outdated.
+
+use crate::error::Result;
+use crate::git;
+use crate::models::{AnchorStatus, CodeAnchor, ReviewThread};
+use std::path::Path;
+
+/// Most lines of the diff hunk matched against the current file
+const HUNK_TAIL: usize = 3;
+
+/// Matches shorter than this are too generic (`}`, blank lines) to trust
+const MIN_MATCH_CHARS: usize = 8;
+
+/// Counts of re-anchored threads by outcome
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnchorSummary {
+    pub moved: usize,
+    pub changed: usize,
+    pub deleted: usize,
+}
+
+/// Re-anchor every unresolved thread to the workbox's current contents
+///
+/// Threads whose code was deleted are marked outdated.
+pub fn reanchor_threads(workbox: &Path, threads: &mut [ReviewThread]) -> Result<AnchorSummary> {
+    let head = git::run(Some(workbox), &["rev-parse", "HEAD"])?;
+    let mut summary = AnchorSummary::default();
+
+    for thread in threads.iter_mut().filter(|t| !t.is_resolved) {
+        let Some(anchor) = reanchor(workbox, thread, &head) else {
+            continue;
+        };
+        match anchor.status {
+            AnchorStatus::Current => {}
+            AnchorStatus::Moved => summary.moved += 1,
+            AnchorStatus::Changed => summary.changed += 1,
+            AnchorStatus::Deleted => {
+                summary.deleted += 1;
+                thread.is_outdated = true;
+            }
+        }
+        thread.anchor = Some(anchor);
+    }
+
+    Ok(summary)
+}
+
+/// Where a thread's code is now
+///
+/// None for threads without a comment or a line to anchor.
+pub fn reanchor(workbox: &Path, thread: &ReviewThread, head: &str) -> Option<CodeAnchor> {
+    let comment = thread.root()?;
+    let original = thread.original_line.or(thread.line)?;
+    let anchor = |path: &str, line: Option<u32>, status| CodeAnchor {
+        path: path.to_string(),
+        line,
+        status,
+        commit: head.to_string(),
+    };
+
+    // Last commit the reviewer could have seen
+    let before = format!("--before={}", comment.created_at.to_rfc3339());
+    let base = git::run(Some(workbox), &["rev-list", "-1", &before, "HEAD"])
+        .ok()
+        .filter(|rev| !rev.is_empty());
+
+    let path = match &base {
+        Some(base) => match renamed_path(workbox, base, &thread.path) {
+            Some(path) => path,
+            None => return Some(anchor(&thread.path, None, AnchorStatus::Deleted)),
+        },
+        None => thread.path.clone(),
+    };
+    let Ok(content) = std::fs::read_to_string(workbox.join(&path)) else {
+        return Some(anchor(&path, None, AnchorStatus::Deleted));
+    };
+    let lines: Vec<&str> = content.lines().collect();
+
+    // Compares the base commit with the working tree, so uncommitted edits count
+    let mapped = base.as_ref().and_then(|base| {
+        let diff = git::run(
+            Some(workbox),
+            &["diff", "-U0", "-M", base, "--", &thread.path, &path],
+        )
+        .ok()?;
+        Some(map_line(&diff, original))
+    });
+    let hint = match mapped {
+        Some(LineMap::Shifted(line) | LineMap::Edited(line) | LineMap::Removed(line)) => line,
+        None => original,
+    };
+
+    let found = comment
+        .diff_hunk
+        .as_deref()
+        .map(|hunk| locate(&lines, &hunk_lines(hunk), hint));
+    let (line, status) = match (found, mapped) {
+        (Some(Some(line)), _) if line == original && path == thread.path => {
+            (line, AnchorStatus::Current)
+        }
+        (Some(Some(line)), _) => (line, AnchorStatus::Moved),
+        (_, Some(LineMap::Removed(_))) => return Some(anchor(&path, None, AnchorStatus::Deleted)),
+        (_, Some(LineMap::Edited(line))) => (line, AnchorStatus::Changed),
+        // The hunk's lines are gone from an untouched region: the base guess was off
+        (Some(None), Some(LineMap::Shifted(line))) => (line, AnchorStatus::Changed),
+        (None, Some(LineMap::Shifted(line))) if line == original && path == thread.path => {
+            (line, AnchorStatus::Current)
+        }
+        (None, Some(LineMap::Shifted(line))) => (line, AnchorStatus::Moved),
+        // No history to go on
+        (Some(None), None) => return Some(anchor(&path, None, AnchorStatus::Deleted)),
+        (None, None) => (original, AnchorStatus::Current),
+    };
+
+    if line as usize > lines.len() {
+        return Some(anchor(&path, None, AnchorStatus::Deleted));
+    }
+    Some(anchor(&path, Some(line), status))
+}
+
+/// The file's path in the working tree, following renames since `base`;
+/// None if it was deleted
+fn renamed_path(workbox: &Path, base: &str, path: &str) -> Option<String> {
+    let changes = git::run(Some(workbox), &["diff", "-M", "--name-status", base]).ok()?;
+    for change in changes.lines() {
+        let fields: Vec<&str> = change.split('\t').collect();
+        match fields.as_slice() {
+            [status, old, new] if status.starts_with('R') && *old == path => {
+                return Some(new.to_string())
+            }
+            ["D", old] if *old == path => return None,
+            _ => {}
+        }
+    }
+    Some(path.to_string())
+}
+
+/// Where a line of the base version ended up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineMap {
+    /// Untouched, now at this line
+    Shifted(u32),
+    /// Part of a replaced region that starts at this line
+    Edited(u32),
+    /// Deleted; the code after it is now at this line
+    Removed(u32),
+}
+
+/// Map `line` through a `git diff -U0` of its file
+pub fn map_line(diff: &str, line: u32) -> LineMap {
+    let mut offset: i64 = 0;
+    for header in diff.lines().filter(|l| l.starts_with("@@ ")) {
+        let Some((old_start, old_len, new_start, new_len)) = parse_hunk_header(header) else {
+            continue;
+        };
+        // A zero-length range names the line before the change
+        let before_line = if old_len == 0 {
+            old_start < line
+        } else {
+            old_start + old_len <= line
+        };
+        if before_line {
+            offset += new_len as i64 - old_len as i64;
+            continue;
+        }
+        if old_len > 0 && old_start <= line {
+            return if new_len == 0 {
+                LineMap::Removed(new_start + 1)
+            } else {
+                LineMap::Edited(new_start + (line - old_start).min(new_len - 1))
+            };
+        }
+        break;
+    }
+    LineMap::Shifted((line as i64 + offset).max(1) as u32)
+}
+
+/// `@@ -a[,b] +c[,d] @@` as (a, b, c, d)
+fn parse_hunk_header(header: &str) -> Option<(u32, u32, u32, u32)> {
+    let mut ranges = header.split_whitespace().skip(1);
+    let range = |part: Option<&str>, sign: char| -> Option<(u32, u32)> {
+        let part = part?.strip_prefix(sign)?;
+        match part.split_once(',') {
+            Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+            None => Some((part.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_len) = range(ranges.next(), '-')?;
+    let (new_start, new_len) = range(ranges.next(), '+')?;
+    Some((old_start, old_len, new_start, new_len))
+}
+
+/// Lines of the hunk's new side; the last is the commented line
+pub fn hunk_lines(hunk: &str) -> Vec<String> {
+    hunk.lines()
+        .filter(|line| !line.starts_with("@@"))
+        .filter_map(|line| match line.chars().next() {
+            Some(' ') | Some('+') => Some(line[1..].to_string()),
+            None => Some(String::new()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Find the hunk's last lines in the file, nearest `hint`
+///
+/// Matches ignore indentation and trailing whitespace. Fewer lines are
+/// tried when the full tail isn't found, as long as they stay specific.
+/// Returns the 1-based line of the commented (last) line.
+pub fn locate(lines: &[&str], hunk: &[String], hint: u32) -> Option<u32> {
+    for len in (1..=hunk.len().min(HUNK_TAIL)).rev() {
+        let tail: Vec<&str> = hunk[hunk.len() - len..].iter().map(|l| l.trim()).collect();
+        if tail.iter().map(|l| l.len()).sum::<usize>() < MIN_MATCH_CHARS {
+            continue;
+        }
+        let best = lines
+            .windows(len)
+            .enumerate()
+            .filter(|(_, window)| window.iter().map(|l| l.trim()).eq(tail.iter().copied()))
+            .map(|(start, _)| (start + len) as u32)
+            .min_by_key(|&line| line.abs_diff(hint));
+        if best.is_some() {
+            return best;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ReviewComment;
+    use chrono::{Duration, Utc};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_map_line_and_locate() {
+        // Two lines added at the top, line 10 replaced, line 20 deleted
+        let diff = "diff --git a/f b/f\n\
+                    @@ -0,0 +1,2 @@\n+a\n+b\n\
+                    @@ -10 +12 @@\n-old\n+new\n\
+                    @@ -20 +21,0 @@\n-gone\n";
+        assert_eq!(map_line(diff, 5), LineMap::Shifted(7));
+        assert_eq!(map_line(diff, 10), LineMap::Edited(12));
+        assert_eq!(map_line(diff, 15), LineMap::Shifted(17));
+        assert_eq!(map_line(diff, 20), LineMap::Removed(22));
+        assert_eq!(map_line(diff, 30), LineMap::Shifted(31));
+        assert_eq!(map_line("", 4), LineMap::Shifted(4));
+
+        let hunk = "@@ -1,3 +1,4 @@\n fn main() {\n-    old();\n+    let x = compute();\n+    println!(\"{}\", x);";
+        assert_eq!(
+            hunk_lines(hunk),
+            vec![
+                "fn main() {",
+                "    let x = compute();",
+                "    println!(\"{}\", x);"
+            ]
+        );
+
+        let file = [
+            "    println!(\"{}\", x);",
+            "fn main() {",
+            "  let x = compute();",
+            "  println!(\"{}\", x);",
+            "}",
+        ];
+        assert_eq!(locate(&file, &hunk_lines(hunk), 1), Some(4));
+        // A lone brace is too generic to anchor on
+        assert_eq!(locate(&file, &["}".to_string()], 5), None);
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        git::run(Some(dir), args).unwrap();
+    }
+
+    #[test]
+    fn test_reanchor_follows_renames_and_flags_deleted_code() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        git(dir, &["init", "--quiet", "-b", "main"]);
+        git(dir, &["config", "user.email", "test@example.com"]);
+        git(dir, &["config", "user.name", "Test"]);
+        std::fs::write(
+            dir.join("old.rs"),
+            "fn one() {}\nfn two() {}\nfn target() -> u32 {\n    42\n}\n",
+        )
+        .unwrap();
+        git(dir, &["add", "-A"]);
+        git(dir, &["commit", "--quiet", "-m", "init"]);
+
+        let thread = ReviewThread {
+            id: "T1".to_string(),
+            path: "old.rs".to_string(),
+            line: Some(3),
+            start_line: None,
+            original_line: Some(3),
+            is_resolved: false,
+            is_outdated: false,
+            comments: vec![ReviewComment {
+                id: 1,
+                path: "old.rs".to_string(),
+                line: Some(3),
+                body: "Rename".to_string(),
+                author: "reviewer".to_string(),
+                created_at: Utc::now() + Duration::seconds(5),
+                updated_at: None,
+                diff_hunk: Some(
+                    "@@ -0,0 +1,3 @@\n+fn one() {}\n+fn two() {}\n+fn target() -> u32 {"
+                        .to_string(),
+                ),
+            }],
+            anchor: None,
+        };
+
+        // Lines inserted above, then the file renamed
+        std::fs::write(
+            dir.join("old.rs"),
+            "use std::fmt;\n\nfn one() {}\nfn two() {}\nfn target() -> u32 {\n    42\n}\n",
+        )
+        .unwrap();
+        git(dir, &["mv", "old.rs", "new.rs"]);
+        let mut threads = vec![thread];
+        let summary = reanchor_threads(dir, &mut threads).unwrap();
+        assert_eq!(summary.moved, 1);
+        let anchor = threads[0].anchor.clone().unwrap();
+        assert_eq!(anchor.path, "new.rs");
+        assert_eq!(anchor.line, Some(5));
+        assert_eq!(anchor.status, AnchorStatus::Moved);
+        assert_eq!(threads[0].location(), ("new.rs", Some(5)));
+
+        // The commented function removed
+        std::fs::write(
+            dir.join("new.rs"),
+            "use std::fmt;\n\nfn one() {}\nfn two() {}\n",
+        )
+        .unwrap();
+        let summary = reanchor_threads(dir, &mut threads).unwrap();
+        assert_eq!(summary.deleted, 1);
+        assert!(threads[0].is_outdated);
+        assert_eq!(threads[0].anchor.as_ref().unwrap().line, None);
+    }
+}
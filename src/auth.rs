@@ -0,0 +1,379 @@
+//! GitHub credentials
+//!
+//! A configured GitHub App is used first, so bot accounts never pick up a
+//! developer's token. Otherwise the token comes from the first of:
+//! `GITHUB_TOKEN` (or `GH_TOKEN`), `gh auth token`, the git credential
+//! helper and `hp.pr.github.token_file`.
+
+use crate::config::{GitHubAppConfig, GitHubConfig};
+use crate::error::{Error, Result};
+use crate::git::RemoteUrl;
+use crate::http::{self, ApiClient, ClientOptions};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Scopes a classic token needs, with the features that use them
+pub const REQUIRED_SCOPES: &[(&str, &str)] = &[
+    (
+        "repo",
+        "pushing, creating PRs, syncing and resolving review threads",
+    ),
+    ("read:org", "requesting team reviewers"),
+];
+
+/// Where a token was found
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenSource {
+    App { app_id: u64, installation_id: u64 },
+    Env(String),
+    GhCli,
+    CredentialHelper,
+    File(PathBuf),
+}
+
+impl fmt::Display for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenSource::App {
+                app_id,
+                installation_id,
+            } => write!(
+                f,
+                "GitHub App {} (installation {})",
+                app_id, installation_id
+            ),
+            TokenSource::Env(var) => write!(f, "${}", var),
+            TokenSource::GhCli => write!(f, "gh auth token"),
+            TokenSource::CredentialHelper => write!(f, "git credential helper"),
+            TokenSource::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GitHubToken {
+    pub token: String,
+    pub source: TokenSource,
+}
+
+/// Find a token for the repository at `remote`
+///
+/// `workbox` is where git runs, so repository-level credential helpers apply.
+pub async fn github_token(
+    github: &GitHubConfig,
+    remote: &RemoteUrl,
+    workbox: &Path,
+) -> Result<GitHubToken> {
+    if let Some(app) = &github.app {
+        return app_token(app, &github.api_url(), remote).await;
+    }
+
+    let found = ["GITHUB_TOKEN", "GH_TOKEN"]
+        .iter()
+        .find_map(|var| {
+            let token = std::env::var(var).ok().filter(|t| !t.trim().is_empty())?;
+            Some((token, TokenSource::Env(var.to_string())))
+        })
+        .or_else(|| gh_token(&github.host).map(|t| (t, TokenSource::GhCli)))
+        .or_else(|| {
+            credential_token(&github.host, workbox).map(|t| (t, TokenSource::CredentialHelper))
+        })
+        .or_else(|| {
+            let path = expand_home(github.token_file.as_ref()?);
+            let token = read_token_file(&path)?;
+            Some((token, TokenSource::File(path)))
+        });
+
+    match found {
+        Some((token, source)) => Ok(GitHubToken {
+            token: token.trim().to_string(),
+            source,
+        }),
+        None => Err(Error::Other(format!(
+            "No GitHub token for {}. Set GITHUB_TOKEN, run 'gh auth login', store one \
+             in a git credential helper or set hp.pr.github.token_file",
+            github.host
+        ))),
+    }
+}
+
+/// Token from the GitHub CLI, if it is installed and logged in
+fn gh_token(host: &str) -> Option<String> {
+    which::which("gh").ok()?;
+    let output = Command::new("gh")
+        .args(["auth", "token", "--hostname", host])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !token.is_empty()).then_some(token)
+}
+
+/// Password stored for `https://<host>` by the git credential helper
+fn credential_token(host: &str, workbox: &Path) -> Option<String> {
+    let mut child = Command::new("git")
+        .args(["credential", "fill"])
+        .current_dir(workbox)
+        // Never prompt: a missing credential just moves on to the next source
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_ASKPASS", "true")
+        .env("SSH_ASKPASS", "true")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    child
+        .stdin
+        .take()?
+        .write_all(format!("protocol=https\nhost={}\n\n", host).as_bytes())
+        .ok()?;
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_credential(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// The `password` of `git credential fill` output
+fn parse_credential(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("password="))
+        .map(str::to_string)
+        .filter(|p| !p.is_empty())
+}
+
+/// First non-comment line of a token file
+fn read_token_file(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()?
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[derive(Serialize)]
+struct AppClaims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+#[derive(Deserialize)]
+struct Installation {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct InstallationToken {
+    token: String,
+}
+
+/// Installation token for a GitHub App, valid for an hour
+///
+/// The installation is looked up from the repository unless configured.
+async fn app_token(
+    app: &GitHubAppConfig,
+    api_url: &str,
+    remote: &RemoteUrl,
+) -> Result<GitHubToken> {
+    let key = std::fs::read(expand_home(&app.private_key_path)).map_err(|e| {
+        Error::Other(format!(
+            "Failed to read GitHub App key {}: {}",
+            app.private_key_path.display(),
+            e
+        ))
+    })?;
+    let key = jsonwebtoken::EncodingKey::from_rsa_pem(&key)
+        .map_err(|e| Error::Other(format!("Invalid GitHub App private key: {}", e)))?;
+
+    // Backdated to allow for clock drift; GitHub caps the lifetime at 10 minutes
+    let now = chrono::Utc::now().timestamp();
+    let claims = AppClaims {
+        iat: now - 60,
+        exp: now + 540,
+        iss: app.app_id.to_string(),
+    };
+    let jwt = jsonwebtoken::encode(
+        &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+        &claims,
+        &key,
+    )
+    .map_err(|e| Error::Other(format!("Failed to sign GitHub App token: {}", e)))?;
+
+    let client = ApiClient::new(
+        "GitHub",
+        api_url,
+        "Authorization",
+        format!("Bearer {}", jwt),
+        &ClientOptions::default(),
+    );
+    let installation_id = match app.installation_id {
+        Some(id) => id,
+        None => {
+            let route = format!("/repos/{}/{}/installation", remote.owner, remote.repo);
+            let installation: Installation = client.get(&route).await.map_err(http::context(
+                "GitHub App is not installed on the repository",
+            ))?;
+            installation.id
+        }
+    };
+
+    let route = format!("/app/installations/{}/access_tokens", installation_id);
+    let token: InstallationToken = client
+        .request(reqwest::Method::POST, &route, None)
+        .await
+        .map_err(http::context("GitHub App authentication failed"))?;
+
+    Ok(GitHubToken {
+        token: token.token,
+        source: TokenSource::App {
+            app_id: app.app_id,
+            installation_id,
+        },
+    })
+}
+
+/// Account behind a token and the scopes it carries
+#[derive(Debug, Clone)]
+pub struct TokenInfo {
+    pub login: String,
+    /// None for fine-grained tokens, which don't report scopes
+    pub scopes: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+/// Look up the token's user and its OAuth scopes
+pub async fn token_info(api_url: &str, token: &str) -> Result<TokenInfo> {
+    let response = reqwest::Client::new()
+        .get(format!("{}/user", api_url))
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "hupasiya")
+        .send()
+        .await
+        .map_err(|e| Error::Other(format!("Failed to reach GitHub: {}", e)))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Error::Other(format!(
+            "GitHub rejected the token ({})",
+            status
+        )));
+    }
+
+    let scopes = response
+        .headers()
+        .get("x-oauth-scopes")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| {
+            v.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        });
+    let user: User = response
+        .json()
+        .await
+        .map_err(|e| Error::Other(format!("Unexpected GitHub response: {}", e)))?;
+
+    Ok(TokenInfo {
+        login: user.login,
+        scopes,
+    })
+}
+
+/// Required scopes the token lacks, with the features that need them
+pub fn missing_scopes(scopes: &[String]) -> Vec<(&'static str, &'static str)> {
+    // Broader scopes that include a required one
+    let implied_by = |scope: &str| -> &[&str] {
+        match scope {
+            "read:org" => &["write:org", "admin:org"],
+            _ => &[],
+        }
+    };
+    REQUIRED_SCOPES
+        .iter()
+        .filter(|(scope, _)| {
+            !scopes
+                .iter()
+                .any(|s| s == scope || implied_by(scope).contains(&s.as_str()))
+        })
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_credential_and_token_file() {
+        let output = "protocol=https\nhost=github.com\nusername=me\npassword=ghp_abc\n";
+        assert_eq!(parse_credential(output).unwrap(), "ghp_abc");
+        assert!(parse_credential("protocol=https\npassword=\n").is_none());
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "# hp bot\n\n  ghp_file  \n").unwrap();
+        assert_eq!(read_token_file(&path).unwrap(), "ghp_file");
+        assert!(read_token_file(&dir.path().join("missing")).is_none());
+    }
+
+    #[test]
+    fn test_missing_scopes() {
+        let scopes = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(missing_scopes(&scopes(&["repo", "read:org"])).is_empty());
+        assert!(missing_scopes(&scopes(&["repo", "admin:org", "gist"])).is_empty());
+
+        let missing = missing_scopes(&scopes(&["public_repo"]));
+        let names: Vec<&str> = missing.iter().map(|(scope, _)| *scope).collect();
+        assert_eq!(names, vec!["repo", "read:org"]);
+    }
+
+    #[tokio::test]
+    async fn test_token_info_reads_scopes() {
+        let mut server = mockito::Server::new_async().await;
+        let classic = server
+            .mock("GET", "/user")
+            .match_header("authorization", "Bearer classic")
+            .with_header("x-oauth-scopes", "repo, read:org")
+            .with_body(r#"{"login": "octocat"}"#)
+            .create_async()
+            .await;
+        let fine_grained = server
+            .mock("GET", "/user")
+            .match_header("authorization", "Bearer fine")
+            .with_body(r#"{"login": "octocat"}"#)
+            .create_async()
+            .await;
+
+        let info = token_info(&server.url(), "classic").await.unwrap();
+        assert_eq!(info.login, "octocat");
+        assert_eq!(info.scopes.unwrap(), vec!["repo", "read:org"]);
+        assert!(token_info(&server.url(), "fine")
+            .await
+            .unwrap()
+            .scopes
+            .is_none());
+
+        classic.assert_async().await;
+        fine_grained.assert_async().await;
+    }
+}
@@ -1,22 +1,30 @@
 //! CLI commands implementation
 
 use crate::activity::ActivityManager;
-use crate::ai_tool::AiTool;
+use crate::adapters;
+use crate::ai_tool::{AiTool, RunOptions};
+use crate::auth;
 use crate::collaboration::CollaborationManager;
-use crate::config::Config;
-use crate::context::ContextManager;
+use crate::config::{Config, ForgeKind};
+use crate::context::{ContextManager, SyncOptions};
 use crate::error::{Error, Result};
+use crate::forge;
+use crate::git;
 use crate::hn_client::{HnClient, WorkboxOptions};
 use crate::models::{AgentType, SessionStatus, SnapshotTrigger};
 use crate::orchestration::Orchestrator;
 use crate::pr::PrManager;
+use crate::processes::ProcessManager;
 use crate::profiles::ProfileManager;
 use crate::session::SessionManager;
-use crate::shepherd::Shepherd;
+use crate::shepherd::{Shepherd, StatusFormat};
 use crate::templates::TemplateManager;
 use crate::utilities::UtilitiesManager;
+use crate::workspace::WorkspaceManager;
 use colored::Colorize;
-use std::io::{self, Write};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
 
 /// Execute the 'new' command
 pub fn cmd_new(
@@ -229,7 +237,7 @@ pub fn cmd_switch(name: &str, output_shell: bool) -> Result<()> {
         println!("export HP_SESSION={}", session.name);
         println!(
             "export HP_CONTEXT={}",
-            session.context_dir.join("context.md").display()
+            adapters::absolute(&session.context_dir.join("context.md"))?.display()
         );
         println!("export HP_WORKBOX={}", session.workbox_path.display());
         println!("export HP_VCS={}", session.vcs_type);
@@ -273,6 +281,21 @@ pub fn cmd_context_view(session_name: Option<String>) -> Result<()> {
     Ok(())
 }
 
+/// Execute the 'context render' command
+pub fn cmd_context_render(session_name: Option<String>) -> Result<()> {
+    let config = Config::load()?;
+    let session_mgr = SessionManager::new(config.clone())?;
+    let context_mgr = ContextManager::new(config)?;
+
+    let name = get_session_name(session_name)?;
+    let session = session_mgr.load_session(&name)?;
+
+    let content = context_mgr.render_context(&session)?;
+    println!("{}", content);
+
+    Ok(())
+}
+
 /// Execute the 'context edit' command
 pub fn cmd_context_edit(session_name: Option<String>) -> Result<()> {
     let config = Config::load()?;
@@ -372,7 +395,12 @@ pub fn cmd_context_snapshot(
 }
 
 /// Execute the 'context sync' command
-pub fn cmd_context_sync(from_session: &str, to_session: &str) -> Result<()> {
+pub fn cmd_context_sync(
+    from_session: &str,
+    to_session: &str,
+    sections: Option<Vec<String>>,
+    interactive: bool,
+) -> Result<()> {
     let config = Config::load()?;
     let session_mgr = SessionManager::new(config.clone())?;
     let context_mgr = ContextManager::new(config)?;
@@ -380,7 +408,18 @@ pub fn cmd_context_sync(from_session: &str, to_session: &str) -> Result<()> {
     let from = session_mgr.load_session(from_session)?;
     let to = session_mgr.load_session(to_session)?;
 
-    context_mgr.sync_context(&from, &to)?;
+    let opts = SyncOptions {
+        sections,
+        interactive,
+    };
+    let report = context_mgr.sync_context(&from, &to, &opts)?;
+
+    if !report.had_base {
+        println!(
+            "{} No common snapshot found; sections changed on both sides will conflict",
+            "⚠".yellow()
+        );
+    }
 
     println!(
         "{} Synced context from '{}' to '{}'",
@@ -388,6 +427,27 @@ pub fn cmd_context_sync(from_session: &str, to_session: &str) -> Result<()> {
         from_session.bold(),
         to_session.bold()
     );
+    for title in &report.updated {
+        println!("  {} {}", "↓".cyan(), title);
+    }
+
+    if !report.conflicts.is_empty() {
+        println!();
+        println!(
+            "{} {} section(s) changed on both sides:",
+            "⚠".yellow(),
+            report.conflicts.len()
+        );
+        for title in &report.conflicts {
+            println!("  {} {}", "!".yellow(), title);
+        }
+        if !interactive {
+            println!(
+                "  Resolve the conflict markers with: hp context edit {}",
+                to_session
+            );
+        }
+    }
 
     Ok(())
 }
@@ -459,6 +519,13 @@ pub fn cmd_doctor() -> Result<()> {
         }
     }
 
+    // Check GitHub authentication
+    if let Ok(config) = Config::load() {
+        print!("Checking GitHub authentication... ");
+        io::stdout().flush()?;
+        all_ok &= check_github_auth(&config);
+    }
+
     // Check directories
     print!("Checking directories... ");
     io::stdout().flush()?;
@@ -485,6 +552,89 @@ pub fn cmd_doctor() -> Result<()> {
     Ok(())
 }
 
+/// Doctor check: a token is found and carries the scopes PR features need
+fn check_github_auth(config: &Config) -> bool {
+    let pr = config.hp.pr.clone().unwrap_or_default();
+    let repo = std::path::Path::new(".");
+    let remote = match git::remote_url(repo, &pr.github.remote)
+        .and_then(|url| git::parse_remote_url(&url))
+    {
+        Ok(remote) if forge::detect(&pr, &remote.host) == ForgeKind::Github => remote,
+        Ok(_) => {
+            println!("{}", "SKIPPED".dimmed());
+            println!("  Repository is not on GitHub");
+            return true;
+        }
+        Err(_) => {
+            println!("{}", "SKIPPED".dimmed());
+            println!("  No '{}' remote", pr.github.remote);
+            return true;
+        }
+    };
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            println!("{}", "FAIL".red());
+            println!("  Error: {}", e);
+            return false;
+        }
+    };
+    let token = match runtime.block_on(auth::github_token(&pr.github, &remote, repo)) {
+        Ok(token) => token,
+        Err(e) => {
+            println!("{}", "FAIL".red());
+            println!("  Error: {}", e);
+            return false;
+        }
+    };
+
+    if let auth::TokenSource::App { .. } = token.source {
+        println!("{}", "OK".green());
+        println!("  Source: {}", token.source);
+        println!("  The installation needs Pull requests and Contents read & write");
+        return true;
+    }
+
+    let info = match runtime.block_on(auth::token_info(&pr.github.api_url(), &token.token)) {
+        Ok(info) => info,
+        Err(e) => {
+            println!("{}", "FAIL".red());
+            println!("  Source: {}", token.source);
+            println!("  Error: {}", e);
+            return false;
+        }
+    };
+    let missing = info
+        .scopes
+        .as_deref()
+        .map(auth::missing_scopes)
+        .unwrap_or_default();
+    let ok = !missing.iter().any(|(scope, _)| *scope == "repo");
+
+    println!("{}", if ok { "OK".green() } else { "FAIL".red() });
+    println!("  Source: {}", token.source);
+    println!("  User: {}", info.login);
+    match &info.scopes {
+        Some(scopes) => {
+            println!("  Scopes: {}", scopes.join(", "));
+            for (scope, feature) in &missing {
+                println!(
+                    "  {} Missing '{}', needed for {}",
+                    "⚠".yellow(),
+                    scope,
+                    feature
+                );
+            }
+        }
+        None => println!(
+            "  Fine-grained token: it needs Pull requests and Contents read & write, \
+             and Members read for team reviewers"
+        ),
+    }
+    ok
+}
+
 /// Execute the 'version' command
 pub fn cmd_version() -> Result<()> {
     println!("hupasiya (hp) v{}", env!("CARGO_PKG_VERSION"));
@@ -602,6 +752,16 @@ pub fn cmd_cascade(parent_name: &str, dry_run: bool) -> Result<()> {
 
     orchestrator.cascade(parent_name, dry_run)?;
 
+    // Keep stacked PRs pointing at the right branches
+    if !dry_run {
+        let pr_mgr = PrManager::new(Config::load()?)?;
+        let runtime = tokio::runtime::Runtime::new()
+            .map_err(|e| Error::Other(format!("Failed to create async runtime: {}", e)))?;
+        if let Err(e) = runtime.block_on(pr_mgr.restack(parent_name, true)) {
+            eprintln!("{} Stacked PRs not updated: {}", "⚠".yellow(), e);
+        }
+    }
+
     Ok(())
 }
 
@@ -632,10 +792,84 @@ pub fn cmd_launch(
     profile: Option<String>,
     extra_args: Vec<String>,
 ) -> Result<()> {
+    let config = Config::load()?;
+    let ai_tool = AiTool::new(config.clone())?;
+
+    ai_tool.launch(session_name.clone(), tool, profile, extra_args)?;
+    auto_create_pr(&config, session_name);
+
+    Ok(())
+}
+
+/// Execute the 'run' command
+pub fn cmd_run(
+    session_name: &str,
+    prompt_file: &Path,
+    tool: Option<String>,
+    profile: Option<String>,
+    timeout: Option<u64>,
+    quiet: bool,
+) -> Result<()> {
+    let prompt = if prompt_file == Path::new("-") {
+        let mut prompt = String::new();
+        io::stdin().read_to_string(&mut prompt)?;
+        prompt
+    } else {
+        fs::read_to_string(prompt_file)
+            .map_err(|e| Error::Other(format!("Failed to read {}: {}", prompt_file.display(), e)))?
+    };
+
     let config = Config::load()?;
     let ai_tool = AiTool::new(config)?;
+    let options = RunOptions {
+        tool,
+        profile,
+        timeout_secs: timeout,
+        echo: !quiet,
+    };
+    let record = ai_tool.run(session_name, &prompt, &options)?;
+
+    if !record.success() {
+        return Err(Error::AiToolFailed(format!(
+            "{} {} (output in {})",
+            record.command,
+            record.describe(),
+            record.run_dir.display()
+        )));
+    }
 
-    ai_tool.launch(session_name, tool, profile, extra_args)?;
+    println!("{} {} {}", "✓".green(), record.command, record.describe());
+    println!("  Output: {}", record.run_dir.display());
+
+    Ok(())
+}
+
+/// Execute the 'ps' command
+pub fn cmd_ps(session_name: Option<&str>) -> Result<()> {
+    let config = Config::load()?;
+    let process_mgr = ProcessManager::new(config)?;
+
+    process_mgr.show(session_name)?;
+
+    Ok(())
+}
+
+/// Execute the 'stop' command
+pub fn cmd_stop(session_name: &str) -> Result<()> {
+    let config = Config::load()?;
+    let process_mgr = ProcessManager::new(config)?;
+
+    process_mgr.stop(session_name)?;
+
+    Ok(())
+}
+
+/// Execute the hidden 'agent-exited' command
+pub fn cmd_agent_exited(id: &str, code: Option<i32>) -> Result<()> {
+    let config = Config::load()?;
+    let process_mgr = ProcessManager::new(config)?;
+
+    process_mgr.finish(id, code)?;
 
     Ok(())
 }
@@ -643,9 +877,10 @@ pub fn cmd_launch(
 /// Execute the 'shell' command
 pub fn cmd_shell(session_name: Option<String>, command: Option<Vec<String>>) -> Result<()> {
     let config = Config::load()?;
-    let ai_tool = AiTool::new(config)?;
+    let ai_tool = AiTool::new(config.clone())?;
 
-    ai_tool.shell(session_name, command)?;
+    ai_tool.shell(session_name.clone(), command)?;
+    auto_create_pr(&config, session_name);
 
     Ok(())
 }
@@ -658,13 +893,34 @@ pub fn cmd_exec(
     tree: bool,
 ) -> Result<()> {
     let config = Config::load()?;
-    let ai_tool = AiTool::new(config)?;
+    let ai_tool = AiTool::new(config.clone())?;
 
     ai_tool.exec(&session_name, command, cascade, tree)?;
+    auto_create_pr(&config, Some(session_name));
 
     Ok(())
 }
 
+/// Open a draft PR if the session's branch was pushed while it ran
+/// (`hp.pr.auto_create`)
+fn auto_create_pr(config: &Config, session_name: Option<String>) {
+    if !config.hp.pr.as_ref().is_some_and(|pr| pr.auto_create) {
+        return;
+    }
+    let Some(session_name) = session_name.or_else(|| std::env::var("HP_SESSION").ok()) else {
+        return;
+    };
+
+    let result = PrManager::new(config.clone()).and_then(|pr_mgr| {
+        let runtime = tokio::runtime::Runtime::new()
+            .map_err(|e| Error::Other(format!("Failed to create async runtime: {}", e)))?;
+        runtime.block_on(pr_mgr.auto_create(&session_name))
+    });
+    if let Err(e) = result {
+        eprintln!("{} Could not open a PR automatically: {}", "⚠".yellow(), e);
+    }
+}
+
 // === PR Commands ===
 
 /// Execute 'pr create' command
@@ -673,14 +929,66 @@ pub fn cmd_pr_create(
     draft: bool,
     reviewers: Option<Vec<String>>,
     labels: Option<Vec<String>>,
-    from_context: bool,
+    stack: bool,
 ) -> Result<()> {
     let config = Config::load()?;
     let pr_mgr = PrManager::new(config)?;
 
     let runtime = tokio::runtime::Runtime::new()
         .map_err(|e| Error::Other(format!("Failed to create async runtime: {}", e)))?;
-    runtime.block_on(pr_mgr.create_pr(session_name, draft, reviewers, labels, from_context))?;
+    if stack {
+        runtime.block_on(pr_mgr.create_stack(session_name, draft, reviewers, labels))?;
+    } else {
+        runtime.block_on(pr_mgr.create_pr(session_name, draft, reviewers, labels))?;
+    }
+
+    Ok(())
+}
+
+/// Execute 'pr restack' command
+pub fn cmd_pr_restack(session_name: &str, push: bool) -> Result<()> {
+    let config = Config::load()?;
+    let pr_mgr = PrManager::new(config)?;
+
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| Error::Other(format!("Failed to create async runtime: {}", e)))?;
+    runtime.block_on(pr_mgr.restack(session_name, push))?;
+
+    Ok(())
+}
+
+/// Execute 'pr update' command
+pub fn cmd_pr_update(session_name: &str, title: bool) -> Result<()> {
+    let config = Config::load()?;
+    let pr_mgr = PrManager::new(config)?;
+
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| Error::Other(format!("Failed to create async runtime: {}", e)))?;
+    runtime.block_on(pr_mgr.update_pr(session_name, title))?;
+
+    Ok(())
+}
+
+/// Execute 'pr label' command
+pub fn cmd_pr_label(session_name: &str, labels: Vec<String>) -> Result<()> {
+    let config = Config::load()?;
+    let pr_mgr = PrManager::new(config)?;
+
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| Error::Other(format!("Failed to create async runtime: {}", e)))?;
+    runtime.block_on(pr_mgr.add_labels(session_name, labels))?;
+
+    Ok(())
+}
+
+/// Execute 'pr flush' command
+pub fn cmd_pr_flush(session_name: Option<&str>) -> Result<()> {
+    let config = Config::load()?;
+    let pr_mgr = PrManager::new(config)?;
+
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| Error::Other(format!("Failed to create async runtime: {}", e)))?;
+    runtime.block_on(pr_mgr.flush(session_name))?;
 
     Ok(())
 }
@@ -698,13 +1006,13 @@ pub fn cmd_pr_sync(session_name: &str, create_shepherd: bool) -> Result<()> {
 }
 
 /// Execute 'pr status' command
-pub fn cmd_pr_status(session_name: &str) -> Result<()> {
+pub fn cmd_pr_status(session_name: &str, integrate: bool) -> Result<()> {
     let config = Config::load()?;
     let pr_mgr = PrManager::new(config)?;
 
     let runtime = tokio::runtime::Runtime::new()
         .map_err(|e| Error::Other(format!("Failed to create async runtime: {}", e)))?;
-    runtime.block_on(pr_mgr.pr_status(session_name))?;
+    runtime.block_on(pr_mgr.pr_status(session_name, integrate))?;
 
     Ok(())
 }
@@ -716,30 +1024,82 @@ pub fn cmd_shepherd(
     session_name: Option<String>,
     batch: bool,
     auto_fix: bool,
-    _status: bool,
+    retriage: bool,
+    apply: bool,
+    post: bool,
+    dry_run: bool,
 ) -> Result<()> {
     let config = Config::load()?;
+    let auto_post = config
+        .hp
+        .pr
+        .as_ref()
+        .is_some_and(|pr| pr.shepherd.auto_post_responses);
     let shepherd = Shepherd::new(config)?;
 
     let session_name = get_session_name(session_name)?;
 
+    // `--apply`/`--post`/`--dry-run` alone act on previously recorded analyses
     if batch {
-        shepherd.run_batch(&session_name, auto_fix)?;
-    } else {
-        shepherd.run_interactive(&session_name)?;
+        shepherd.run_batch(&session_name, auto_fix || apply, retriage)?;
+    } else if apply {
+        shepherd.apply_fixes(&session_name)?;
+    } else if !(post || dry_run) {
+        shepherd.run_interactive(&session_name, retriage)?;
     }
 
+    if post || dry_run || auto_post {
+        shepherd.post_responses(&session_name, dry_run)?;
+    }
+
+    Ok(())
+}
+
+/// Execute 'shepherd status' command
+pub fn cmd_shepherd_status(session_name: Option<String>, format: StatusFormat) -> Result<()> {
+    let config = Config::load()?;
+    let shepherd = Shepherd::new(config)?;
+
+    let session_name = get_session_name(session_name)?;
+    shepherd.show_status(&session_name, format)?;
+
+    Ok(())
+}
+
+/// Execute 'shepherd --spawn' command
+pub fn cmd_shepherd_spawn(session_name: Option<String>, child_name: Option<String>) -> Result<()> {
+    let config = Config::load()?;
+    let shepherd = Shepherd::new(config)?;
+
+    let session_name = get_session_name(session_name)?;
+    shepherd.spawn(&session_name, child_name.as_deref())?;
+
+    Ok(())
+}
+
+/// Execute 'shepherd --gather' command
+pub fn cmd_shepherd_gather(session_name: Option<String>) -> Result<()> {
+    let config = Config::load()?;
+    let shepherd = Shepherd::new(config)?;
+
+    let session_name = get_session_name(session_name)?;
+    shepherd.gather_spawned(&session_name)?;
+
     Ok(())
 }
 
 // === Activity Commands ===
 
 /// Execute 'activity show' command
-pub fn cmd_activity(session_name: &str, limit: Option<usize>) -> Result<()> {
+pub fn cmd_activity(session_name: &str, limit: Option<usize>, follow: bool) -> Result<()> {
     let config = Config::load()?;
     let activity_mgr = ActivityManager::new(config)?;
 
-    activity_mgr.show_activity(session_name, limit)?;
+    if follow {
+        activity_mgr.follow_activity(session_name, limit)?;
+    } else {
+        activity_mgr.show_activity(session_name, limit)?;
+    }
 
     Ok(())
 }
@@ -796,6 +1156,36 @@ pub fn cmd_template_install(source: &str, name: Option<String>) -> Result<()> {
     Ok(())
 }
 
+/// Execute 'template update' command
+pub fn cmd_template_update(name: Option<String>) -> Result<()> {
+    let config = Config::load()?;
+    let template_mgr = TemplateManager::new(config)?;
+
+    template_mgr.update(name.as_deref())?;
+
+    Ok(())
+}
+
+/// Execute 'template outdated' command
+pub fn cmd_template_outdated() -> Result<()> {
+    let config = Config::load()?;
+    let template_mgr = TemplateManager::new(config)?;
+
+    template_mgr.outdated()?;
+
+    Ok(())
+}
+
+/// Execute 'template sync' command
+pub fn cmd_template_sync(source: Option<String>, locked: bool) -> Result<()> {
+    let config = Config::load()?;
+    let template_mgr = TemplateManager::new(config)?;
+
+    template_mgr.sync(source.as_deref(), locked)?;
+
+    Ok(())
+}
+
 // === Collaboration Commands ===
 
 /// Execute 'collab handoff' command
@@ -862,6 +1252,26 @@ pub fn cmd_profile_use(profile_name: &str) -> Result<()> {
 
 // === Utility Commands ===
 
+/// Execute 'workspace up' command
+pub fn cmd_workspace_up(root: &str, detach: bool) -> Result<()> {
+    let config = Config::load()?;
+    let workspace_mgr = WorkspaceManager::new(config)?;
+
+    workspace_mgr.up(root, detach)?;
+
+    Ok(())
+}
+
+/// Execute 'workspace down' command
+pub fn cmd_workspace_down(root: &str) -> Result<()> {
+    let config = Config::load()?;
+    let workspace_mgr = WorkspaceManager::new(config)?;
+
+    workspace_mgr.down(root)?;
+
+    Ok(())
+}
+
 /// Execute 'util monitor' command
 pub fn cmd_monitor(watch: bool) -> Result<()> {
     let config = Config::load()?;
@@ -0,0 +1,168 @@
+//! CODEOWNERS parsing for reviewer suggestions
+//!
+//! Follows the GitHub/GitLab format: one `<pattern> <owner>...` rule per
+//! line, gitignore-style patterns, and the last matching rule wins. Owners
+//! are `@user`, `@org/team` or email addresses; emails are skipped since
+//! neither forge accepts them as reviewers.
+
+use std::path::Path;
+
+/// Locations checked for a CODEOWNERS file, in order
+pub const CODEOWNERS_PATHS: &[&str] = &[
+    ".github/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+    ".gitlab/CODEOWNERS",
+];
+
+/// Parsed CODEOWNERS rules
+#[derive(Debug, Clone, Default)]
+pub struct CodeOwners {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: String,
+    owners: Vec<String>,
+}
+
+impl CodeOwners {
+    /// Load the first CODEOWNERS file found in the workbox
+    pub fn load(workbox: &Path) -> Option<Self> {
+        CODEOWNERS_PATHS
+            .iter()
+            .find_map(|path| std::fs::read_to_string(workbox.join(path)).ok())
+            .map(|content| Self::parse(&content))
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(str::trim)
+            // GitLab section headers (`[Docs]`) and comments
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('['))
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let pattern = parts.next()?.to_string();
+                let owners = parts
+                    .take_while(|owner| !owner.starts_with('#'))
+                    .filter_map(|owner| owner.strip_prefix('@'))
+                    .map(str::to_string)
+                    .collect();
+                Some(Rule { pattern, owners })
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Owners of a repository-relative path; empty if no rule matches
+    pub fn owners_for(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| pattern_matches(&rule.pattern, path))
+            .map(|rule| rule.owners.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Owners of any of `paths`, in first-seen order
+    pub fn suggest<S: AsRef<str>>(&self, paths: &[S]) -> Vec<String> {
+        let mut owners: Vec<String> = Vec::new();
+        for path in paths {
+            for owner in self.owners_for(path.as_ref()) {
+                if !owners.contains(owner) {
+                    owners.push(owner.clone());
+                }
+            }
+        }
+        owners
+    }
+}
+
+/// Whether a gitignore-style pattern matches a file path
+///
+/// A pattern matching a directory matches everything beneath it. Patterns
+/// without an inner slash match at any depth.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let dir_only = pattern.ends_with('/');
+    let trimmed = pattern.trim_end_matches('/');
+    let anchored = trimmed.contains('/');
+    let trimmed = trimmed.trim_start_matches('/');
+    let pattern = if anchored {
+        trimmed.to_string()
+    } else {
+        format!("**/{}", trimmed)
+    };
+
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    (1..=segments.len())
+        .filter(|&end| !(dir_only && end == segments.len()))
+        .any(|end| glob_match(pattern.as_bytes(), segments[..end].join("/").as_bytes()))
+}
+
+/// `*` and `?` stop at `/`; `**` crosses directories and `**/` may match
+/// nothing
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            glob_match(rest, text)
+                || text
+                    .iter()
+                    .enumerate()
+                    .any(|(i, &c)| c == b'/' && glob_match(rest, &text[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        [b'*', rest @ ..] => {
+            let segment_end = text.iter().position(|&c| c == b'/').unwrap_or(text.len());
+            (0..=segment_end).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'?', rest @ ..] => {
+            matches!(text.first(), Some(&c) if c != b'/') && glob_match(rest, &text[1..])
+        }
+        [p, rest @ ..] => text.first() == Some(p) && glob_match(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("*", "src/main.rs"));
+        assert!(pattern_matches("*.rs", "src/main.rs"));
+        assert!(!pattern_matches("*.rs", "README.md"));
+        assert!(pattern_matches("/docs/", "docs/guide/intro.md"));
+        assert!(!pattern_matches("/docs/", "src/docs/intro.md"));
+        assert!(pattern_matches("docs/", "src/docs/intro.md"));
+        assert!(!pattern_matches("docs/", "docs"));
+        assert!(pattern_matches("src/*.rs", "src/lib.rs"));
+        assert!(!pattern_matches("src/*.rs", "src/forge/mod.rs"));
+        assert!(pattern_matches("src/**/mod.rs", "src/forge/mod.rs"));
+        assert!(pattern_matches("src/**/mod.rs", "src/mod.rs"));
+        assert!(pattern_matches("Cargo.toml", "crates/core/Cargo.toml"));
+        assert!(pattern_matches("/spec", "spec/commands.md"));
+    }
+
+    #[test]
+    fn test_last_matching_rule_wins() {
+        let owners = CodeOwners::parse(
+            "# Default owners\n\
+             *       @alice\n\
+             \n\
+             [Backend]\n\
+             src/    @acme/backend dev@example.com # core\n\
+             src/pr.rs @bob\n",
+        );
+
+        assert_eq!(owners.owners_for("README.md"), ["alice"]);
+        assert_eq!(owners.owners_for("src/forge.rs"), ["acme/backend"]);
+        assert_eq!(owners.owners_for("src/pr.rs"), ["bob"]);
+        assert_eq!(
+            owners.suggest(&["src/pr.rs", "src/git.rs", "Cargo.toml", "src/forge.rs"]),
+            vec!["bob", "acme/backend", "alice"]
+        );
+    }
+}
@@ -1,5 +1,6 @@
 //! Configuration management for hupasiya
 
+use crate::adapters;
 use crate::error::Result;
 use crate::models::AgentType;
 use serde::{Deserialize, Serialize};
@@ -175,6 +176,10 @@ pub struct SessionConfig {
     #[serde(default = "default_context_dir")]
     pub context_dir: PathBuf,
 
+    /// Where to record running agent processes
+    #[serde(default = "default_processes_dir")]
+    pub processes_dir: PathBuf,
+
     /// Auto-sync with parent on commit
     #[serde(default)]
     pub auto_sync: bool,
@@ -189,6 +194,7 @@ impl Default for SessionConfig {
         Self {
             metadata_dir: default_metadata_dir(),
             context_dir: default_context_dir(),
+            processes_dir: default_processes_dir(),
             auto_sync: false,
             auto_snapshot: false,
         }
@@ -203,6 +209,10 @@ fn default_context_dir() -> PathBuf {
     PathBuf::from(".hp/contexts")
 }
 
+fn default_processes_dir() -> PathBuf {
+    PathBuf::from(".hp/processes")
+}
+
 /// AI tool configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AiToolConfig {
@@ -225,6 +235,15 @@ pub struct AiToolConfig {
     /// Environment variables
     #[serde(default)]
     pub env: HashMap<String, String>,
+
+    /// Argument templates for headless runs, keyed by tool command name;
+    /// `{prompt}` and `{prompt_file}` are replaced
+    #[serde(default)]
+    pub headless_args: HashMap<String, Vec<String>>,
+
+    /// Seconds before a headless run is stopped
+    #[serde(default = "default_run_timeout")]
+    pub timeout_secs: u64,
 }
 
 impl Default for AiToolConfig {
@@ -235,14 +254,38 @@ impl Default for AiToolConfig {
             context_strategy: ContextStrategy::SlashCommand,
             extra_args: Vec::new(),
             env: HashMap::new(),
+            headless_args: HashMap::new(),
+            timeout_secs: default_run_timeout(),
         }
     }
 }
 
+impl AiToolConfig {
+    /// Headless argument template for `tool`, falling back to the built-in
+    /// templates for known tools. `None` means the prompt goes on stdin.
+    pub fn headless_template(&self, tool: &str) -> Option<Vec<String>> {
+        let name = Path::new(tool)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(tool);
+        if let Some(args) = self.headless_args.get(name) {
+            return Some(args.clone());
+        }
+
+        adapters::for_command(tool)
+            .headless_args
+            .map(|args| args.iter().map(|a| a.to_string()).collect())
+    }
+}
+
 fn default_ai_command() -> String {
     "claude-code".to_string()
 }
 
+fn default_run_timeout() -> u64 {
+    1800
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum LaunchMethod {
@@ -316,13 +359,102 @@ pub enum ConflictStrategy {
 /// PR configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PrConfig {
+    /// Forge hosting the repository; inferred from the remote host when unset
+    #[serde(default)]
+    pub forge: Option<ForgeKind>,
+
+    #[serde(default)]
     pub github: GitHubConfig,
 
+    #[serde(default)]
+    pub gitlab: GitLabConfig,
+
+    /// PR title template, e.g. `{{agent_type}}: {{summary}}`
+    #[serde(default)]
+    pub title_template: Option<String>,
+
+    /// File with a PR body template using `{{name}}` placeholders; the
+    /// repository's pull request template is filled in when unset
+    #[serde(default)]
+    pub body_template: Option<PathBuf>,
+
+    /// Open a draft PR the first time a session's branch is pushed
     #[serde(default)]
     pub auto_create: bool,
 
+    /// Request the CODEOWNERS of the changed files as reviewers; they are
+    /// only suggested otherwise
+    #[serde(default)]
+    pub request_codeowners: bool,
+
     #[serde(default)]
     pub shepherd: ShepherdConfig,
+
+    /// Retries for transient forge API failures
+    #[serde(default)]
+    pub retry: RetryConfig,
+
+    /// Cached API responses, revalidated with ETags
+    #[serde(default = "default_pr_cache_dir")]
+    pub cache_dir: PathBuf,
+
+    /// Actions waiting for the network, sent by `hp pr flush`
+    #[serde(default = "default_pr_queue_file")]
+    pub queue_file: PathBuf,
+}
+
+impl Default for PrConfig {
+    fn default() -> Self {
+        Self {
+            forge: None,
+            github: GitHubConfig::default(),
+            gitlab: GitLabConfig::default(),
+            title_template: None,
+            body_template: None,
+            auto_create: false,
+            request_codeowners: false,
+            shepherd: ShepherdConfig::default(),
+            retry: RetryConfig::default(),
+            cache_dir: default_pr_cache_dir(),
+            queue_file: default_pr_queue_file(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryConfig {
+    /// Retries after the first attempt
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    /// Longest wait before a retry; rate limits resetting later fail instead
+    #[serde(default = "default_max_wait_secs")]
+    pub max_wait_secs: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            max_wait_secs: default_max_wait_secs(),
+        }
+    }
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_max_wait_secs() -> u64 {
+    60
+}
+
+fn default_pr_cache_dir() -> PathBuf {
+    PathBuf::from(".hp/cache/forge")
+}
+
+fn default_pr_queue_file() -> PathBuf {
+    PathBuf::from(".hp/pr-queue.json")
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -334,6 +466,134 @@ pub struct GitHubConfig {
 
     #[serde(default)]
     pub labels: Vec<String>,
+
+    /// Git host the remote must point at (e.g. a GitHub Enterprise host)
+    #[serde(default = "default_github_host")]
+    pub host: String,
+
+    /// REST API base URL; derived from `host` when unset
+    #[serde(default)]
+    pub api_base_url: Option<String>,
+
+    /// Git remote holding the repository
+    #[serde(default = "default_remote")]
+    pub remote: String,
+
+    /// File holding a token, tried after the environment, `gh` and the git
+    /// credential helper
+    #[serde(default)]
+    pub token_file: Option<PathBuf>,
+
+    /// Authenticate as a GitHub App installation instead of a user
+    #[serde(default)]
+    pub app: Option<GitHubAppConfig>,
+}
+
+/// GitHub App used for bot accounts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAppConfig {
+    pub app_id: u64,
+
+    /// PEM private key generated for the app
+    pub private_key_path: PathBuf,
+
+    /// Installation to act as; looked up from the repository when unset
+    #[serde(default)]
+    pub installation_id: Option<u64>,
+}
+
+impl Default for GitHubConfig {
+    fn default() -> Self {
+        Self {
+            org: String::new(),
+            default_reviewers: Vec::new(),
+            labels: Vec::new(),
+            host: default_github_host(),
+            api_base_url: None,
+            remote: default_remote(),
+            token_file: None,
+            app: None,
+        }
+    }
+}
+
+impl GitHubConfig {
+    /// REST API base: api.github.com for github.com, `/api/v3` on Enterprise
+    pub fn api_url(&self) -> String {
+        match &self.api_base_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None if self.host == "github.com" => "https://api.github.com".to_string(),
+            None => format!("https://{}/api/v3", self.host),
+        }
+    }
+
+    /// GraphQL base; Enterprise serves GraphQL at `/api/graphql`, not under `/api/v3`
+    pub fn graphql_url(&self) -> String {
+        let api = self.api_url();
+        api.strip_suffix("/v3").map(str::to_string).unwrap_or(api)
+    }
+}
+
+/// Code forge serving pull (or merge) requests
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    Github,
+    Gitlab,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitLabConfig {
+    /// Git host of a GitLab instance; remotes on it use the GitLab forge
+    #[serde(default = "default_gitlab_host")]
+    pub host: String,
+
+    /// REST API base URL; `https://<remote host>/api/v4` when unset
+    #[serde(default)]
+    pub api_base_url: Option<String>,
+
+    /// Git remote holding the project
+    #[serde(default = "default_remote")]
+    pub remote: String,
+
+    /// Environment variable holding the access token
+    #[serde(default = "default_gitlab_token_env")]
+    pub token_env: String,
+
+    #[serde(default)]
+    pub default_reviewers: Vec<String>,
+
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+impl Default for GitLabConfig {
+    fn default() -> Self {
+        Self {
+            host: default_gitlab_host(),
+            api_base_url: None,
+            remote: default_remote(),
+            token_env: default_gitlab_token_env(),
+            default_reviewers: Vec::new(),
+            labels: Vec::new(),
+        }
+    }
+}
+
+fn default_gitlab_host() -> String {
+    "gitlab.com".to_string()
+}
+
+fn default_gitlab_token_env() -> String {
+    "GITLAB_TOKEN".to_string()
+}
+
+fn default_github_host() -> String {
+    "github.com".to_string()
+}
+
+fn default_remote() -> String {
+    "origin".to_string()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -349,6 +609,19 @@ pub struct ShepherdConfig {
 
     #[serde(default = "default_analysis_model")]
     pub analysis_model: String,
+
+    /// Provider used to analyze review comments
+    #[serde(default)]
+    pub analyzer: AnalyzerConfig,
+
+    /// Command that must pass before a fix is committed (e.g. `cargo test`)
+    #[serde(default)]
+    pub verify_command: Option<String>,
+
+    /// AI tool arguments for non-interactive fixes; `{prompt}` is replaced.
+    /// Defaults to the tool's `ai_tool.headless_args` template.
+    #[serde(default)]
+    pub fix_args: Option<Vec<String>>,
 }
 
 impl Default for ShepherdConfig {
@@ -358,6 +631,9 @@ impl Default for ShepherdConfig {
             confidence_threshold: ConfidenceLevel::High,
             auto_post_responses: false,
             analysis_model: default_analysis_model(),
+            analyzer: AnalyzerConfig::default(),
+            verify_command: None,
+            fix_args: None,
         }
     }
 }
@@ -366,6 +642,55 @@ fn default_analysis_model() -> String {
     "gpt-4".to_string()
 }
 
+/// Shepherd comment analyzer settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzerConfig {
+    /// Provider; inferred from `command` or `analysis_model` when unset
+    #[serde(default)]
+    pub provider: Option<AnalyzerProvider>,
+
+    /// Command for the `command` provider (request JSON on stdin)
+    #[serde(default)]
+    pub command: Option<String>,
+
+    /// Environment variable holding the API key
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+
+    /// Override the provider's API base URL
+    #[serde(default)]
+    pub base_url: Option<String>,
+
+    /// Lines of file content sent either side of the commented line
+    #[serde(default = "default_context_lines")]
+    pub context_lines: usize,
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            command: None,
+            api_key_env: None,
+            base_url: None,
+            context_lines: default_context_lines(),
+        }
+    }
+}
+
+fn default_context_lines() -> usize {
+    20
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnalyzerProvider {
+    Openai,
+    Anthropic,
+    Command,
+    Stub,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum ConfidenceLevel {
@@ -398,6 +723,10 @@ pub struct MarketplaceConfig {
 
     #[serde(default = "default_cache_dir")]
     pub cache_dir: PathBuf,
+
+    /// Additional registries; when set, `registry_url` is ignored
+    #[serde(default)]
+    pub registries: Vec<RegistryConfig>,
 }
 
 impl Default for MarketplaceConfig {
@@ -407,7 +736,59 @@ impl Default for MarketplaceConfig {
             registry_url: default_registry_url(),
             auto_update: false,
             cache_dir: default_cache_dir(),
+            registries: Vec::new(),
+        }
+    }
+}
+
+impl MarketplaceConfig {
+    /// Registries in lookup order (lowest priority value first)
+    pub fn effective_registries(&self) -> Vec<RegistryConfig> {
+        if self.registries.is_empty() {
+            return vec![RegistryConfig {
+                name: "default".to_string(),
+                url: self.registry_url.clone(),
+                priority: 0,
+                token: None,
+                token_env: None,
+            }];
         }
+
+        let mut registries = self.registries.clone();
+        registries.sort_by_key(|r| r.priority);
+        registries
+    }
+}
+
+/// A single template registry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegistryConfig {
+    pub name: String,
+
+    /// `https://` URL, `file://` URL or local directory
+    pub url: String,
+
+    /// Lookup order; lower values are tried first
+    #[serde(default)]
+    pub priority: u32,
+
+    /// Bearer token sent with requests
+    #[serde(default)]
+    pub token: Option<String>,
+
+    /// Environment variable holding the bearer token
+    #[serde(default)]
+    pub token_env: Option<String>,
+}
+
+impl RegistryConfig {
+    /// Resolve the auth token, preferring the environment variable
+    pub fn resolve_token(&self) -> Option<String> {
+        self.token_env
+            .as_ref()
+            .and_then(|var| std::env::var(var).ok())
+            .filter(|t| !t.is_empty())
+            .or_else(|| self.token.clone())
     }
 }
 
@@ -485,6 +866,35 @@ hp:
         assert_eq!(dev_profile.hn.as_ref().unwrap().command, "hn-dev");
     }
 
+    #[test]
+    fn test_marketplace_registries() {
+        let yaml = r#"
+hp:
+  templates:
+    marketplace:
+      registries:
+        - name: public
+          url: https://hp-templates.dev
+          priority: 20
+        - name: internal
+          url: https://templates.example.com
+          priority: 10
+          token_env: HP_TEST_REGISTRY_TOKEN_UNSET
+          token: fallback
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let registries = config.hp.templates.marketplace.effective_registries();
+        assert_eq!(registries.len(), 2);
+        assert_eq!(registries[0].name, "internal");
+        assert_eq!(registries[1].name, "public");
+        assert_eq!(registries[0].resolve_token().as_deref(), Some("fallback"));
+
+        // Without explicit registries, registry_url is used
+        let registries = MarketplaceConfig::default().effective_registries();
+        assert_eq!(registries.len(), 1);
+        assert_eq!(registries[0].url, "https://hp-templates.dev");
+    }
+
     #[test]
     fn test_confidence_level() {
         let yaml = "high";
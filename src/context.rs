@@ -2,13 +2,37 @@
 
 use crate::config::Config;
 use crate::error::{Error, Result};
+use crate::merge::{self, MergedSection, Outcome, Resolution};
 use crate::models::{AgentType, Session, SnapshotInfo, SnapshotTrigger};
-use chrono::Utc;
+use crate::session::SessionManager;
+use chrono::{NaiveDateTime, Utc};
+use colored::Colorize;
+use dialoguer::Select;
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
 
+/// Options for `hp context sync`
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Only sync these section headings
+    pub sections: Option<Vec<String>>,
+    /// Pick resolutions for conflicting sections interactively
+    pub interactive: bool,
+}
+
+/// Result of a context sync
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// Sections updated from the source
+    pub updated: Vec<String>,
+    /// Sections changed on both sides
+    pub conflicts: Vec<String>,
+    /// Whether a common base snapshot was found
+    pub had_base: bool,
+}
+
 /// Context manager
 #[allow(dead_code)]
 pub struct ContextManager {
@@ -106,7 +130,7 @@ impl ContextManager {
 
         // Generate snapshot filename
         let timestamp = Utc::now();
-        let filename = format!("{}_{}.md", timestamp.format("%Y%m%d_%H%M%S"), name);
+        let filename = format!("{}_{}.md", timestamp.format(SNAPSHOT_TIME_FORMAT), name);
         let snapshot_path = snapshots_dir.join(&filename);
 
         // Copy current context to snapshot
@@ -145,7 +169,13 @@ impl ContextManager {
                     let parts: Vec<&str> = filename.splitn(3, '_').collect();
                     if parts.len() >= 3 {
                         let name = parts[2].to_string();
-                        let timestamp = Utc::now(); // In production, parse from filename
+                        let timestamp = match NaiveDateTime::parse_from_str(
+                            &format!("{}_{}", parts[0], parts[1]),
+                            SNAPSHOT_TIME_FORMAT,
+                        ) {
+                            Ok(naive) => naive.and_utc(),
+                            Err(_) => continue,
+                        };
 
                         snapshots.push(SnapshotInfo {
                             name,
@@ -159,8 +189,12 @@ impl ContextManager {
             }
         }
 
-        // Sort by timestamp (newest first)
-        snapshots.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        // Sort by timestamp (newest first); filenames break same-second ties
+        snapshots.sort_by(|a, b| {
+            b.timestamp
+                .cmp(&a.timestamp)
+                .then_with(|| b.path.cmp(&a.path))
+        });
 
         Ok(snapshots)
     }
@@ -184,10 +218,267 @@ impl ContextManager {
     }
 
     /// Sync context from one session to another
-    pub fn sync_context(&self, from_session: &Session, to_session: &Session) -> Result<()> {
+    ///
+    /// Sections are merged three-way against the last common snapshot, so
+    /// edits made in the target session survive. The source content is then
+    /// recorded as `sync-<source>` in the target's snapshots to serve as the
+    /// base for the next sync.
+    pub fn sync_context(
+        &self,
+        from_session: &Session,
+        to_session: &Session,
+        opts: &SyncOptions,
+    ) -> Result<SyncReport> {
         let from_content = self.read_context(from_session)?;
-        self.write_context(to_session, &from_content)?;
-        Ok(())
+        let to_content = self.read_context(to_session)?;
+        let base = self.find_sync_base(from_session, to_session)?;
+
+        let merged = merge::merge3(
+            base.as_deref(),
+            &from_content,
+            &to_content,
+            opts.sections.as_deref(),
+        );
+
+        let mut report = SyncReport {
+            had_base: base.is_some(),
+            ..Default::default()
+        };
+        for section in &merged {
+            match section.outcome {
+                Outcome::Take(_) | Outcome::Removed if !section.key.is_empty() => {
+                    report.updated.push(section.title.clone())
+                }
+                Outcome::Conflict { .. } => report.conflicts.push(section.title.clone()),
+                _ => {}
+            }
+        }
+
+        let rendered = merge::render(&merged, &from_session.name, &to_session.name, |c| {
+            if opts.interactive {
+                self.pick_resolution(c, &from_session.name, &to_session.name)
+            } else {
+                Ok(Resolution::Markers)
+            }
+        })?;
+
+        if rendered.trim_end() != to_content.trim_end() {
+            self.create_snapshot(
+                to_session,
+                &format!("before-sync-{}", from_session.name),
+                SnapshotTrigger::BeforeSync,
+                None,
+            )?;
+            self.write_context(to_session, &rendered)?;
+        }
+
+        // Sections left out of this sync keep their previous base
+        let synced = match &opts.sections {
+            Some(only) => merge::overlay(base.as_deref(), &from_content, only),
+            None => from_content,
+        };
+        self.write_snapshot(to_session, &format!("sync-{}", from_session.name), &synced)?;
+
+        Ok(report)
+    }
+
+    /// Find the base for a three-way sync
+    ///
+    /// Prefers the source content recorded at the last sync, then the newest
+    /// snapshot whose content exists in both sessions.
+    fn find_sync_base(&self, from: &Session, to: &Session) -> Result<Option<String>> {
+        let to_snapshots = self.list_snapshots(to)?;
+        let sync_name = format!("sync-{}", from.name);
+        if let Some(snapshot) = to_snapshots.iter().find(|s| s.name == sync_name) {
+            return Ok(Some(fs::read_to_string(&snapshot.path)?));
+        }
+
+        let from_contents: Vec<String> = self
+            .list_snapshots(from)?
+            .iter()
+            .filter_map(|s| fs::read_to_string(&s.path).ok())
+            .map(|c| c.trim_end().to_string())
+            .collect();
+        for snapshot in &to_snapshots {
+            let content = fs::read_to_string(&snapshot.path)?;
+            if from_contents.iter().any(|c| *c == content.trim_end()) {
+                return Ok(Some(content));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn write_snapshot(&self, session: &Session, name: &str, content: &str) -> Result<PathBuf> {
+        let snapshots_dir = session.context_dir.join("snapshots");
+        fs::create_dir_all(&snapshots_dir)?;
+
+        let filename = format!("{}_{}.md", Utc::now().format(SNAPSHOT_TIME_FORMAT), name);
+        let path = snapshots_dir.join(filename);
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    fn pick_resolution(
+        &self,
+        section: &MergedSection,
+        source_label: &str,
+        target_label: &str,
+    ) -> Result<Resolution> {
+        if let Outcome::Conflict { source, target } = &section.outcome {
+            println!();
+            println!(
+                "{} Conflict in section: {}",
+                "⚠".yellow(),
+                section.title.bold()
+            );
+            println!();
+            println!("{}", format!("--- {} (target)", target_label).red());
+            println!("{}", target.as_deref().unwrap_or("(deleted)").trim_end());
+            println!("{}", format!("+++ {} (source)", source_label).green());
+            println!("{}", source.as_deref().unwrap_or("(deleted)").trim_end());
+            println!();
+        }
+
+        let choices = [
+            format!("Keep {}", target_label),
+            format!("Take {}", source_label),
+            "Keep both".to_string(),
+            "Write conflict markers".to_string(),
+        ];
+        let selection = Select::new()
+            .with_prompt("How should this section be resolved?")
+            .items(&choices)
+            .default(3)
+            .interact()
+            .map_err(|e| Error::Other(format!("Selection failed: {}", e)))?;
+
+        Ok(match selection {
+            0 => Resolution::Target,
+            1 => Resolution::Source,
+            2 => Resolution::Both,
+            _ => Resolution::Markers,
+        })
+    }
+
+    /// Path of the repo-wide context prepended to every session
+    pub fn global_context_path(&self) -> PathBuf {
+        self.context_base_dir.join("global.md")
+    }
+
+    /// Directory holding shared `{{> fragment}}` files
+    pub fn fragments_dir(&self) -> PathBuf {
+        self.context_base_dir.join("fragments")
+    }
+
+    /// Render the fully-expanded context the AI tool will see
+    ///
+    /// The repo-wide `global.md` comes first, followed by the session context.
+    /// `{{> name}}` pulls in `fragments/<name>.md`; `{{> parent}}` pulls in
+    /// the parent session's rendered context.
+    pub fn render_context(&self, session: &Session) -> Result<String> {
+        let mut stack = Vec::new();
+        let body = self.expand_includes(&self.read_context(session)?, session, &mut stack)?;
+
+        let global_path = self.global_context_path();
+        let rendered = if global_path.exists() {
+            let global = fs::read_to_string(&global_path)?;
+            let global = self.expand_includes(&global, session, &mut stack)?;
+            format!("{}\n\n---\n\n{}", global.trim_end(), body)
+        } else {
+            body
+        };
+
+        self.substitute_variables(&rendered, session)
+    }
+
+    /// Render context and write it next to `context.md` for the AI tool
+    pub fn write_rendered_context(&self, session: &Session) -> Result<PathBuf> {
+        let rendered = self.render_context(session)?;
+        let path = session.context_dir.join("rendered.md");
+        fs::write(&path, rendered)?;
+        Ok(path)
+    }
+
+    /// Expand `{{> name}}` directives, detecting include cycles
+    fn expand_includes(
+        &self,
+        content: &str,
+        session: &Session,
+        stack: &mut Vec<String>,
+    ) -> Result<String> {
+        let mut result = String::with_capacity(content.len());
+        let mut rest = content;
+
+        while let Some(start) = rest.find("{{>") {
+            let end = rest[start..].find("}}").ok_or_else(|| {
+                Error::ParseError("Unterminated '{{>' include directive".to_string())
+            })? + start;
+            let name = rest[start + 3..end].trim();
+
+            // Each session's parent is a distinct include
+            let key = match (name, &session.parent) {
+                ("parent", Some(parent)) => format!("parent:{}", parent),
+                _ => name.to_string(),
+            };
+            if stack.contains(&key) {
+                return Err(Error::Other(format!(
+                    "Context include cycle: {} -> {}",
+                    stack.join(" -> "),
+                    key
+                )));
+            }
+
+            stack.push(key);
+            let included = if name == "parent" {
+                self.render_parent(session, stack)?
+            } else {
+                let fragment = self.read_fragment(name)?;
+                self.expand_includes(&fragment, session, stack)?
+            };
+            stack.pop();
+
+            result.push_str(&rest[..start]);
+            result.push_str(included.trim_end());
+            rest = &rest[end + 2..];
+        }
+
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    fn read_fragment(&self, name: &str) -> Result<String> {
+        if name.is_empty() || name.split('/').any(|part| part == ".." || part.is_empty()) {
+            return Err(Error::InvalidInput(format!(
+                "Invalid context fragment name '{}'",
+                name
+            )));
+        }
+
+        let path = self.fragments_dir().join(format!("{}.md", name));
+        if !path.exists() {
+            return Err(Error::Other(format!(
+                "Context fragment '{}' not found (expected {})",
+                name,
+                path.display()
+            )));
+        }
+
+        Ok(fs::read_to_string(path)?)
+    }
+
+    fn render_parent(&self, session: &Session, stack: &mut Vec<String>) -> Result<String> {
+        let parent_name = match &session.parent {
+            Some(parent) => parent,
+            None => return Ok(String::new()),
+        };
+
+        let parent = SessionManager::new(self.config.clone())?.load_session(parent_name)?;
+
+        let content = self.read_context(&parent)?;
+        let rendered = self.expand_includes(&content, &parent, stack)?;
+
+        self.substitute_variables(&rendered, &parent)
     }
 
     /// Get default template content for an agent type
@@ -222,8 +513,11 @@ impl ContextManager {
     }
 }
 
+/// Timestamp prefix of snapshot filenames
+const SNAPSHOT_TIME_FORMAT: &str = "%Y%m%d_%H%M%S";
+
 /// Get built-in template for an agent type
-fn get_builtin_template(agent_type: &AgentType) -> String {
+pub(crate) fn get_builtin_template(agent_type: &AgentType) -> String {
     match agent_type {
         AgentType::Feature => TEMPLATE_FEATURE.to_string(),
         AgentType::Bugfix => TEMPLATE_BUGFIX.to_string(),
@@ -660,16 +954,118 @@ mod tests {
         manager.init_context(&session1).unwrap();
         manager.init_context(&session2).unwrap();
 
-        // Write content to session1
-        let content = "# Shared Content";
-        manager.write_context(&session1, content).unwrap();
+        manager
+            .write_context(&session1, "# One\n\n## Objective\n\nShip it\n")
+            .unwrap();
+        manager
+            .write_context(&session2, "# Two\n\n## Notes\n\nLocal\n")
+            .unwrap();
 
         // Sync to session2
-        manager.sync_context(&session1, &session2).unwrap();
+        let report = manager
+            .sync_context(&session1, &session2, &SyncOptions::default())
+            .unwrap();
+        assert_eq!(report.updated, vec!["Objective".to_string()]);
+        assert!(!report.had_base);
 
-        // Verify session2 has the same content
+        // Session2 gains the section but keeps its own header and notes
         let session2_content = manager.read_context(&session2).unwrap();
-        assert_eq!(session2_content, content);
+        assert_eq!(
+            session2_content,
+            "# Two\n\n## Objective\n\nShip it\n\n## Notes\n\nLocal\n"
+        );
+    }
+
+    #[test]
+    fn test_sync_context_three_way() {
+        let (manager, temp) = create_test_manager();
+        let parent = create_test_session_with_context_dir(&temp.path().join("contexts"));
+        let mut child = create_test_session_with_context_dir(&temp.path().join("contexts"));
+        child.name = "child".to_string();
+        child.context_dir = temp.path().join("contexts/child");
+        manager.init_context(&parent).unwrap();
+        manager.init_context(&child).unwrap();
+
+        let base = "# Parent\n\n## Objective\n\nAuth\n\n## Notes\n\n-\n";
+        manager.write_context(&parent, base).unwrap();
+        manager
+            .sync_context(&parent, &child, &SyncOptions::default())
+            .unwrap();
+        manager
+            .write_context(&child, &base.replace("# Parent", "# Child"))
+            .unwrap();
+
+        // Both sides edit: parent changes Objective, child changes Notes
+        manager
+            .write_context(&parent, &base.replace("Auth", "OAuth"))
+            .unwrap();
+        manager
+            .write_context(
+                &child,
+                &base
+                    .replace("# Parent", "# Child")
+                    .replace("-\n", "- child\n"),
+            )
+            .unwrap();
+
+        let report = manager
+            .sync_context(&parent, &child, &SyncOptions::default())
+            .unwrap();
+        assert!(report.had_base);
+        assert!(report.conflicts.is_empty());
+        let content = manager.read_context(&child).unwrap();
+        assert!(content.starts_with("# Child"));
+        assert!(content.contains("OAuth"));
+        assert!(content.contains("- child"));
+
+        // Conflicting edits produce markers
+        manager
+            .write_context(&parent, &base.replace("Auth", "SAML"))
+            .unwrap();
+        manager
+            .write_context(&child, &content.replace("OAuth", "OIDC"))
+            .unwrap();
+        let report = manager
+            .sync_context(&parent, &child, &SyncOptions::default())
+            .unwrap();
+        assert_eq!(report.conflicts, vec!["Objective".to_string()]);
+        let content = manager.read_context(&child).unwrap();
+        assert!(content.contains("<<<<<<< child"));
+        assert!(content.contains(">>>>>>> test-session"));
+
+        // A partial sync leaves the other sections' base alone
+        manager
+            .write_context(
+                &child,
+                &base
+                    .replace("# Parent", "# Child")
+                    .replace("Auth", "SAML")
+                    .replace("-\n", "- child\n"),
+            )
+            .unwrap();
+        manager
+            .write_context(
+                &parent,
+                &base.replace("Auth", "SSO").replace("-\n", "- parent\n"),
+            )
+            .unwrap();
+        let objective_only = SyncOptions {
+            sections: Some(vec!["Objective".to_string()]),
+            ..Default::default()
+        };
+        manager
+            .sync_context(&parent, &child, &objective_only)
+            .unwrap();
+        assert!(manager.read_context(&child).unwrap().contains("SSO"));
+        let report = manager
+            .sync_context(&parent, &child, &SyncOptions::default())
+            .unwrap();
+        assert_eq!(report.conflicts, vec!["Notes".to_string()]);
+
+        // Snapshot timestamps come from the filename
+        let snapshots = manager.list_snapshots(&child).unwrap();
+        assert!(snapshots.iter().any(|s| s.name == "sync-test-session"));
+        assert!(snapshots.iter().all(|s| s.timestamp <= Utc::now()));
     }
 
     #[test]
@@ -691,4 +1087,94 @@ mod tests {
         assert!(get_builtin_template(&AgentType::Test).contains("Test"));
         assert!(get_builtin_template(&AgentType::Docs).contains("Documentation"));
     }
+
+    #[test]
+    fn test_render_context_with_global_and_fragments() {
+        let (manager, temp) = create_test_manager();
+        let session = create_test_session_with_context_dir(&temp.path().join("contexts"));
+        manager.init_context(&session).unwrap();
+
+        fs::create_dir_all(manager.fragments_dir().join("team")).unwrap();
+        fs::write(
+            manager.fragments_dir().join("standards.md"),
+            "## Standards\n{{> team/naming}}\n",
+        )
+        .unwrap();
+        fs::write(
+            manager.fragments_dir().join("team/naming.md"),
+            "Use snake_case in {{repo_name}}.\n",
+        )
+        .unwrap();
+        fs::write(manager.global_context_path(), "# Global\n{{> standards}}\n").unwrap();
+        manager
+            .write_context(&session, "# Session\n\n{{> standards}}\n")
+            .unwrap();
+
+        let rendered = manager.render_context(&session).unwrap();
+        assert!(rendered.starts_with("# Global\n## Standards\nUse snake_case in myrepo."));
+        assert!(rendered.contains("---\n\n# Session\n\n## Standards"));
+        assert!(!rendered.contains("{{>"));
+
+        let path = manager.write_rendered_context(&session).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), rendered);
+    }
+
+    #[test]
+    fn test_render_context_errors() {
+        let (manager, temp) = create_test_manager();
+        let session = create_test_session_with_context_dir(&temp.path().join("contexts"));
+        manager.init_context(&session).unwrap();
+        fs::create_dir_all(manager.fragments_dir()).unwrap();
+
+        manager.write_context(&session, "{{> missing}}").unwrap();
+        assert!(manager.render_context(&session).is_err());
+
+        manager.write_context(&session, "{{> ../secrets}}").unwrap();
+        assert!(manager.render_context(&session).is_err());
+
+        fs::write(manager.fragments_dir().join("a.md"), "{{> b}}").unwrap();
+        fs::write(manager.fragments_dir().join("b.md"), "{{> a}}").unwrap();
+        manager.write_context(&session, "{{> a}}").unwrap();
+        let err = manager.render_context(&session).unwrap_err();
+        assert!(err.to_string().contains("cycle"));
+
+        // Sessions without a parent render `{{> parent}}` as empty
+        manager
+            .write_context(&session, "before{{> parent}}after")
+            .unwrap();
+        assert_eq!(manager.render_context(&session).unwrap(), "beforeafter");
+    }
+
+    #[test]
+    fn test_render_parent_context() {
+        let temp = TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.hp.sessions.context_dir = temp.path().join("contexts");
+        config.hp.sessions.metadata_dir = temp.path().join("sessions");
+        fs::create_dir_all(&config.hp.sessions.metadata_dir).unwrap();
+        let manager = ContextManager::new(config.clone()).unwrap();
+
+        let mut parent = create_test_session();
+        parent.name = "parent-session".to_string();
+        parent.context_dir = temp.path().join("contexts/parent-session");
+        manager.init_context(&parent).unwrap();
+        manager
+            .write_context(&parent, "Parent plan for {{session_name}}")
+            .unwrap();
+        fs::write(
+            config.hp.sessions.metadata_dir.join("parent-session.yaml"),
+            serde_yaml::to_string(&parent).unwrap(),
+        )
+        .unwrap();
+
+        let mut child = create_test_session_with_context_dir(&temp.path().join("contexts"));
+        child.parent = Some("parent-session".to_string());
+        manager.init_context(&child).unwrap();
+        manager
+            .write_context(&child, "# Child\n{{> parent}}\n")
+            .unwrap();
+
+        let rendered = manager.render_context(&child).unwrap();
+        assert_eq!(rendered, "# Child\nParent plan for parent-session\n");
+    }
 }
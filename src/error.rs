@@ -71,6 +71,10 @@ pub enum Error {
     #[error("Template '{0}' not found\n\nOptions:\n  • List available templates: hp template list\n  • Search for templates: hp template search <query>\n  • Install from marketplace: hp template install {0}\n  • Check spelling and try again")]
     TemplateNotFound(String),
 
+    /// Template content doesn't match its locked hash
+    #[error("Template '{0}' failed integrity check\n\nExpected sha256: {1}\nActual sha256:   {2}\n\nTo fix:\n  • Check whether the registry republished this version\n  • Re-resolve the template: hp template update {0}\n  • Restore .hp/templates.lock from version control if it was edited")]
+    TemplateIntegrity(String, String, String),
+
     /// Session is locked
     #[error("Session '{0}' is locked by {1}\n\nThis session is currently in use by another developer or process.\n\nYou can:\n  • Wait for the lock to be released\n  • Contact {1} to coordinate\n  • Clone the session instead: hp collab clone {0} <new-name>")]
     SessionLocked(String, String),
@@ -91,6 +95,10 @@ pub enum Error {
     #[error("Profile '{0}' not found\n\nAvailable profiles:\n  • List all profiles: hp profile list\n  • Use default profile (omit --profile flag)\n  • Check profile name spelling")]
     ProfileNotFound(String),
 
+    /// A server could not be reached
+    #[error("{0}")]
+    Network(String),
+
     /// Generic error
     #[error("{0}")]
     Other(String),
@@ -102,12 +110,6 @@ impl From<anyhow::Error> for Error {
     }
 }
 
-impl From<octocrab::Error> for Error {
-    fn from(err: octocrab::Error) -> Self {
-        Error::Other(format!("GitHub API error: {}", err))
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
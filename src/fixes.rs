@@ -0,0 +1,219 @@
+//! Applying shepherd fixes in a session workbox
+//!
+//! A fix is either a GitHub ```` ```suggestion ```` block, applied verbatim
+//! over the commented lines, or a change made by the AI tool running
+//! non-interactively. Each fix is verified and committed on its own, and
+//! rolled back if verification fails.
+
+use crate::error::{Error, Result};
+use crate::git;
+use std::path::Path;
+use std::process::Command;
+
+/// Result of applying one fix
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixResult {
+    /// Fix committed with this commit id
+    Committed(String),
+    /// The change left the tree untouched
+    NoChanges,
+    /// Verification failed and the change was rolled back
+    VerifyFailed(String),
+}
+
+/// Extract the first ```` ```suggestion ```` block from a comment body
+///
+/// An empty block is a valid suggestion that deletes the lines.
+pub fn extract_suggestion(body: &str) -> Option<String> {
+    let mut lines = body.lines();
+    lines.find(|l| l.trim_start().starts_with("```suggestion"))?;
+
+    let mut suggestion = Vec::new();
+    for line in lines {
+        if line.trim_start().starts_with("```") {
+            return Some(suggestion.join("\n"));
+        }
+        suggestion.push(line);
+    }
+    None
+}
+
+/// Replace lines `start..=end` (1-based) of `content` with `suggestion`
+pub fn apply_suggestion(content: &str, start: u32, end: u32, suggestion: &str) -> Result<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let (start, end) = (start as usize, end as usize);
+    if start == 0 || start > end || end > lines.len() {
+        return Err(Error::Other(format!(
+            "Suggestion lines {}-{} are outside the file ({} lines)",
+            start,
+            end,
+            lines.len()
+        )));
+    }
+
+    let mut result: Vec<&str> = lines[..start - 1].to_vec();
+    if !suggestion.is_empty() {
+        result.extend(suggestion.lines());
+    }
+    result.extend(&lines[end..]);
+
+    let mut output = result.join("\n");
+    if content.ends_with('\n') && !output.is_empty() {
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Whether the workbox has no uncommitted changes
+pub fn is_clean(workbox: &Path) -> Result<bool> {
+    Ok(git::run(Some(workbox), &["status", "--porcelain"])?.is_empty())
+}
+
+/// Apply `change` in a clean workbox, verify it and commit it
+///
+/// The commit references the review comment so fixes can be traced back.
+/// On verification failure the tree is restored to HEAD.
+pub fn apply_fix<F>(
+    workbox: &Path,
+    comment_id: u64,
+    summary: &str,
+    verify_command: Option<&str>,
+    change: F,
+) -> Result<FixResult>
+where
+    F: FnOnce(&Path) -> Result<()>,
+{
+    if !is_clean(workbox)? {
+        return Err(Error::Other(
+            "Workbox has uncommitted changes; commit or stash them before applying fixes"
+                .to_string(),
+        ));
+    }
+
+    if let Err(e) = change(workbox) {
+        rollback(workbox)?;
+        return Err(e);
+    }
+
+    if is_clean(workbox)? {
+        return Ok(FixResult::NoChanges);
+    }
+
+    if let Some(command) = verify_command {
+        if let Err(output) = verify(workbox, command) {
+            rollback(workbox)?;
+            return Ok(FixResult::VerifyFailed(output));
+        }
+    }
+
+    let message = format!(
+        "Address review comment {}\n\n{}\n\nReview-comment: {}",
+        comment_id, summary, comment_id
+    );
+    git::run(Some(workbox), &["add", "-A"])?;
+    git::run(Some(workbox), &["commit", "--quiet", "-m", &message])?;
+
+    Ok(FixResult::Committed(git::run(
+        Some(workbox),
+        &["rev-parse", "HEAD"],
+    )?))
+}
+
+/// Run the verification command, returning its output on failure
+fn verify(workbox: &Path, command: &str) -> std::result::Result<(), String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(workbox)
+        .output()
+        .map_err(|e| format!("Failed to run '{}': {}", command, e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        let mut log = String::from_utf8_lossy(&output.stdout).to_string();
+        log.push_str(&String::from_utf8_lossy(&output.stderr));
+        Err(log.trim().to_string())
+    }
+}
+
+fn rollback(workbox: &Path) -> Result<()> {
+    git::run(Some(workbox), &["reset", "--quiet", "--hard", "HEAD"])?;
+    git::run(Some(workbox), &["clean", "--quiet", "-fd"])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn init_repo() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        git::run(Some(dir), &["init", "--quiet", "-b", "main"]).unwrap();
+        git::run(Some(dir), &["config", "user.email", "test@example.com"]).unwrap();
+        git::run(Some(dir), &["config", "user.name", "Test"]).unwrap();
+        std::fs::write(dir.join("lib.rs"), "fn a() {}\nfn b() {}\nfn c() {}\n").unwrap();
+        git::run(Some(dir), &["add", "-A"]).unwrap();
+        git::run(Some(dir), &["commit", "--quiet", "-m", "init"]).unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_extract_suggestion() {
+        let body = "Rename this:\n```suggestion\nfn better() {}\n```\nThanks";
+        assert_eq!(extract_suggestion(body).unwrap(), "fn better() {}");
+        assert_eq!(extract_suggestion("```suggestion\n```").unwrap(), "");
+        assert!(extract_suggestion("```rust\nfn a() {}\n```").is_none());
+        assert!(extract_suggestion("```suggestion\nunterminated").is_none());
+    }
+
+    #[test]
+    fn test_apply_suggestion() {
+        let content = "a\nb\nc\n";
+        assert_eq!(apply_suggestion(content, 2, 2, "B").unwrap(), "a\nB\nc\n");
+        assert_eq!(
+            apply_suggestion(content, 1, 2, "x\ny\nz").unwrap(),
+            "x\ny\nz\nc\n"
+        );
+        assert_eq!(apply_suggestion(content, 3, 3, "").unwrap(), "a\nb\n");
+        assert!(apply_suggestion(content, 3, 4, "x").is_err());
+    }
+
+    #[test]
+    fn test_apply_fix_commits_and_rolls_back() {
+        let repo = init_repo();
+        let dir = repo.path();
+        let edit = |dir: &Path| -> Result<()> {
+            let content = std::fs::read_to_string(dir.join("lib.rs"))?;
+            std::fs::write(
+                dir.join("lib.rs"),
+                apply_suggestion(&content, 2, 2, "fn renamed() {}")?,
+            )?;
+            Ok(())
+        };
+
+        // Failing verification leaves HEAD and the tree untouched
+        let head = git::run(Some(dir), &["rev-parse", "HEAD"]).unwrap();
+        let result = apply_fix(dir, 42, "Rename b", Some("echo broken; exit 1"), edit).unwrap();
+        assert_eq!(result, FixResult::VerifyFailed("broken".to_string()));
+        assert_eq!(git::run(Some(dir), &["rev-parse", "HEAD"]).unwrap(), head);
+        assert!(is_clean(dir).unwrap());
+
+        let result = apply_fix(dir, 42, "Rename b", Some("true"), edit).unwrap();
+        let commit = match result {
+            FixResult::Committed(commit) => commit,
+            other => panic!("unexpected {:?}", other),
+        };
+        let message = git::run(Some(dir), &["log", "-1", "--format=%B", &commit]).unwrap();
+        assert!(message.starts_with("Address review comment 42"));
+        assert!(message.contains("Review-comment: 42"));
+
+        let noop = apply_fix(dir, 43, "Nothing", None, |_| Ok(())).unwrap();
+        assert_eq!(noop, FixResult::NoChanges);
+
+        std::fs::write(dir.join("dirty.txt"), "wip").unwrap();
+        assert!(apply_fix(dir, 44, "Dirty", None, edit).is_err());
+    }
+}
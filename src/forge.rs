@@ -0,0 +1,499 @@
+//! Code forge abstraction
+//!
+//! Pull requests on GitHub and merge requests on GitLab are driven through
+//! the [`Forge`] trait, so `hp pr` and shepherd work the same on either.
+//! The forge is chosen per repository from `hp.pr.forge` or, when unset,
+//! from the host of the repository's remote.
+
+use crate::auth;
+use crate::config::{ForgeKind, PrConfig};
+use crate::error::{Error, Result};
+use crate::git::{self, RemoteUrl};
+use crate::github::GitHubForge;
+use crate::gitlab::GitLabForge;
+use crate::http::{ClientOptions, RetryPolicy};
+use crate::models::{PrStatus, PrSyncCursor, ReviewThread};
+use async_trait::async_trait;
+use colored::Colorize;
+use std::env;
+use std::path::Path;
+use std::time::Duration;
+
+impl ForgeKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Github => "GitHub",
+            Self::Gitlab => "GitLab",
+        }
+    }
+
+    /// "pull request" or "merge request"
+    pub fn request_noun(self) -> &'static str {
+        match self {
+            Self::Github => "pull request",
+            Self::Gitlab => "merge request",
+        }
+    }
+
+    /// How the forge refers to a request, e.g. "PR #7" or "MR !7"
+    pub fn reference(self, number: u64) -> String {
+        match self {
+            Self::Github => format!("PR #{}", number),
+            Self::Gitlab => format!("MR !{}", number),
+        }
+    }
+}
+
+/// A pull or merge request to open
+#[derive(Debug, Clone)]
+pub struct NewPullRequest {
+    pub title: String,
+    pub body: String,
+    /// Branch with the changes
+    pub head: String,
+    /// Branch to merge into
+    pub base: String,
+    pub draft: bool,
+}
+
+/// An opened pull or merge request
+#[derive(Debug, Clone)]
+pub struct CreatedPullRequest {
+    /// PR number or MR iid
+    pub number: u64,
+    pub url: String,
+}
+
+/// Operations hupasiya needs from a code forge
+///
+/// `number` is the PR number on GitHub and the MR iid on GitLab. Thread IDs
+/// are the forge's own (GraphQL node IDs, GitLab discussion IDs).
+#[async_trait]
+pub trait Forge: Send + Sync {
+    fn kind(&self) -> ForgeKind;
+
+    /// Open a pull request
+    async fn create(&self, request: &NewPullRequest) -> Result<CreatedPullRequest>;
+
+    /// Request reviews from usernames and `org/team` names; GitLab has no
+    /// team reviewers and ignores them
+    async fn request_reviewers(
+        &self,
+        number: u64,
+        reviewers: &[String],
+        team_reviewers: &[String],
+    ) -> Result<()>;
+
+    /// Current PR description
+    async fn description(&self, number: u64) -> Result<String>;
+
+    /// Replace the PR description, and the title when given
+    async fn update_description(&self, number: u64, title: Option<&str>, body: &str) -> Result<()>;
+
+    async fn add_labels(&self, number: u64, labels: &[String]) -> Result<()>;
+
+    /// Change the branch a PR merges into
+    async fn retarget(&self, number: u64, base: &str) -> Result<()>;
+
+    /// All review threads with their comments
+    async fn review_threads(&self, number: u64) -> Result<Vec<ReviewThread>>;
+
+    /// Review threads after a previous sync
+    ///
+    /// Forges that can fetch only what changed since `cursor` override
+    /// this; the default fetches everything.
+    async fn sync_threads(
+        &self,
+        number: u64,
+        _previous: &[ReviewThread],
+        _cursor: Option<&PrSyncCursor>,
+    ) -> Result<Vec<ReviewThread>> {
+        self.review_threads(number).await
+    }
+
+    /// Reply to a thread; `comment_id` is its root comment
+    async fn reply(&self, number: u64, thread_id: &str, comment_id: u64, body: &str) -> Result<()>;
+
+    /// Mark a thread as resolved
+    async fn resolve_thread(&self, number: u64, thread_id: &str) -> Result<()>;
+
+    /// Post a top-level comment summarizing a review pass
+    async fn submit_review(&self, number: u64, body: &str) -> Result<()>;
+
+    /// Reviews, checks and merge state
+    async fn status(&self, number: u64) -> Result<PrDetails>;
+}
+
+/// Connect to the forge hosting the repository checked out at `workbox_path`
+pub async fn connect(pr: &PrConfig, workbox_path: &Path) -> Result<Box<dyn Forge>> {
+    let ForgeRemote {
+        kind, url, parsed, ..
+    } = forge_remote(pr, |remote| git::remote_url(workbox_path, remote))?;
+
+    let options = client_options(pr);
+    match kind {
+        ForgeKind::Github => {
+            let token = auth::github_token(&pr.github, &parsed, workbox_path).await?;
+            Ok(Box::new(GitHubForge::connect(
+                &pr.github,
+                token.token,
+                &url,
+                &options,
+            )?))
+        }
+        ForgeKind::Gitlab => Ok(Box::new(GitLabForge::connect(
+            &pr.gitlab,
+            token(&pr.gitlab.token_env)?,
+            &url,
+            &options,
+        )?)),
+    }
+}
+
+/// The git remote of the forge a repository is hosted on
+#[derive(Debug, Clone)]
+pub struct ForgeRemote {
+    pub kind: ForgeKind,
+    /// Remote name, e.g. `origin`
+    pub name: String,
+    pub url: String,
+    pub parsed: RemoteUrl,
+}
+
+/// The forge and its remote
+///
+/// With `hp.pr.forge` set that forge's remote is used. Otherwise the
+/// GitHub and GitLab remotes are tried in turn, taking the first whose host
+/// is detected as the forge it is configured for, or else the first that
+/// exists. `url_of` looks up a remote's URL.
+pub fn forge_remote(pr: &PrConfig, url_of: impl Fn(&str) -> Result<String>) -> Result<ForgeRemote> {
+    let candidates = match pr.forge {
+        Some(ForgeKind::Gitlab) => vec![(ForgeKind::Gitlab, &pr.gitlab.remote)],
+        Some(ForgeKind::Github) => vec![(ForgeKind::Github, &pr.github.remote)],
+        None => vec![
+            (ForgeKind::Github, &pr.github.remote),
+            (ForgeKind::Gitlab, &pr.gitlab.remote),
+        ],
+    };
+
+    let mut fallback = None;
+    let mut first_error = None;
+    for (configured_for, remote) in candidates {
+        let url = match url_of(remote) {
+            Ok(url) => url,
+            Err(e) => {
+                first_error.get_or_insert(e);
+                continue;
+            }
+        };
+        let parsed = git::parse_remote_url(&url)?;
+        let found = ForgeRemote {
+            kind: detect(pr, &parsed.host),
+            name: remote.clone(),
+            url,
+            parsed,
+        };
+        if found.kind == configured_for {
+            return Ok(found);
+        }
+        fallback.get_or_insert(found);
+    }
+    match (fallback, first_error) {
+        (Some(found), _) => Ok(found),
+        (None, Some(e)) => Err(e),
+        (None, None) => Err(Error::Other("No forge remote configured".to_string())),
+    }
+}
+
+/// Retry and ETag cache settings from `hp.pr`
+pub fn client_options(pr: &PrConfig) -> ClientOptions {
+    ClientOptions {
+        retry: RetryPolicy {
+            max_retries: pr.retry.max_retries,
+            max_delay: Duration::from_secs(pr.retry.max_wait_secs),
+            ..RetryPolicy::default()
+        },
+        etag_cache: Some(pr.cache_dir.join("etags.json")),
+    }
+}
+
+/// Forge for a remote host: configured explicitly, the configured GitLab
+/// host or any host with a `gitlab` label, and GitHub otherwise
+pub fn detect(pr: &PrConfig, host: &str) -> ForgeKind {
+    if let Some(kind) = pr.forge {
+        return kind;
+    }
+
+    let host = host.to_lowercase();
+    if host == pr.gitlab.host.to_lowercase() || host.split('.').any(|label| label == "gitlab") {
+        ForgeKind::Gitlab
+    } else {
+        ForgeKind::Github
+    }
+}
+
+fn token(var: &str) -> Result<String> {
+    env::var(var).map_err(|_| Error::Other(format!("{} not set", var)))
+}
+
+/// A reviewer's latest decision
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewerDecision {
+    pub reviewer: String,
+    /// APPROVED, CHANGES_REQUESTED, COMMENTED or DISMISSED
+    pub state: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckState {
+    Success,
+    Pending,
+    Failure,
+    Skipped,
+}
+
+/// A check run or commit status on the head commit
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    pub name: String,
+    pub state: CheckState,
+    pub required: bool,
+    /// Failure title or status description
+    pub summary: Option<String>,
+    pub url: Option<String>,
+}
+
+/// Everything `hp pr status` reports about a PR
+#[derive(Debug, Clone)]
+pub struct PrDetails {
+    pub kind: ForgeKind,
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    pub state: PrStatus,
+    /// MERGEABLE, CONFLICTING or UNKNOWN
+    pub mergeable: String,
+    /// GitHub's mergeStateStatus, e.g. BLOCKED or BEHIND (GitLab's
+    /// detailed merge status is mapped onto the same values)
+    pub merge_state: String,
+    /// APPROVED, CHANGES_REQUESTED, REVIEW_REQUIRED, or None if not required
+    pub review_decision: Option<String>,
+    pub reviews: Vec<ReviewerDecision>,
+    pub requested_reviewers: Vec<String>,
+    pub checks: Vec<CheckResult>,
+    pub base_ref: String,
+    pub head_ref: String,
+    pub head_oid: String,
+    pub behind_by: Option<u64>,
+}
+
+impl PrDetails {
+    /// Why the forge will or won't merge the PR
+    pub fn merge_state_reason(&self) -> &'static str {
+        match self.merge_state.as_str() {
+            "CLEAN" => "ready to merge",
+            "BEHIND" => "head branch is behind the base branch",
+            "BLOCKED" => "blocked by required reviews or checks",
+            "DIRTY" => "merge conflicts must be resolved",
+            "DRAFT" => "request is a draft",
+            "HAS_HOOKS" => "mergeable once pre-receive hooks pass",
+            "UNSTABLE" => "mergeable, but non-required checks are failing",
+            _ => "mergeability is still being computed",
+        }
+    }
+
+    pub fn print(&self) {
+        println!();
+        println!(
+            "{} {}: {}",
+            "🔗".bold(),
+            self.kind.reference(self.number),
+            self.title
+        );
+        println!("   URL: {}", self.url);
+        println!("   State: {}", format!("{:?}", self.state).to_uppercase());
+        println!("   Branch: {} → {}", self.head_ref, self.base_ref);
+        if let Some(behind) = self.behind_by {
+            if behind > 0 {
+                println!(
+                    "   {} {} commits behind {}",
+                    "⚠".yellow(),
+                    behind,
+                    self.base_ref
+                );
+            }
+        }
+
+        println!();
+        println!("{}", "Reviews".bold());
+        let decision = match self.review_decision.as_deref() {
+            Some("APPROVED") => "approved".green(),
+            Some("CHANGES_REQUESTED") => "changes requested".red(),
+            Some("REVIEW_REQUIRED") => "required reviews missing".yellow(),
+            _ => "not required".dimmed(),
+        };
+        println!("   Required review: {}", decision);
+        for review in &self.reviews {
+            let state = match review.state.as_str() {
+                "APPROVED" => "✓ approved".green(),
+                "CHANGES_REQUESTED" => "✗ changes requested".red(),
+                other => other.to_lowercase().normal(),
+            };
+            println!("   {} {}", review.reviewer, state);
+        }
+        for reviewer in &self.requested_reviewers {
+            println!("   {} {}", reviewer, "awaiting review".dimmed());
+        }
+
+        if !self.checks.is_empty() {
+            let count = |state| self.checks.iter().filter(|c| c.state == state).count();
+            println!();
+            println!(
+                "{} ({} passed, {} failed, {} pending)",
+                "Checks".bold(),
+                count(CheckState::Success),
+                count(CheckState::Failure),
+                count(CheckState::Pending)
+            );
+            for check in &self.checks {
+                let icon = match check.state {
+                    CheckState::Success => "✓".green(),
+                    CheckState::Failure => "✗".red(),
+                    CheckState::Pending => "…".yellow(),
+                    CheckState::Skipped => "-".dimmed(),
+                };
+                let required = if check.required { " (required)" } else { "" };
+                println!("   {} {}{}", icon, check.name, required.dimmed());
+                if check.state == CheckState::Failure {
+                    if let Some(summary) = &check.summary {
+                        println!("     {}", summary.dimmed());
+                    }
+                    if let Some(url) = &check.url {
+                        println!("     {}", url.dimmed());
+                    }
+                }
+            }
+        }
+
+        println!();
+        let mergeable = match self.mergeable.as_str() {
+            "MERGEABLE" => "Yes ✓".green(),
+            "CONFLICTING" => "No ✗".red(),
+            _ => "Unknown".yellow(),
+        };
+        println!(
+            "   Mergeable: {} - {}",
+            mergeable,
+            self.merge_state_reason()
+        );
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GitLabConfig;
+
+    fn pr_config(forge: Option<ForgeKind>, gitlab_host: &str) -> PrConfig {
+        PrConfig {
+            forge,
+            gitlab: GitLabConfig {
+                host: gitlab_host.to_string(),
+                ..GitLabConfig::default()
+            },
+            ..PrConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_detect_forge_from_host() {
+        let config = pr_config(None, "git.corp.example");
+        assert_eq!(detect(&config, "github.com"), ForgeKind::Github);
+        assert_eq!(detect(&config, "ghe.example.com"), ForgeKind::Github);
+        assert_eq!(detect(&config, "gitlab.com"), ForgeKind::Gitlab);
+        assert_eq!(detect(&config, "gitlab.example.com"), ForgeKind::Gitlab);
+        assert_eq!(detect(&config, "Git.Corp.Example"), ForgeKind::Gitlab);
+        assert_eq!(detect(&config, "mygitlab.example.com"), ForgeKind::Github);
+
+        let config = pr_config(Some(ForgeKind::Gitlab), "gitlab.com");
+        assert_eq!(detect(&config, "code.example.com"), ForgeKind::Gitlab);
+    }
+
+    #[test]
+    fn test_forge_remote_follows_detected_forge() {
+        let mut config = pr_config(None, "gitlab.com");
+        config.gitlab.remote = "gitlab".to_string();
+        let remotes = |urls: &[(&str, &str)]| {
+            let urls: std::collections::HashMap<String, String> = urls
+                .iter()
+                .map(|(name, url)| (name.to_string(), url.to_string()))
+                .collect();
+            move |remote: &str| {
+                urls.get(remote)
+                    .cloned()
+                    .ok_or_else(|| Error::Other(format!("No remote '{}'", remote)))
+            }
+        };
+
+        // origin points at GitHub
+        let found = forge_remote(
+            &config,
+            remotes(&[
+                ("origin", "git@github.com:acme/app.git"),
+                ("gitlab", "git@gitlab.com:acme/app.git"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(found.kind, ForgeKind::Github);
+        assert_eq!(found.name, "origin");
+
+        // origin is a GitLab mirror: the GitLab remote is used
+        let found = forge_remote(
+            &config,
+            remotes(&[
+                ("origin", "git@gitlab.com:acme/mirror.git"),
+                ("gitlab", "git@gitlab.com:acme/app.git"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(found.kind, ForgeKind::Gitlab);
+        assert_eq!(found.name, "gitlab");
+        assert_eq!(found.url, "git@gitlab.com:acme/app.git");
+
+        // Only the GitLab remote exists
+        let found = forge_remote(
+            &config,
+            remotes(&[("gitlab", "git@gitlab.com:acme/app.git")]),
+        )
+        .unwrap();
+        assert_eq!(
+            (found.kind, found.name.as_str()),
+            (ForgeKind::Gitlab, "gitlab")
+        );
+
+        // Same remote for both: detection alone decides
+        config.gitlab.remote = "origin".to_string();
+        let found = forge_remote(
+            &config,
+            remotes(&[("origin", "git@gitlab.com:acme/app.git")]),
+        )
+        .unwrap();
+        assert_eq!(found.kind, ForgeKind::Gitlab);
+
+        let err = forge_remote(&config, remotes(&[])).unwrap_err();
+        assert!(err.to_string().contains("No remote 'origin'"));
+    }
+
+    #[test]
+    fn test_forge_config_defaults() {
+        let config: PrConfig = serde_yaml::from_str("forge: gitlab\n").unwrap();
+        assert_eq!(config.forge, Some(ForgeKind::Gitlab));
+        assert_eq!(config.gitlab.host, "gitlab.com");
+        assert_eq!(config.gitlab.token_env, "GITLAB_TOKEN");
+        assert_eq!(config.github.host, "github.com");
+
+        assert_eq!(ForgeKind::Github.reference(7), "PR #7");
+        assert_eq!(ForgeKind::Gitlab.reference(7), "MR !7");
+    }
+}
@@ -0,0 +1,765 @@
+//! GitLab forge
+//!
+//! Merge requests through the GitLab REST API (v4). Review threads are MR
+//! discussions anchored to a diff position; replies and resolution go
+//! through the discussion endpoints.
+
+use crate::config::{ForgeKind, GitLabConfig};
+use crate::error::{Error, Result};
+use crate::forge::{
+    CheckResult, CheckState, CreatedPullRequest, Forge, NewPullRequest, PrDetails, ReviewerDecision,
+};
+use crate::git;
+use crate::http::{self, ApiClient, ClientOptions};
+use crate::models::{PrStatus, ReviewComment, ReviewThread};
+use async_trait::async_trait;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// GitLab forge bound to one project
+pub struct GitLabForge {
+    client: ApiClient,
+    /// URL-encoded project path, e.g. `group%2Fsub%2Frepo`
+    project: String,
+}
+
+impl GitLabForge {
+    /// Connect to the project behind `remote_url`
+    ///
+    /// The API is served from the remote's host unless `api_base_url` is set.
+    pub fn connect(
+        gitlab: &GitLabConfig,
+        token: String,
+        remote_url: &str,
+        options: &ClientOptions,
+    ) -> Result<Self> {
+        let remote = git::parse_remote_url(remote_url)?;
+        let api_url = match &gitlab.api_base_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => format!("https://{}/api/v4", remote.host),
+        };
+
+        Ok(Self {
+            client: ApiClient::new("GitLab", &api_url, "PRIVATE-TOKEN", token, options),
+            project: format!("{}/{}", remote.owner, remote.repo).replace('/', "%2F"),
+        })
+    }
+
+    fn mr_route(&self, number: u64) -> String {
+        format!("/projects/{}/merge_requests/{}", self.project, number)
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        route: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<T> {
+        self.client.request(method, route, body).await
+    }
+
+    async fn get<T: DeserializeOwned>(&self, route: &str) -> Result<T> {
+        self.client.get(route).await
+    }
+
+    /// Every page of a list endpoint
+    async fn get_all<T: DeserializeOwned>(&self, route: &str) -> Result<Vec<T>> {
+        let separator = if route.contains('?') { '&' } else { '?' };
+        let mut items = Vec::new();
+        for page in 1.. {
+            let batch: Vec<T> = self
+                .get(&format!("{}{}per_page=100&page={}", route, separator, page))
+                .await?;
+            let done = batch.len() < 100;
+            items.extend(batch);
+            if done {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// User IDs for usernames, as MR reviewers are set by ID
+    async fn user_ids(&self, usernames: &[String]) -> Result<Vec<u64>> {
+        let mut ids = Vec::new();
+        for username in usernames {
+            let users: Vec<User> = self.get(&format!("/users?username={}", username)).await?;
+            let user = users
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::Other(format!("GitLab user '{}' not found", username)))?;
+            ids.push(user.id);
+        }
+        Ok(ids)
+    }
+}
+
+#[async_trait]
+impl Forge for GitLabForge {
+    fn kind(&self) -> ForgeKind {
+        ForgeKind::Gitlab
+    }
+
+    async fn create(&self, request: &NewPullRequest) -> Result<CreatedPullRequest> {
+        let title = if request.draft {
+            format!("Draft: {}", request.title)
+        } else {
+            request.title.clone()
+        };
+        let payload = serde_json::json!({
+            "source_branch": request.head,
+            "target_branch": request.base,
+            "title": title,
+            "description": request.body,
+        });
+
+        let mr: MergeRequest = self
+            .request(
+                Method::POST,
+                &format!("/projects/{}/merge_requests", self.project),
+                Some(payload),
+            )
+            .await
+            .map_err(http::context("Failed to create merge request"))?;
+
+        Ok(CreatedPullRequest {
+            number: mr.iid,
+            url: mr.web_url,
+        })
+    }
+
+    async fn request_reviewers(
+        &self,
+        number: u64,
+        reviewers: &[String],
+        _team_reviewers: &[String],
+    ) -> Result<()> {
+        if reviewers.is_empty() {
+            return Ok(());
+        }
+        let payload = serde_json::json!({ "reviewer_ids": self.user_ids(reviewers).await? });
+        let _: serde_json::Value = self
+            .request(Method::PUT, &self.mr_route(number), Some(payload))
+            .await
+            .map_err(http::context("Failed to request reviewers"))?;
+        Ok(())
+    }
+
+    async fn description(&self, number: u64) -> Result<String> {
+        let mr: MergeRequest = self.get(&self.mr_route(number)).await?;
+        Ok(mr.description.unwrap_or_default())
+    }
+
+    async fn update_description(&self, number: u64, title: Option<&str>, body: &str) -> Result<()> {
+        let mut payload = serde_json::json!({ "description": body });
+        if let Some(title) = title {
+            payload["title"] = title.into();
+        }
+        let _: serde_json::Value = self
+            .request(Method::PUT, &self.mr_route(number), Some(payload))
+            .await
+            .map_err(http::context("Failed to update merge request"))?;
+        Ok(())
+    }
+
+    async fn add_labels(&self, number: u64, labels: &[String]) -> Result<()> {
+        let _: serde_json::Value = self
+            .request(
+                Method::PUT,
+                &self.mr_route(number),
+                Some(serde_json::json!({ "add_labels": labels.join(",") })),
+            )
+            .await
+            .map_err(http::context("Failed to add labels"))?;
+        Ok(())
+    }
+
+    async fn retarget(&self, number: u64, base: &str) -> Result<()> {
+        let _: serde_json::Value = self
+            .request(
+                Method::PUT,
+                &self.mr_route(number),
+                Some(serde_json::json!({ "target_branch": base })),
+            )
+            .await
+            .map_err(http::context("Failed to retarget merge request"))?;
+        Ok(())
+    }
+
+    async fn review_threads(&self, number: u64) -> Result<Vec<ReviewThread>> {
+        let mr: MergeRequest = self.get(&self.mr_route(number)).await?;
+        let discussions: Vec<Discussion> = self
+            .get_all(&format!("{}/discussions", self.mr_route(number)))
+            .await?;
+
+        let head_sha = mr.diff_refs.and_then(|refs| refs.head_sha);
+        Ok(discussions
+            .into_iter()
+            .filter_map(|d| d.into_thread(head_sha.as_deref()))
+            .collect())
+    }
+
+    async fn reply(
+        &self,
+        number: u64,
+        thread_id: &str,
+        _comment_id: u64,
+        body: &str,
+    ) -> Result<()> {
+        let route = format!("{}/discussions/{}/notes", self.mr_route(number), thread_id);
+        let _: serde_json::Value = self
+            .request(
+                Method::POST,
+                &route,
+                Some(serde_json::json!({ "body": body })),
+            )
+            .await
+            .map_err(http::context("Failed to reply to comment"))?;
+        Ok(())
+    }
+
+    async fn resolve_thread(&self, number: u64, thread_id: &str) -> Result<()> {
+        let route = format!(
+            "{}/discussions/{}?resolved=true",
+            self.mr_route(number),
+            thread_id
+        );
+        let _: serde_json::Value = self
+            .request(Method::PUT, &route, None)
+            .await
+            .map_err(http::context("Failed to resolve thread"))?;
+        Ok(())
+    }
+
+    /// GitLab has no review summaries; post an MR note instead
+    async fn submit_review(&self, number: u64, body: &str) -> Result<()> {
+        let route = format!("{}/notes", self.mr_route(number));
+        let _: serde_json::Value = self
+            .request(
+                Method::POST,
+                &route,
+                Some(serde_json::json!({ "body": body })),
+            )
+            .await
+            .map_err(http::context("Failed to submit review"))?;
+        Ok(())
+    }
+
+    async fn status(&self, number: u64) -> Result<PrDetails> {
+        let mr: MergeRequest = self
+            .get(&format!(
+                "{}?include_diverged_commits_count=true",
+                self.mr_route(number)
+            ))
+            .await
+            .map_err(http::context("Failed to fetch merge request"))?;
+        let approvals: Approvals = self
+            .get(&format!("{}/approvals", self.mr_route(number)))
+            .await?;
+        let jobs: Vec<Job> = match &mr.head_pipeline {
+            Some(pipeline) => {
+                self.get_all(&format!(
+                    "/projects/{}/pipelines/{}/jobs",
+                    self.project, pipeline.id
+                ))
+                .await?
+            }
+            None => Vec::new(),
+        };
+
+        Ok(mr_details(mr, approvals, jobs))
+    }
+}
+
+// === REST response types ===
+
+#[derive(Deserialize)]
+struct User {
+    #[serde(default)]
+    id: u64,
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct MergeRequest {
+    iid: u64,
+    web_url: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    has_conflicts: bool,
+    #[serde(default)]
+    detailed_merge_status: Option<String>,
+    #[serde(default)]
+    source_branch: String,
+    #[serde(default)]
+    target_branch: String,
+    #[serde(default)]
+    sha: Option<String>,
+    #[serde(default)]
+    diverged_commits_count: Option<u64>,
+    #[serde(default)]
+    reviewers: Vec<User>,
+    #[serde(default)]
+    head_pipeline: Option<Pipeline>,
+    #[serde(default)]
+    diff_refs: Option<DiffRefs>,
+}
+
+#[derive(Deserialize)]
+struct Pipeline {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct DiffRefs {
+    head_sha: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Approvals {
+    #[serde(default)]
+    approvals_required: u32,
+    #[serde(default)]
+    approvals_left: u32,
+    #[serde(default)]
+    approved_by: Vec<Approval>,
+}
+
+#[derive(Deserialize)]
+struct Approval {
+    user: User,
+}
+
+#[derive(Deserialize)]
+struct Job {
+    name: String,
+    status: String,
+    #[serde(default)]
+    allow_failure: bool,
+    #[serde(default)]
+    failure_reason: Option<String>,
+    #[serde(default)]
+    web_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Discussion {
+    id: String,
+    #[serde(default)]
+    notes: Vec<Note>,
+}
+
+#[derive(Deserialize)]
+struct Note {
+    id: u64,
+    body: String,
+    author: Option<User>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    system: bool,
+    #[serde(default)]
+    resolvable: bool,
+    #[serde(default)]
+    resolved: bool,
+    #[serde(default)]
+    position: Option<Position>,
+}
+
+#[derive(Deserialize)]
+struct Position {
+    new_path: Option<String>,
+    old_path: Option<String>,
+    new_line: Option<u32>,
+    old_line: Option<u32>,
+    #[serde(default)]
+    head_sha: Option<String>,
+    #[serde(default)]
+    line_range: Option<LineRange>,
+}
+
+#[derive(Deserialize)]
+struct LineRange {
+    start: Option<LinePoint>,
+}
+
+#[derive(Deserialize)]
+struct LinePoint {
+    new_line: Option<u32>,
+    old_line: Option<u32>,
+}
+
+impl Discussion {
+    /// A resolvable discussion on the diff as a review thread
+    ///
+    /// General MR comments and system notes are skipped. A thread is
+    /// outdated when its position no longer refers to the MR head.
+    fn into_thread(self, head_sha: Option<&str>) -> Option<ReviewThread> {
+        let root = self.notes.first()?;
+        if root.system || !root.resolvable {
+            return None;
+        }
+        let position = root.position.as_ref()?;
+        let path = position
+            .new_path
+            .clone()
+            .or_else(|| position.old_path.clone())?;
+        let line = position.new_line.or(position.old_line);
+        let start_line = position
+            .line_range
+            .as_ref()
+            .and_then(|range| range.start.as_ref())
+            .and_then(|start| start.new_line.or(start.old_line))
+            .filter(|start| Some(*start) != line);
+        let is_outdated = matches!(
+            (head_sha, position.head_sha.as_deref()),
+            (Some(head), Some(anchored)) if head != anchored
+        );
+        let is_resolved = self
+            .notes
+            .iter()
+            .filter(|n| n.resolvable)
+            .all(|n| n.resolved);
+
+        let comments = self
+            .notes
+            .into_iter()
+            .filter(|n| !n.system)
+            .map(|n| ReviewComment {
+                id: n.id,
+                path: path.clone(),
+                line,
+                body: n.body,
+                author: n
+                    .author
+                    .map(|a| a.username)
+                    .unwrap_or_else(|| "ghost".to_string()),
+                created_at: n.created_at,
+                updated_at: n.updated_at,
+                diff_hunk: None,
+            })
+            .collect();
+
+        Some(ReviewThread {
+            id: self.id,
+            path,
+            line,
+            start_line,
+            original_line: line,
+            is_resolved,
+            is_outdated,
+            comments,
+            anchor: None,
+        })
+    }
+}
+
+/// Map an MR onto the GitHub-shaped status `hp pr status` prints
+fn mr_details(mr: MergeRequest, approvals: Approvals, jobs: Vec<Job>) -> PrDetails {
+    let state = match (mr.state.as_str(), mr.draft) {
+        ("merged", _) => PrStatus::Merged,
+        ("closed" | "locked", _) => PrStatus::Closed,
+        (_, true) => PrStatus::Draft,
+        _ => PrStatus::Open,
+    };
+
+    let merge_status = mr.detailed_merge_status.as_deref().unwrap_or("unchecked");
+    let merge_state = match merge_status {
+        _ if mr.has_conflicts => "DIRTY",
+        "mergeable" => "CLEAN",
+        "need_rebase" => "BEHIND",
+        "conflict" | "broken_status" => "DIRTY",
+        "draft_status" => "DRAFT",
+        "checking" | "unchecked" | "preparing" | "approvals_syncing" => "UNKNOWN",
+        _ => "BLOCKED",
+    };
+    let mergeable = match merge_state {
+        "DIRTY" => "CONFLICTING",
+        "UNKNOWN" => "UNKNOWN",
+        _ => "MERGEABLE",
+    };
+
+    let review_decision = if merge_status == "requested_changes" {
+        Some("CHANGES_REQUESTED".to_string())
+    } else if approvals.approvals_required == 0 {
+        None
+    } else if approvals.approvals_left == 0 {
+        Some("APPROVED".to_string())
+    } else {
+        Some("REVIEW_REQUIRED".to_string())
+    };
+
+    let reviews: Vec<ReviewerDecision> = approvals
+        .approved_by
+        .into_iter()
+        .map(|a| ReviewerDecision {
+            reviewer: a.user.username,
+            state: "APPROVED".to_string(),
+        })
+        .collect();
+    let requested_reviewers = mr
+        .reviewers
+        .into_iter()
+        .map(|u| u.username)
+        .filter(|name| !reviews.iter().any(|r| &r.reviewer == name))
+        .collect();
+
+    let checks = jobs
+        .into_iter()
+        .map(|job| CheckResult {
+            state: match job.status.as_str() {
+                "success" => CheckState::Success,
+                "failed" | "canceled" => CheckState::Failure,
+                "skipped" | "manual" => CheckState::Skipped,
+                _ => CheckState::Pending,
+            },
+            name: job.name,
+            required: !job.allow_failure,
+            summary: job.failure_reason,
+            url: job.web_url,
+        })
+        .collect();
+
+    PrDetails {
+        kind: ForgeKind::Gitlab,
+        number: mr.iid,
+        title: mr.title,
+        url: mr.web_url,
+        state,
+        mergeable: mergeable.to_string(),
+        merge_state: merge_state.to_string(),
+        review_decision,
+        reviews,
+        requested_reviewers,
+        checks,
+        base_ref: mr.target_branch,
+        head_ref: mr.source_branch,
+        head_oid: mr.sha.unwrap_or_default(),
+        behind_by: mr.diverged_commits_count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_forge(server: &mockito::Server) -> GitLabForge {
+        let gitlab = GitLabConfig {
+            api_base_url: Some(format!("{}/api/v4", server.url())),
+            ..GitLabConfig::default()
+        };
+        GitLabForge::connect(
+            &gitlab,
+            "token".to_string(),
+            "git@gitlab.example.com:group/sub/repo.git",
+            &ClientOptions::default(),
+        )
+        .unwrap()
+    }
+
+    fn note(id: u64, author: &str, body: &str, resolved: bool) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "body": body,
+            "author": { "id": 1, "username": author },
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+            "system": false,
+            "resolvable": true,
+            "resolved": resolved,
+            "position": {
+                "new_path": "src/lib.rs",
+                "old_path": "src/lib.rs",
+                "new_line": 12,
+                "old_line": null,
+                "head_sha": "abc",
+                "line_range": { "start": { "new_line": 10, "old_line": null } }
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_review_threads_from_discussions() {
+        let mut server = mockito::Server::new_async().await;
+        let mr = server
+            .mock("GET", "/api/v4/projects/group%2Fsub%2Frepo/merge_requests/3")
+            .match_header("private-token", "token")
+            .with_body(
+                r#"{"iid":3,"web_url":"https://gitlab.example.com/mr/3","diff_refs":{"head_sha":"abc"}}"#,
+            )
+            .create_async()
+            .await;
+        let discussions = server
+            .mock(
+                "GET",
+                "/api/v4/projects/group%2Fsub%2Frepo/merge_requests/3/discussions",
+            )
+            .match_query(mockito::Matcher::Any)
+            .with_body(
+                serde_json::json!([
+                    {
+                        "id": "d1",
+                        "notes": [note(11, "alice", "Rename", false), note(12, "bob", "Why?", false)]
+                    },
+                    { "id": "d2", "notes": [note(13, "alice", "Typo", true)] },
+                    {
+                        "id": "d3",
+                        "notes": [{
+                            "id": 14, "body": "added 1 commit", "author": null,
+                            "created_at": "2024-01-01T00:00:00Z", "system": true
+                        }]
+                    }
+                ])
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let threads = mock_forge(&server).review_threads(3).await.unwrap();
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].id, "d1");
+        assert_eq!(threads[0].path, "src/lib.rs");
+        assert_eq!(
+            (threads[0].start_line, threads[0].line),
+            (Some(10), Some(12))
+        );
+        assert_eq!(threads[0].replies()[0].author, "bob");
+        assert!(!threads[0].is_resolved && !threads[0].is_outdated);
+        assert!(threads[1].is_resolved);
+
+        mr.assert_async().await;
+        discussions.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_reply_resolve_and_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let reply = server
+            .mock(
+                "POST",
+                "/api/v4/projects/group%2Fsub%2Frepo/merge_requests/3/discussions/d1/notes",
+            )
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "body": "Done" }),
+            ))
+            .with_status(201)
+            .with_body("{}")
+            .create_async()
+            .await;
+        let resolve = server
+            .mock(
+                "PUT",
+                "/api/v4/projects/group%2Fsub%2Frepo/merge_requests/3/discussions/d1",
+            )
+            .match_query(mockito::Matcher::UrlEncoded(
+                "resolved".to_string(),
+                "true".to_string(),
+            ))
+            .with_body("{}")
+            .create_async()
+            .await;
+        server
+            .mock(
+                "POST",
+                "/api/v4/projects/group%2Fsub%2Frepo/merge_requests/3/notes",
+            )
+            .with_status(401)
+            .with_body(r#"{"message":"401 Unauthorized"}"#)
+            .create_async()
+            .await;
+
+        let forge = mock_forge(&server);
+        forge.reply(3, "d1", 11, "Done").await.unwrap();
+        forge.resolve_thread(3, "d1").await.unwrap();
+        let err = forge.submit_review(3, "Summary").await.unwrap_err();
+        assert!(err.to_string().contains("401 Unauthorized"));
+
+        reply.assert_async().await;
+        resolve.assert_async().await;
+    }
+
+    #[test]
+    fn test_mr_details() {
+        let mr: MergeRequest = serde_json::from_value(serde_json::json!({
+            "iid": 3,
+            "web_url": "https://gitlab.example.com/mr/3",
+            "title": "Add auth",
+            "state": "opened",
+            "draft": false,
+            "has_conflicts": false,
+            "detailed_merge_status": "not_approved",
+            "source_branch": "auth",
+            "target_branch": "main",
+            "sha": "abc",
+            "diverged_commits_count": 2,
+            "reviewers": [{ "id": 1, "username": "alice" }, { "id": 2, "username": "bob" }],
+            "head_pipeline": { "id": 9 }
+        }))
+        .unwrap();
+        let approvals: Approvals = serde_json::from_value(serde_json::json!({
+            "approvals_required": 2,
+            "approvals_left": 1,
+            "approved_by": [{ "user": { "id": 1, "username": "alice" } }]
+        }))
+        .unwrap();
+        let jobs: Vec<Job> = serde_json::from_value(serde_json::json!([
+            { "name": "test", "status": "failed", "allow_failure": false,
+              "failure_reason": "script_failure", "web_url": "https://ci/1" },
+            { "name": "lint", "status": "running", "allow_failure": true },
+            { "name": "deploy", "status": "manual", "allow_failure": false }
+        ]))
+        .unwrap();
+
+        let details = mr_details(mr, approvals, jobs);
+        assert_eq!(details.state, PrStatus::Open);
+        assert_eq!(details.merge_state, "BLOCKED");
+        assert_eq!(details.review_decision.as_deref(), Some("REVIEW_REQUIRED"));
+        assert_eq!(details.reviews[0].reviewer, "alice");
+        assert_eq!(details.requested_reviewers, vec!["bob"]);
+        assert_eq!(details.behind_by, Some(2));
+
+        let states: Vec<CheckState> = details.checks.iter().map(|c| c.state).collect();
+        assert_eq!(
+            states,
+            vec![
+                CheckState::Failure,
+                CheckState::Pending,
+                CheckState::Skipped
+            ]
+        );
+        assert!(details.checks[0].required && !details.checks[1].required);
+    }
+
+    #[test]
+    fn test_mr_details_merged_and_conflicting() {
+        let merged: MergeRequest = serde_json::from_value(serde_json::json!({
+            "iid": 1, "web_url": "u", "state": "merged", "detailed_merge_status": "not_open"
+        }))
+        .unwrap();
+        let details = mr_details(merged, serde_json::from_str("{}").unwrap(), Vec::new());
+        assert_eq!(details.state, PrStatus::Merged);
+        assert!(details.review_decision.is_none());
+
+        let conflicting: MergeRequest = serde_json::from_value(serde_json::json!({
+            "iid": 2, "web_url": "u", "state": "opened", "draft": true,
+            "has_conflicts": true, "detailed_merge_status": "mergeable"
+        }))
+        .unwrap();
+        let details = mr_details(conflicting, serde_json::from_str("{}").unwrap(), Vec::new());
+        assert_eq!(details.state, PrStatus::Draft);
+        assert_eq!(details.mergeable, "CONFLICTING");
+        assert_eq!(
+            details.merge_state_reason(),
+            "merge conflicts must be resolved"
+        );
+    }
+}
@@ -0,0 +1,557 @@
+//! HTTP client for forge APIs
+//!
+//! Transient failures (5xx, 429 and rate-limited 403s) are retried with
+//! exponential backoff, honoring `Retry-After` and `X-RateLimit-Reset`.
+//! GET responses are cached with their ETag and revalidated with
+//! `If-None-Match`; GitHub doesn't count 304s against the rate limit.
+//! Failing to reach the server at all is [`Error::Network`], which callers
+//! use to queue outbound actions.
+
+use crate::error::{Error, Result};
+use reqwest::header::{HeaderMap, ETAG, IF_NONE_MATCH, RETRY_AFTER, USER_AGENT};
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How hard to retry transient failures
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Backoff before the first retry; doubled for each further one
+    pub base_delay: Duration,
+    /// Longest wait for a retry; longer rate-limit resets fail instead
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Retry and caching settings shared by the forges
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    pub retry: RetryPolicy,
+    /// File holding cached GET responses; no caching when unset
+    pub etag_cache: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedResponse {
+    etag: String,
+    body: String,
+}
+
+/// JSON API client for one base URL
+pub struct ApiClient {
+    client: reqwest::Client,
+    /// "GitHub" or "GitLab", for error messages
+    name: &'static str,
+    base_url: String,
+    auth_header: &'static str,
+    auth_value: String,
+    retry: RetryPolicy,
+    etag_cache: Option<PathBuf>,
+    etags: Mutex<Option<HashMap<String, CachedResponse>>>,
+}
+
+impl ApiClient {
+    /// Client sending `auth_header: auth_value` with every request
+    pub fn new(
+        name: &'static str,
+        base_url: &str,
+        auth_header: &'static str,
+        auth_value: String,
+        options: &ClientOptions,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            name,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth_header,
+            auth_value,
+            retry: options.retry,
+            etag_cache: options.etag_cache.clone(),
+            etags: Mutex::new(None),
+        }
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, route: &str) -> Result<T> {
+        self.request(Method::GET, route, None).await
+    }
+
+    pub async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        route: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<T> {
+        let idempotent = method == Method::GET;
+        let text = self.send(method, route, body.as_ref(), idempotent).await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// A POST that only reads, such as a GraphQL query, retried like a GET
+    pub async fn post_read<T: DeserializeOwned>(
+        &self,
+        route: &str,
+        body: serde_json::Value,
+    ) -> Result<T> {
+        let text = self.send(Method::POST, route, Some(&body), true).await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Send a request and return the body of its successful response
+    ///
+    /// Only `idempotent` requests are retried after a 5xx or a timeout;
+    /// writes may have been applied.
+    async fn send(
+        &self,
+        method: Method,
+        route: &str,
+        body: Option<&serde_json::Value>,
+        idempotent: bool,
+    ) -> Result<String> {
+        let url = format!("{}{}", self.base_url, route);
+        let cached = if method == Method::GET {
+            self.cached(&url)
+        } else {
+            None
+        };
+
+        let mut attempt = 0;
+        loop {
+            let mut request = self
+                .client
+                .request(method.clone(), &url)
+                .header(self.auth_header, &self.auth_value)
+                .header(USER_AGENT, "hupasiya");
+            if let Some(cached) = &cached {
+                request = request.header(IF_NONE_MATCH, &cached.etag);
+            }
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(e) if e.is_connect() || (idempotent && e.is_timeout()) => {
+                    if attempt < self.retry.max_retries {
+                        tokio::time::sleep(backoff(&self.retry, attempt)).await;
+                        attempt += 1;
+                        continue;
+                    }
+                    return Err(Error::Network(format!(
+                        "Could not reach {}: {}",
+                        self.name, e
+                    )));
+                }
+                Err(e) => return Err(Error::Other(format!("{} request failed: {}", self.name, e))),
+            };
+
+            let status = response.status();
+            let headers = response.headers().clone();
+            if status == StatusCode::NOT_MODIFIED {
+                if let Some(cached) = cached {
+                    return Ok(cached.body);
+                }
+            }
+            let text = response
+                .text()
+                .await
+                .map_err(|e| Error::Other(format!("{} request failed: {}", self.name, e)))?;
+
+            if let Some(delay) = retry_delay(
+                status,
+                &headers,
+                &text,
+                idempotent,
+                attempt,
+                &self.retry,
+                chrono::Utc::now().timestamp(),
+            ) {
+                if attempt < self.retry.max_retries {
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    continue;
+                }
+            }
+
+            if !status.is_success() {
+                return Err(Error::Other(format!(
+                    "{} API error ({}): {}",
+                    self.name,
+                    status,
+                    error_message(&text)
+                )));
+            }
+
+            if method == Method::GET {
+                if let Some(etag) = headers.get(ETAG).and_then(|v| v.to_str().ok()) {
+                    self.store(&url, etag, &text);
+                }
+            }
+            return Ok(text);
+        }
+    }
+
+    fn cached(&self, url: &str) -> Option<CachedResponse> {
+        let mut etags = self.etags.lock().ok()?;
+        let path = self.etag_cache.as_ref()?;
+        etags
+            .get_or_insert_with(|| {
+                std::fs::read_to_string(path)
+                    .ok()
+                    .and_then(|content| serde_json::from_str(&content).ok())
+                    .unwrap_or_default()
+            })
+            .get(url)
+            .cloned()
+    }
+
+    /// Cache a response; a cache that can't be written is skipped
+    fn store(&self, url: &str, etag: &str, body: &str) {
+        let (Some(path), Ok(mut etags)) = (&self.etag_cache, self.etags.lock()) else {
+            return;
+        };
+        let etags = etags.get_or_insert_with(HashMap::new);
+        etags.insert(
+            url.to_string(),
+            CachedResponse {
+                etag: etag.to_string(),
+                body: body.to_string(),
+            },
+        );
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(content) = serde_json::to_string(etags) {
+            let _ = std::fs::write(path, content);
+        }
+    }
+}
+
+/// Exponential backoff for the given retry attempt
+fn backoff(policy: &RetryPolicy, attempt: u32) -> Duration {
+    policy
+        .base_delay
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(policy.max_delay)
+}
+
+/// How long to wait before retrying a response, or None if it shouldn't be
+///
+/// 429 is retried, as are 403s that are rate limits (GitHub's secondary
+/// limits send `Retry-After` or say so in the message). 5xx is retried only
+/// for `idempotent` requests, since a write may have gone through. Waits
+/// longer than the policy allows aren't retried.
+fn retry_delay(
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: &str,
+    idempotent: bool,
+    attempt: u32,
+    policy: &RetryPolicy,
+    now: i64,
+) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let retry_after = header(RETRY_AFTER.as_str())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    let exhausted = header("x-ratelimit-remaining") == Some("0");
+    let reset = header("x-ratelimit-reset")
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .map(|reset| Duration::from_secs((reset - now).max(0) as u64));
+
+    let rate_limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN
+            && (retry_after.is_some() || exhausted || body.to_lowercase().contains("rate limit")));
+    let transient = idempotent
+        && matches!(
+            status,
+            StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        );
+    if !rate_limited && !transient {
+        return None;
+    }
+
+    let delay = match (retry_after, exhausted, reset) {
+        (Some(delay), _, _) => delay,
+        (None, true, Some(reset)) => reset,
+        _ => backoff(policy, attempt),
+    };
+    (delay <= policy.max_delay).then_some(delay)
+}
+
+/// Prefix an error with what failed, keeping network errors distinguishable
+pub fn context(what: &'static str) -> impl Fn(Error) -> Error {
+    move |e| match e {
+        Error::Network(message) => Error::Network(format!("{}: {}", what, message)),
+        e => Error::Other(format!("{}: {}", what, e)),
+    }
+}
+
+/// The `message` (GitHub, GitLab) or `error` of an error response
+pub fn error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| {
+            v.get("message")
+                .or_else(|| v.get("error"))
+                .map(|m| match m.as_str() {
+                    Some(s) => s.to_string(),
+                    None => m.to_string(),
+                })
+        })
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fast() -> ClientOptions {
+        ClientOptions {
+            retry: RetryPolicy {
+                max_retries: 2,
+                base_delay: Duration::ZERO,
+                max_delay: Duration::from_secs(60),
+            },
+            etag_cache: None,
+        }
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RetryPolicy::default();
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut map = HeaderMap::new();
+            for (name, value) in pairs {
+                map.insert(*name, value.parse().unwrap());
+            }
+            map
+        };
+        let none = HeaderMap::new();
+
+        assert_eq!(
+            retry_delay(StatusCode::BAD_GATEWAY, &none, "", true, 2, &policy, 0),
+            Some(Duration::from_secs(4))
+        );
+        // A write that failed server-side may still have been applied
+        assert_eq!(
+            retry_delay(StatusCode::BAD_GATEWAY, &none, "", false, 0, &policy, 0),
+            None
+        );
+        assert_eq!(
+            retry_delay(StatusCode::NOT_FOUND, &none, "", true, 0, &policy, 0),
+            None
+        );
+        // Secondary rate limits are retried, even for writes
+        assert_eq!(
+            retry_delay(
+                StatusCode::FORBIDDEN,
+                &headers(&[("retry-after", "30")]),
+                "",
+                false,
+                0,
+                &policy,
+                0
+            ),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            retry_delay(
+                StatusCode::FORBIDDEN,
+                &none,
+                r#"{"message":"You have exceeded a secondary rate limit"}"#,
+                false,
+                0,
+                &policy,
+                0
+            ),
+            Some(Duration::from_secs(1))
+        );
+        // Plain permission errors aren't retried
+        assert_eq!(
+            retry_delay(
+                StatusCode::FORBIDDEN,
+                &none,
+                r#"{"message":"Resource not accessible"}"#,
+                false,
+                0,
+                &policy,
+                0
+            ),
+            None
+        );
+        // Primary limit: wait for the reset, unless it is too far away
+        let exhausted =
+            |reset: &str| headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", reset)]);
+        assert_eq!(
+            retry_delay(
+                StatusCode::FORBIDDEN,
+                &exhausted("1010"),
+                "",
+                true,
+                0,
+                &policy,
+                1000
+            ),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            retry_delay(
+                StatusCode::FORBIDDEN,
+                &exhausted("4600"),
+                "",
+                true,
+                0,
+                &policy,
+                1000
+            ),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let unavailable = server
+            .mock("GET", "/flaky")
+            .with_status(503)
+            .expect(3)
+            .create_async()
+            .await;
+        let client = ApiClient::new(
+            "GitHub",
+            &server.url(),
+            "Authorization",
+            "t".into(),
+            &fast(),
+        );
+
+        let err = client.get::<serde_json::Value>("/flaky").await.unwrap_err();
+        assert!(err.to_string().contains("503"));
+        // Two retries after the first attempt
+        unavailable.assert_async().await;
+
+        let failed = server
+            .mock("POST", "/write")
+            .with_status(502)
+            .expect(1)
+            .create_async()
+            .await;
+        assert!(client
+            .request::<serde_json::Value>(Method::POST, "/write", Some(serde_json::json!({})))
+            .await
+            .is_err());
+        failed.assert_async().await;
+
+        // A POST that only reads is retried like a GET
+        let query = server
+            .mock("POST", "/graphql")
+            .with_status(502)
+            .expect(3)
+            .create_async()
+            .await;
+        assert!(client
+            .post_read::<serde_json::Value>("/graphql", serde_json::json!({}))
+            .await
+            .is_err());
+        query.assert_async().await;
+
+        let limited = server
+            .mock("POST", "/limited")
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .expect(1)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("POST", "/limited")
+            .with_body(r#"{"ok":true}"#)
+            .create_async()
+            .await;
+        let value: serde_json::Value = client
+            .request(Method::POST, "/limited", Some(serde_json::json!({})))
+            .await
+            .unwrap();
+        assert_eq!(value["ok"], true);
+        limited.assert_async().await;
+        ok.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_etag_revalidation() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let options = ClientOptions {
+            etag_cache: Some(dir.path().join("etags.json")),
+            ..fast()
+        };
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/pulls/1")
+            .match_header("if-none-match", mockito::Matcher::Missing)
+            .with_header("etag", "\"v1\"")
+            .with_body(r#"{"title":"Cached"}"#)
+            .create_async()
+            .await;
+        let revalidated = server
+            .mock("GET", "/pulls/1")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .create_async()
+            .await;
+
+        let client = ApiClient::new(
+            "GitHub",
+            &server.url(),
+            "Authorization",
+            "t".into(),
+            &options,
+        );
+        let pr: serde_json::Value = client.get("/pulls/1").await.unwrap();
+        assert_eq!(pr["title"], "Cached");
+
+        // A new client reads the cache from disk
+        let client = ApiClient::new(
+            "GitHub",
+            &server.url(),
+            "Authorization",
+            "t".into(),
+            &options,
+        );
+        let pr: serde_json::Value = client.get("/pulls/1").await.unwrap();
+        assert_eq!(pr["title"], "Cached");
+
+        first.assert_async().await;
+        revalidated.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_is_network_error() {
+        // Nothing listens on the discard port
+        let client = ApiClient::new(
+            "GitLab",
+            "http://127.0.0.1:9",
+            "PRIVATE-TOKEN",
+            "t".into(),
+            &fast(),
+        );
+        let err = client.get::<serde_json::Value>("/user").await.unwrap_err();
+        assert!(matches!(err, Error::Network(_)));
+    }
+}
@@ -7,22 +7,38 @@
 #![allow(dead_code)]
 
 pub mod activity;
+pub mod adapters;
 pub mod ai_tool;
+pub mod analyzer;
+pub mod anchor;
+pub mod auth;
 pub mod cli;
+pub mod codeowners;
 pub mod collaboration;
 pub mod config;
 pub mod context;
 pub mod error;
+pub mod fixes;
+pub mod forge;
+pub mod git;
+pub mod github;
+pub mod gitlab;
 pub mod hn_client;
+pub mod http;
+pub mod merge;
 pub mod models;
 pub mod orchestration;
 pub mod pr;
+pub mod pr_body;
+pub mod processes;
 pub mod profiles;
 pub mod progress;
+pub mod queue;
 pub mod session;
 pub mod shepherd;
 pub mod templates;
 pub mod tutorial;
 pub mod utilities;
+pub mod workspace;
 
 pub use error::{Error, Result};
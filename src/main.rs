@@ -2,24 +2,40 @@ use anyhow::Result;
 use clap::{Args, Parser, Subcommand};
 
 mod activity;
+mod adapters;
 mod ai_tool;
+mod analyzer;
+mod anchor;
+mod auth;
 mod cli;
+mod codeowners;
 mod collaboration;
 mod completions;
 mod config;
 mod context;
 mod error;
+mod fixes;
+mod forge;
+mod git;
+mod github;
+mod gitlab;
 mod hn_client;
+mod http;
+mod merge;
 mod models;
 mod orchestration;
 mod pr;
+mod pr_body;
+mod processes;
 mod profiles;
 mod progress;
+mod queue;
 mod session;
 mod shepherd;
 mod templates;
 mod tutorial;
 mod utilities;
+mod workspace;
 
 /// hupasiya - Multi-agent session orchestrator
 #[derive(Parser)]
@@ -150,6 +166,54 @@ enum Commands {
         extra_args: Vec<String>,
     },
 
+    /// Run AI tool non-interactively on a prompt, capturing its output
+    Run {
+        /// Session name
+        session: String,
+
+        /// File with the task prompt (`-` for stdin)
+        #[arg(long)]
+        prompt_file: std::path::PathBuf,
+
+        /// Override AI tool command
+        #[arg(long)]
+        tool: Option<String>,
+
+        /// Configuration profile to use
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Seconds before the run is stopped (default: ai_tool.timeout_secs)
+        #[arg(long)]
+        timeout: Option<u64>,
+
+        /// Don't print the tool's output as it runs
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// List running AI agents
+    Ps {
+        /// Only show agents for this session
+        session: Option<String>,
+    },
+
+    /// Stop the AI agents running for a session
+    Stop {
+        /// Session name
+        session: String,
+    },
+
+    /// Record an agent's exit (called by the tmux/screen launch wrapper)
+    #[command(hide = true)]
+    AgentExited {
+        /// Registry id of the agent
+        id: String,
+
+        /// Exit code
+        code: Option<i32>,
+    },
+
     /// Launch shell in session workbox
     Shell {
         /// Session name (or use HP_SESSION env var)
@@ -183,6 +247,9 @@ enum Commands {
 
     /// Shepherd - Interactive PR comment resolution
     Shepherd {
+        #[command(subcommand)]
+        command: Option<ShepherdSubcommand>,
+
         /// Session name
         session: Option<String>,
 
@@ -194,9 +261,37 @@ enum Commands {
         #[arg(long)]
         auto_fix: bool,
 
-        /// Show status only
+        /// Show status only (same as 'hp shepherd status')
         #[arg(long)]
         status: bool,
+
+        /// Triage threads that already have a recorded decision again
+        #[arg(long)]
+        retriage: bool,
+
+        /// Apply recorded high-confidence fixes, one commit each
+        #[arg(long)]
+        apply: bool,
+
+        /// Post responses to the PR and resolve handled threads
+        #[arg(long)]
+        post: bool,
+
+        /// Preview what would be posted without posting
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Spawn a child session to work through the unresolved threads
+        #[arg(long, conflicts_with_all = ["batch", "apply", "post", "gather"])]
+        spawn: bool,
+
+        /// Name of the spawned session (default: <session>-shepherd)
+        #[arg(long, requires = "spawn")]
+        name: Option<String>,
+
+        /// Merge a spawned shepherd session's fixes into its parent
+        #[arg(long, conflicts_with_all = ["batch", "apply", "post"])]
+        gather: bool,
     },
 
     /// Activity and metrics
@@ -214,6 +309,9 @@ enum Commands {
     /// Utilities
     Util(UtilCommand),
 
+    /// Managed tmux workspace for a session tree
+    Workspace(WorkspaceCommand),
+
     /// Check installation and configuration
     Doctor,
 
@@ -275,13 +373,27 @@ enum ContextSubcommand {
         restore: Option<String>,
     },
 
-    /// Sync context between sessions
+    /// Sync context between sessions (three-way merge by section)
     Sync {
         /// Source session
         from: String,
 
         /// Target session
         to: String,
+
+        /// Only sync these sections (comma-separated headings)
+        #[arg(long, value_delimiter = ',')]
+        sections: Option<Vec<String>>,
+
+        /// Choose how to resolve conflicting sections
+        #[arg(long, short)]
+        interactive: bool,
+    },
+
+    /// Show the fully-expanded context the AI tool will see
+    Render {
+        /// Session name (or use HP_SESSION env var)
+        session: Option<String>,
     },
 }
 
@@ -291,6 +403,19 @@ struct PrCommand {
     command: PrSubcommand,
 }
 
+#[derive(Subcommand)]
+enum ShepherdSubcommand {
+    /// Show thread states and triage history, grouped by action
+    Status {
+        /// Session name
+        session: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t)]
+        format: shepherd::StatusFormat,
+    },
+}
+
 #[derive(Subcommand)]
 enum PrSubcommand {
     /// Create PR from session
@@ -310,9 +435,33 @@ enum PrSubcommand {
         #[arg(long)]
         labels: Option<String>,
 
-        /// Use context for PR body
-        #[arg(long)]
+        /// No effect; the body is always generated from context
+        #[arg(long, hide = true)]
         from_context: bool,
+
+        /// Create a PR for every session in the tree, each targeting its parent
+        #[arg(long)]
+        stack: bool,
+    },
+
+    /// Retarget and relink the PRs of a session stack
+    Restack {
+        /// Any session in the stack
+        session: String,
+
+        /// Push the stacked branches first
+        #[arg(long)]
+        push: bool,
+    },
+
+    /// Regenerate the PR description from context and commits
+    Update {
+        /// Session name
+        session: String,
+
+        /// Regenerate the title too
+        #[arg(long)]
+        title: bool,
     },
 
     /// Sync PR comments to context
@@ -329,6 +478,26 @@ enum PrSubcommand {
     Status {
         /// Session name
         session: String,
+
+        /// Mark the session integrated if the PR has merged
+        #[arg(long)]
+        integrate: bool,
+    },
+
+    /// Add labels to a session's PR
+    Label {
+        /// Session name
+        session: String,
+
+        /// Labels to add
+        #[arg(required = true)]
+        labels: Vec<String>,
+    },
+
+    /// Send actions queued while the forge was unreachable
+    Flush {
+        /// Only send actions for this session
+        session: Option<String>,
     },
 }
 
@@ -348,6 +517,10 @@ enum ActivitySubcommand {
         /// Limit number of events
         #[arg(long)]
         limit: Option<usize>,
+
+        /// Keep printing new events as they are logged
+        #[arg(short, long)]
+        follow: bool,
     },
 
     /// Show session metrics
@@ -379,13 +552,32 @@ enum TemplateSubcommand {
 
     /// Install template
     Install {
-        /// Template source (file path or URL)
+        /// Template source (name[@version], git+<url>#<ref>:<path>, file path or URL)
         source: String,
 
         /// Template name
         #[arg(long)]
         name: Option<String>,
     },
+
+    /// Update locked templates within their version requirements
+    Update {
+        /// Template name (or update all)
+        name: Option<String>,
+    },
+
+    /// Show templates with newer versions available
+    Outdated,
+
+    /// Pull updates for git template sources
+    Sync {
+        /// Git source to sync (or sync all)
+        source: Option<String>,
+
+        /// Restore templates at their pinned commits without updating
+        #[arg(long)]
+        locked: bool,
+    },
 }
 
 #[derive(Args)]
@@ -460,6 +652,31 @@ enum ProfileSubcommand {
     },
 }
 
+#[derive(Args)]
+struct WorkspaceCommand {
+    #[command(subcommand)]
+    command: WorkspaceSubcommand,
+}
+
+#[derive(Subcommand)]
+enum WorkspaceSubcommand {
+    /// Open a tmux window per session in the tree, then attach
+    Up {
+        /// Root session of the tree
+        root: String,
+
+        /// Don't attach after creating the workspace
+        #[arg(short, long)]
+        detach: bool,
+    },
+
+    /// Kill the tree's tmux workspace
+    Down {
+        /// Root session of the tree
+        root: String,
+    },
+}
+
 #[derive(Args)]
 struct UtilCommand {
     #[command(subcommand)]
@@ -535,7 +752,13 @@ fn main() -> Result<()> {
                 list,
                 restore,
             } => cli::cmd_context_snapshot(session, name, description, list, restore),
-            ContextSubcommand::Sync { from, to } => cli::cmd_context_sync(&from, &to),
+            ContextSubcommand::Sync {
+                from,
+                to,
+                sections,
+                interactive,
+            } => cli::cmd_context_sync(&from, &to, sections, interactive),
+            ContextSubcommand::Render { session } => cli::cmd_context_render(session),
         },
 
         Commands::Cascade { parent, dry_run } => cli::cmd_cascade(&parent, dry_run),
@@ -551,6 +774,21 @@ fn main() -> Result<()> {
             extra_args,
         } => cli::cmd_launch(session, tool, profile, extra_args),
 
+        Commands::Run {
+            session,
+            prompt_file,
+            tool,
+            profile,
+            timeout,
+            quiet,
+        } => cli::cmd_run(&session, &prompt_file, tool, profile, timeout, quiet),
+
+        Commands::Ps { session } => cli::cmd_ps(session.as_deref()),
+
+        Commands::Stop { session } => cli::cmd_stop(&session),
+
+        Commands::AgentExited { id, code } => cli::cmd_agent_exited(&id, code),
+
         Commands::Shell { session, command } => cli::cmd_shell(session, command),
 
         Commands::Exec {
@@ -566,27 +804,56 @@ fn main() -> Result<()> {
                 draft,
                 reviewers,
                 labels,
-                from_context,
+                from_context: _,
+                stack,
             } => {
                 let reviewers_vec =
                     reviewers.map(|r| r.split(',').map(|s| s.trim().to_string()).collect());
                 let labels_vec =
                     labels.map(|l| l.split(',').map(|s| s.trim().to_string()).collect());
-                cli::cmd_pr_create(&session, draft, reviewers_vec, labels_vec, from_context)
+                cli::cmd_pr_create(&session, draft, reviewers_vec, labels_vec, stack)
             }
+            PrSubcommand::Restack { session, push } => cli::cmd_pr_restack(&session, push),
+            PrSubcommand::Update { session, title } => cli::cmd_pr_update(&session, title),
             PrSubcommand::Sync { session, shepherd } => cli::cmd_pr_sync(&session, shepherd),
-            PrSubcommand::Status { session } => cli::cmd_pr_status(&session),
+            PrSubcommand::Status { session, integrate } => cli::cmd_pr_status(&session, integrate),
+            PrSubcommand::Label { session, labels } => cli::cmd_pr_label(&session, labels),
+            PrSubcommand::Flush { session } => cli::cmd_pr_flush(session.as_deref()),
         },
 
         Commands::Shepherd {
+            command,
             session,
             batch,
             auto_fix,
             status,
-        } => cli::cmd_shepherd(session, batch, auto_fix, status),
+            retriage,
+            apply,
+            post,
+            dry_run,
+            spawn,
+            name,
+            gather,
+        } => {
+            if let Some(ShepherdSubcommand::Status { session, format }) = command {
+                cli::cmd_shepherd_status(session, format)
+            } else if status {
+                cli::cmd_shepherd_status(session, shepherd::StatusFormat::Table)
+            } else if spawn {
+                cli::cmd_shepherd_spawn(session, name)
+            } else if gather {
+                cli::cmd_shepherd_gather(session)
+            } else {
+                cli::cmd_shepherd(session, batch, auto_fix, retriage, apply, post, dry_run)
+            }
+        }
 
         Commands::Activity(activity) => match activity.command {
-            ActivitySubcommand::Show { session, limit } => cli::cmd_activity(&session, limit),
+            ActivitySubcommand::Show {
+                session,
+                limit,
+                follow,
+            } => cli::cmd_activity(&session, limit, follow),
             ActivitySubcommand::Metrics { session } => cli::cmd_metrics(&session),
             ActivitySubcommand::Stats => cli::cmd_stats(),
         },
@@ -597,6 +864,9 @@ fn main() -> Result<()> {
             TemplateSubcommand::Install { source, name } => {
                 cli::cmd_template_install(&source, name)
             }
+            TemplateSubcommand::Update { name } => cli::cmd_template_update(name),
+            TemplateSubcommand::Outdated => cli::cmd_template_outdated(),
+            TemplateSubcommand::Sync { source, locked } => cli::cmd_template_sync(source, locked),
         },
 
         Commands::Collab(collab) => match collab.command {
@@ -637,6 +907,11 @@ fn main() -> Result<()> {
             UtilSubcommand::Leave { session, archive } => cli::cmd_leave(&session, archive),
         },
 
+        Commands::Workspace(workspace) => match workspace.command {
+            WorkspaceSubcommand::Up { root, detach } => cli::cmd_workspace_up(&root, detach),
+            WorkspaceSubcommand::Down { root } => cli::cmd_workspace_down(&root),
+        },
+
         Commands::Doctor => cli::cmd_doctor(),
 
         Commands::Tutorial { skip_intro } => cli::cmd_tutorial(skip_intro),
@@ -0,0 +1,393 @@
+//! Section-aware three-way merge for markdown contexts
+//!
+//! A context is split into sections at `#` and `##` headings. The preamble
+//! (everything up to the first `##` heading, including the document title)
+//! is session-specific and is never merged. Every other section is merged
+//! independently against a common base.
+
+/// A markdown section, keyed by its normalized heading
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// Normalized heading text (empty for the preamble)
+    pub key: String,
+    /// Heading text as written
+    pub title: String,
+    /// Full section text including the heading line
+    pub text: String,
+}
+
+/// How a conflicting section should be resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolution {
+    Target,
+    Source,
+    Both,
+    Markers,
+}
+
+/// Per-section merge outcome
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Target's section is kept as-is
+    Keep(String),
+    /// Section updated or added from source
+    Take(String),
+    /// Section removed because source deleted it
+    Removed,
+    /// Both sides changed the section
+    Conflict {
+        source: Option<String>,
+        target: Option<String>,
+    },
+}
+
+/// A merged section with its outcome
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedSection {
+    pub key: String,
+    pub title: String,
+    pub outcome: Outcome,
+}
+
+/// Split markdown into sections, ignoring headings inside code fences
+pub fn split_sections(content: &str) -> Vec<Section> {
+    let mut sections = vec![Section {
+        key: String::new(),
+        title: String::new(),
+        text: String::new(),
+    }];
+    let mut in_fence = false;
+    let mut seen_heading = false;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+
+        let level = heading_level(line);
+        let boundary = !in_fence
+            && match level {
+                Some(2) => true,
+                // A leading `#` title belongs to the preamble
+                Some(1) => seen_heading,
+                _ => false,
+            };
+        if level.is_some() && !in_fence {
+            seen_heading = true;
+        }
+
+        if boundary {
+            let title = line.trim().trim_start_matches('#').trim().to_string();
+            sections.push(Section {
+                key: unique_key(&sections, &normalize(&title)),
+                title,
+                text: String::new(),
+            });
+        }
+
+        if let Some(section) = sections.last_mut() {
+            section.text.push_str(line);
+        }
+    }
+
+    sections
+}
+
+/// Merge `source` into `target` section by section against `base`
+///
+/// Without a base every differing section present on both sides conflicts.
+/// When `only` is set, sections not named in it keep the target's version.
+pub fn merge3(
+    base: Option<&str>,
+    source: &str,
+    target: &str,
+    only: Option<&[String]>,
+) -> Vec<MergedSection> {
+    let base = base.map(split_sections).unwrap_or_default();
+    let source = split_sections(source);
+    let target = split_sections(target);
+
+    let find = |sections: &[Section], key: &str| -> Option<String> {
+        sections
+            .iter()
+            .find(|s| s.key == key)
+            .map(|s| s.text.clone())
+    };
+    let selected = |key: &str| {
+        !key.is_empty() && only.is_none_or(|names| names.iter().any(|n| normalize(n) == key))
+    };
+
+    let decide = |key: &str| -> Outcome {
+        let tgt = find(&target, key);
+        if !selected(key) {
+            return Outcome::Keep(tgt.unwrap_or_default());
+        }
+
+        let src = find(&source, key);
+        let bse = find(&base, key);
+        let same = |a: &Option<String>, b: &Option<String>| {
+            a.as_deref().map(str::trim_end) == b.as_deref().map(str::trim_end)
+        };
+
+        if same(&src, &tgt) || same(&bse, &src) {
+            tgt.map(Outcome::Keep).unwrap_or(Outcome::Removed)
+        } else if same(&bse, &tgt) {
+            src.map(Outcome::Take).unwrap_or(Outcome::Removed)
+        } else {
+            Outcome::Conflict {
+                source: src,
+                target: tgt,
+            }
+        }
+    };
+
+    // Target order first, then sections new in source after their predecessor
+    let mut merged: Vec<MergedSection> = target
+        .iter()
+        .map(|s| MergedSection {
+            key: s.key.clone(),
+            title: s.title.clone(),
+            outcome: decide(&s.key),
+        })
+        .collect();
+
+    let mut anchor = 0;
+    for section in &source {
+        if let Some(pos) = merged.iter().position(|m| m.key == section.key) {
+            anchor = pos + 1;
+            continue;
+        }
+        merged.insert(
+            anchor,
+            MergedSection {
+                key: section.key.clone(),
+                title: section.title.clone(),
+                outcome: decide(&section.key),
+            },
+        );
+        anchor += 1;
+    }
+
+    merged
+}
+
+/// Base to record after syncing only the sections in `only`: the source's
+/// version of those sections and `base`'s version of the rest
+pub fn overlay(base: Option<&str>, source: &str, only: &[String]) -> String {
+    let base = base.map(split_sections).unwrap_or_default();
+    let source = split_sections(source);
+    let selected = |key: &str| !key.is_empty() && only.iter().any(|n| normalize(n) == key);
+    let outcome = |section: &Section| Outcome::Take(section.text.clone());
+
+    let mut sections: Vec<MergedSection> = base
+        .iter()
+        .map(|s| MergedSection {
+            key: s.key.clone(),
+            title: s.title.clone(),
+            outcome: if selected(&s.key) {
+                source
+                    .iter()
+                    .find(|src| src.key == s.key)
+                    .map(outcome)
+                    .unwrap_or(Outcome::Removed)
+            } else {
+                outcome(s)
+            },
+        })
+        .collect();
+    for section in source.iter().filter(|s| selected(&s.key)) {
+        if !sections.iter().any(|m| m.key == section.key) {
+            sections.push(MergedSection {
+                key: section.key.clone(),
+                title: section.title.clone(),
+                outcome: outcome(section),
+            });
+        }
+    }
+
+    // Nothing here conflicts
+    render(&sections, "", "", |_| Ok(Resolution::Target)).unwrap_or_default()
+}
+
+/// Render merged sections, resolving conflicts with `resolve`
+pub fn render<F>(
+    sections: &[MergedSection],
+    source_label: &str,
+    target_label: &str,
+    mut resolve: F,
+) -> crate::error::Result<String>
+where
+    F: FnMut(&MergedSection) -> crate::error::Result<Resolution>,
+{
+    let mut parts: Vec<String> = Vec::new();
+
+    for section in sections {
+        match &section.outcome {
+            Outcome::Keep(text) | Outcome::Take(text) => parts.push(text.clone()),
+            Outcome::Removed => {}
+            Outcome::Conflict { source, target } => {
+                let source = source.clone().unwrap_or_default();
+                let target = target.clone().unwrap_or_default();
+                match resolve(section)? {
+                    Resolution::Target => parts.push(target),
+                    Resolution::Source => parts.push(source),
+                    Resolution::Both => {
+                        parts.push(target);
+                        parts.push(source);
+                    }
+                    Resolution::Markers => parts.push(format!(
+                        "<<<<<<< {}\n{}\n=======\n{}\n>>>>>>> {}\n",
+                        target_label,
+                        target.trim_end(),
+                        source.trim_end(),
+                        source_label
+                    )),
+                }
+            }
+        }
+    }
+
+    let mut result = String::new();
+    for part in parts.iter().filter(|p| !p.trim().is_empty()) {
+        result.push_str(part.trim_end());
+        result.push_str("\n\n");
+    }
+    Ok(format!("{}\n", result.trim_end()))
+}
+
+fn heading_level(line: &str) -> Option<usize> {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[hashes..];
+    if (1..=6).contains(&hashes) && (rest.starts_with(' ') || rest.trim().is_empty()) {
+        Some(hashes)
+    } else {
+        None
+    }
+}
+
+fn normalize(title: &str) -> String {
+    title.trim().to_lowercase()
+}
+
+/// Disambiguate repeated headings as `name#2`, `name#3`, ...
+fn unique_key(sections: &[Section], key: &str) -> String {
+    let count = sections
+        .iter()
+        .filter(|s| s.key == key || s.key.starts_with(&format!("{}#", key)))
+        .count();
+    if count == 0 {
+        key.to_string()
+    } else {
+        format!("{}#{}", key, count + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "# Session: parent\n\n## Objective\n\nBuild auth\n\n## Notes\n\n- none\n";
+
+    #[test]
+    fn test_split_sections() {
+        let sections = split_sections(
+            "# Title\nmeta\n\n## Objective\ntext\n### Detail\nmore\n```\n## not a heading\n```\n## Notes\n",
+        );
+        let keys: Vec<&str> = sections.iter().map(|s| s.key.as_str()).collect();
+        assert_eq!(keys, vec!["", "objective", "notes"]);
+        assert!(sections[1].text.contains("### Detail"));
+        assert!(sections[1].text.contains("## not a heading"));
+        assert_eq!(sections[2].title, "Notes");
+
+        let sections = split_sections("## Notes\na\n## Notes\nb\n");
+        assert_eq!(sections[2].key, "notes#2");
+    }
+
+    #[test]
+    fn test_merge_takes_one_sided_changes() {
+        let source = BASE.replace("Build auth", "Build OAuth auth");
+        let target = "# Session: child\n\n## Objective\n\nBuild auth\n\n## Notes\n\n- child note\n";
+
+        let merged = merge3(Some(BASE), &source, target, None);
+        assert!(merged
+            .iter()
+            .all(|m| !matches!(m.outcome, Outcome::Conflict { .. })));
+
+        let rendered = render(&merged, "parent", "child", |_| unreachable!()).unwrap();
+        assert!(rendered.starts_with("# Session: child"));
+        assert!(rendered.contains("Build OAuth auth"));
+        assert!(rendered.contains("- child note"));
+    }
+
+    #[test]
+    fn test_merge_conflicts_and_resolution() {
+        let source = BASE.replace("Build auth", "Build OAuth");
+        let target = BASE.replace("Build auth", "Build SAML");
+
+        let merged = merge3(Some(BASE), &source, &target, None);
+        let conflicts: Vec<_> = merged
+            .iter()
+            .filter(|m| matches!(m.outcome, Outcome::Conflict { .. }))
+            .collect();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].title, "Objective");
+
+        let marked = render(&merged, "parent", "child", |_| Ok(Resolution::Markers)).unwrap();
+        assert!(marked.contains("<<<<<<< child\n## Objective\n\nBuild SAML\n=======\n"));
+        assert!(marked.contains("Build OAuth\n>>>>>>> parent"));
+
+        let taken = render(&merged, "parent", "child", |_| Ok(Resolution::Source)).unwrap();
+        assert!(taken.contains("Build OAuth") && !taken.contains("Build SAML"));
+    }
+
+    #[test]
+    fn test_merge_section_filter_and_additions() {
+        let source = format!(
+            "{}\n## Architecture\n\nLayers\n",
+            BASE.replace("Build auth", "Build OAuth")
+                .replace("- none", "- from parent")
+        );
+
+        let only = vec!["Notes".to_string(), "architecture".to_string()];
+        let merged = merge3(Some(BASE), &source, BASE, Some(&only));
+        let rendered = render(&merged, "p", "c", |_| unreachable!()).unwrap();
+
+        assert!(rendered.contains("Build auth"));
+        assert!(rendered.contains("- from parent"));
+        assert!(rendered.trim_end().ends_with("Layers"));
+    }
+
+    #[test]
+    fn test_overlay() {
+        let source = format!(
+            "{}\n## Architecture\n\nLayers\n",
+            BASE.replace("Build auth", "Build OAuth")
+                .replace("- none", "- from parent")
+        );
+        let only = vec!["notes".to_string(), "Architecture".to_string()];
+
+        let base = overlay(Some(BASE), &source, &only);
+        assert!(base.contains("Build auth"));
+        assert!(base.contains("- from parent"));
+        assert!(base.trim_end().ends_with("Layers"));
+
+        // Without an earlier base only the synced sections are known
+        let base = overlay(None, &source, &only);
+        assert!(!base.contains("Objective"));
+        assert!(base.contains("- from parent"));
+    }
+
+    #[test]
+    fn test_merge_without_base() {
+        let source = "# A\n\n## Objective\n\nX\n\n## Shared\n\nsame\n";
+        let target = "# B\n\n## Objective\n\nY\n\n## Shared\n\nsame\n\n## Local\n\nmine\n";
+
+        let merged = merge3(None, source, target, None);
+        let outcome = |key: &str| &merged.iter().find(|m| m.key == key).unwrap().outcome;
+        assert!(matches!(outcome(""), Outcome::Keep(t) if t.starts_with("# B")));
+        assert!(matches!(outcome("objective"), Outcome::Conflict { .. }));
+        assert!(matches!(outcome("shared"), Outcome::Keep(_)));
+        assert!(matches!(outcome("local"), Outcome::Keep(_)));
+    }
+}
@@ -146,8 +146,15 @@ pub struct Session {
     pub pr_url: Option<String>,
     /// PR status
     pub pr_status: Option<PrStatus>,
-    /// Unresolved PR review comments
-    pub unresolved_comments: Vec<ReviewComment>,
+    /// PR review threads with their GitHub resolution state
+    #[serde(default)]
+    pub review_threads: Vec<ReviewThread>,
+    /// Shepherd decisions for review threads, keyed by root comment ID
+    #[serde(default)]
+    pub shepherd_analyses: Vec<ShepherdAnalysis>,
+    /// Where the last PR sync left off
+    #[serde(default)]
+    pub pr_sync_cursor: Option<PrSyncCursor>,
 
     // === Activity & Metrics ===
     /// Activity log
@@ -201,7 +208,9 @@ impl Session {
             pr_number: None,
             pr_url: None,
             pr_status: None,
-            unresolved_comments: Vec::new(),
+            review_threads: Vec::new(),
+            shepherd_analyses: Vec::new(),
+            pr_sync_cursor: None,
             activity_log: Vec::new(),
             metrics: SessionMetrics::default(),
             tags: Vec::new(),
@@ -210,6 +219,11 @@ impl Session {
         }
     }
 
+    /// Review threads not yet resolved on GitHub
+    pub fn unresolved_threads(&self) -> impl Iterator<Item = &ReviewThread> {
+        self.review_threads.iter().filter(|t| !t.is_resolved)
+    }
+
     /// Log an activity event
     pub fn log_activity(&mut self, event_type: ActivityType, details: String) {
         self.activity_log.push(ActivityEvent {
@@ -281,6 +295,7 @@ pub enum SnapshotTrigger {
     Manual,
     BeforeCascade,
     BeforeGather,
+    BeforeSync,
     AfterPrReview,
     OnPause,
 }
@@ -302,8 +317,10 @@ pub enum ActivityType {
     SessionCreated,
     ContextEdited,
     AiLaunched,
+    AiExited,
     CommitMade,
     PrCreated,
+    PrUpdated,
     PrSynced,
     PrCommentReceived,
     ShepherdRun,
@@ -385,12 +402,93 @@ pub struct ReviewComment {
     pub author: String,
     /// Timestamp
     pub created_at: DateTime<Utc>,
-    /// Is resolved
-    pub resolved: bool,
+    /// Last edit, if known
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
     /// Diff hunk
     pub diff_hunk: Option<String>,
 }
 
+/// Position of the last incremental PR sync
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PrSyncCursor {
+    /// Highest comment ID seen
+    pub last_comment_id: u64,
+    /// Latest comment creation or edit time seen
+    pub synced_at: DateTime<Utc>,
+}
+
+/// PR review thread: a root comment and its replies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewThread {
+    /// GraphQL node ID of the thread
+    pub id: String,
+    /// File path
+    pub path: String,
+    /// Line in the current diff (None when outdated)
+    pub line: Option<u32>,
+    /// First line of a multi-line comment
+    #[serde(default)]
+    pub start_line: Option<u32>,
+    /// Line in the diff the thread was created on
+    pub original_line: Option<u32>,
+    /// Resolved on GitHub
+    pub is_resolved: bool,
+    /// The code the thread refers to has since changed
+    pub is_outdated: bool,
+    /// Comments in order; the first is the root
+    pub comments: Vec<ReviewComment>,
+    /// Where the code is in the workbox now, if re-anchored since syncing
+    #[serde(default)]
+    pub anchor: Option<CodeAnchor>,
+}
+
+impl ReviewThread {
+    /// The comment that started the thread
+    pub fn root(&self) -> Option<&ReviewComment> {
+        self.comments.first()
+    }
+
+    /// Path and line of the thread's code in the workbox: the re-anchored
+    /// position if known, otherwise the forge's
+    pub fn location(&self) -> (&str, Option<u32>) {
+        match &self.anchor {
+            Some(anchor) => (&anchor.path, anchor.line),
+            None => (&self.path, self.line.or(self.original_line)),
+        }
+    }
+
+    /// Replies to the root comment
+    pub fn replies(&self) -> &[ReviewComment] {
+        self.comments.get(1..).unwrap_or(&[])
+    }
+}
+
+/// Position of a review thread's code in the current workbox contents
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CodeAnchor {
+    /// File path, following renames
+    pub path: String,
+    /// Line in the current file (None when the code was deleted)
+    pub line: Option<u32>,
+    pub status: AnchorStatus,
+    /// Workbox HEAD when the anchor was computed
+    pub commit: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnchorStatus {
+    /// The code is where the comment was made
+    Current,
+    /// The code is unchanged but has moved
+    Moved,
+    /// The commented lines were edited; the line is the best guess
+    Changed,
+    /// The commented code no longer exists
+    Deleted,
+}
+
 /// Shepherd analysis of a PR comment
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
@@ -409,6 +507,50 @@ pub struct ShepherdAnalysis {
     pub changes: Option<String>,
     /// Response to post
     pub response: String,
+    /// Response has been posted as a PR reply
+    #[serde(default)]
+    pub posted: bool,
+    /// Thread has been resolved on GitHub by shepherd
+    #[serde(default)]
+    pub resolved: bool,
+    /// Commit that applied the fix, if shepherd applied one
+    #[serde(default)]
+    pub applied_commit: Option<String>,
+    /// Who or what decided: the user, or the analyzer and model
+    #[serde(default)]
+    pub decided_by: String,
+    /// When the decision was recorded
+    #[serde(default)]
+    pub decided_at: Option<DateTime<Utc>>,
+    /// Earlier decisions for the comment, oldest first
+    #[serde(default)]
+    pub history: Vec<TriageRecord>,
+}
+
+impl ShepherdAnalysis {
+    /// This decision as a history entry
+    pub fn to_record(&self) -> TriageRecord {
+        TriageRecord {
+            action: self.action.clone(),
+            confidence: self.confidence,
+            notes: self.response.clone(),
+            decided_by: self.decided_by.clone(),
+            decided_at: self.decided_at,
+            commit: self.applied_commit.clone(),
+        }
+    }
+}
+
+/// A superseded shepherd decision
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TriageRecord {
+    pub action: ShepherdAction,
+    pub confidence: ConfidenceLevel,
+    pub notes: String,
+    pub decided_by: String,
+    pub decided_at: Option<DateTime<Utc>>,
+    /// Commit that applied the fix
+    pub commit: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
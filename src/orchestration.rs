@@ -31,7 +31,10 @@ impl Orchestrator {
         })
     }
 
-    /// Cascade: Sync parent changes to all children
+    /// Cascade: Sync parent changes to all descendants
+    ///
+    /// Each child is merged with its parent before its own children, so
+    /// changes flow down the whole tree (or PR stack).
     pub fn cascade(&self, parent_name: &str, dry_run: bool) -> Result<()> {
         let parent = self.session_mgr.load_session(parent_name)?;
 
@@ -50,22 +53,7 @@ impl Orchestrator {
 
         let mut cascaded = 0;
         let mut skipped = 0;
-
-        for child_name in &parent.children {
-            match self.cascade_to_child(&parent, child_name, dry_run) {
-                Ok(true) => cascaded += 1,
-                Ok(false) => skipped += 1,
-                Err(e) => {
-                    eprintln!(
-                        "  {} Failed to cascade to '{}': {}",
-                        "✗".red(),
-                        child_name,
-                        e
-                    );
-                    skipped += 1;
-                }
-            }
-        }
+        self.cascade_subtree(&parent, dry_run, &mut cascaded, &mut skipped);
 
         println!();
         if dry_run {
@@ -82,12 +70,52 @@ impl Orchestrator {
         Ok(())
     }
 
+    /// Cascade into each child, then on into its own children
+    ///
+    /// Descendants of a child that failed are skipped.
+    fn cascade_subtree(
+        &self,
+        parent: &Session,
+        dry_run: bool,
+        cascaded: &mut usize,
+        skipped: &mut usize,
+    ) {
+        for child_name in &parent.children {
+            match self.cascade_to_child(parent, child_name, dry_run) {
+                Ok(merged) => {
+                    if merged {
+                        *cascaded += 1;
+                    } else {
+                        *skipped += 1;
+                    }
+                    if let Ok(child) = self.session_mgr.load_session(child_name) {
+                        self.cascade_subtree(&child, dry_run, cascaded, skipped);
+                    }
+                }
+                Err(e) => {
+                    eprintln!(
+                        "  {} Failed to cascade to '{}': {}",
+                        "✗".red(),
+                        child_name,
+                        e
+                    );
+                    *skipped += 1;
+                }
+            }
+        }
+    }
+
     /// Cascade to a single child
     fn cascade_to_child(&self, parent: &Session, child_name: &str, dry_run: bool) -> Result<bool> {
         // Load child session
         let mut child = self.session_mgr.load_session(child_name)?;
 
-        println!("  {} {}", "→".cyan(), child_name);
+        println!(
+            "  {} {} {}",
+            "→".cyan(),
+            child_name,
+            format!("(from {})", parent.name).dimmed()
+        );
 
         // Get child workbox info
         let child_wb = self.hn_client.get_workbox_info(&child.workbox_name)?;
@@ -156,17 +184,48 @@ impl Orchestrator {
 
     /// Gather: Collect all children back to parent
     pub fn gather(&self, parent_name: &str, dry_run: bool) -> Result<()> {
+        self.gather_children(parent_name, None, dry_run)?;
+        Ok(())
+    }
+
+    /// Gather a single child back to its parent, leaving its siblings alone
+    ///
+    /// Returns whether the child was merged.
+    pub fn gather_child(&self, parent_name: &str, child_name: &str, dry_run: bool) -> Result<bool> {
+        Ok(self.gather_children(parent_name, Some(child_name), dry_run)? > 0)
+    }
+
+    /// Gather `only` or every child; returns how many were merged
+    fn gather_children(
+        &self,
+        parent_name: &str,
+        only: Option<&str>,
+        dry_run: bool,
+    ) -> Result<usize> {
         let mut parent = self.session_mgr.load_session(parent_name)?;
 
-        if parent.children.is_empty() {
+        let children: Vec<String> = parent
+            .children
+            .iter()
+            .filter(|child| only.is_none_or(|name| name == child.as_str()))
+            .cloned()
+            .collect();
+        if let Some(name) = only.filter(|_| children.is_empty()) {
+            return Err(Error::Other(format!(
+                "'{}' is not a child of '{}'",
+                name, parent_name
+            )));
+        }
+
+        if children.is_empty() {
             println!("{}", "No child sessions to gather from.".yellow());
-            return Ok(());
+            return Ok(0);
         }
 
         println!(
             "{} Gathering {} children to '{}'...",
             "←".cyan(),
-            parent.children.len(),
+            children.len(),
             parent_name.bold()
         );
         println!();
@@ -184,7 +243,7 @@ impl Orchestrator {
         let mut gathered = 0;
         let mut skipped = 0;
 
-        for child_name in parent.children.clone() {
+        for child_name in children {
             match self.gather_from_child(&parent, &child_name, dry_run) {
                 Ok(true) => gathered += 1,
                 Ok(false) => skipped += 1,
@@ -219,7 +278,7 @@ impl Orchestrator {
             );
         }
 
-        Ok(())
+        Ok(gathered)
     }
 
     /// Gather from a single child
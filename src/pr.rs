@@ -1,18 +1,29 @@
 //! PR integration for hupasiya
 //!
-//! Handles GitHub PR creation, syncing comments, and status tracking.
+//! Handles PR creation, syncing comments, and status tracking on the
+//! repository's forge (GitHub pull requests or GitLab merge requests).
 
-use crate::config::Config;
+use crate::anchor;
+use crate::codeowners::CodeOwners;
+use crate::config::{Config, ForgeKind, PrConfig};
 use crate::context::ContextManager;
 use crate::error::{Error, Result};
+use crate::forge::{self, Forge, NewPullRequest};
+use crate::git;
 use crate::hn_client::HnClient;
-use crate::models::{ActivityType, PrStatus, ReviewComment, Session};
+use crate::models::{
+    ActivityType, AnchorStatus, PrStatus, PrSyncCursor, ReviewComment, ReviewThread, Session,
+    SessionStatus,
+};
+use crate::pr_body::{self, PrBodyParts, PrLink, StackEntry};
+use crate::queue::{ActionQueue, QueueEntry, QueuedAction};
 use crate::session::SessionManager;
+use crate::shepherd::{is_shepherd_reply, print_anchor_summary};
 use colored::Colorize;
-use octocrab::Octocrab;
-use std::env;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-/// PR manager for GitHub integration
+/// PR manager for forge integration
 pub struct PrManager {
     #[allow(dead_code)]
     config: Config,
@@ -37,166 +48,402 @@ impl PrManager {
         })
     }
 
-    /// Create GitHub PR for a session
+    /// Create a PR (or GitLab MR) for a session
+    ///
+    /// When the forge can't be reached the request is queued for
+    /// `hp pr flush` and `None` is returned.
     pub async fn create_pr(
         &self,
         session_name: &str,
         draft: bool,
         reviewers: Option<Vec<String>>,
         labels: Option<Vec<String>>,
-        from_context: bool,
+    ) -> Result<Option<u64>> {
+        let action = QueuedAction::CreatePr {
+            draft,
+            reviewers: reviewers.clone().unwrap_or_default(),
+            labels: labels.clone().unwrap_or_default(),
+        };
+        let result = self
+            .create_pr_now(session_name, draft, reviewers, labels)
+            .await;
+        queue_offline(&self.queue(), session_name, action, result)
+    }
+
+    async fn create_pr_now(
+        &self,
+        session_name: &str,
+        draft: bool,
+        reviewers: Option<Vec<String>>,
+        labels: Option<Vec<String>>,
     ) -> Result<u64> {
         let mut session = self.session_mgr.load_session(session_name)?;
 
-        // Get GitHub token
-        let github_token = env::var("GITHUB_TOKEN")
-            .map_err(|_| Error::Other("GITHUB_TOKEN not set".to_string()))?;
-
         // Get workbox info
         let workbox_info = self.hn_client.get_workbox_info(&session.workbox_name)?;
 
-        // Parse repo from git remote
-        let repo_info = self.get_repo_info(&workbox_info.path)?;
-
-        // Build PR title and body
-        let (title, body) = if from_context {
-            self.build_pr_from_context(&session)?
-        } else {
-            (
-                format!("{}: {}", session.agent_type.as_str(), session.name),
-                format!("Automated PR for session: {}", session.name),
+        // Pick the forge from config or the git remote
+        let forge = forge::connect(&self.pr_config(), &workbox_info.path).await?;
+
+        let number = self
+            .open_pr(
+                forge.as_ref(),
+                &mut session,
+                &workbox_info.path,
+                &workbox_info.base_branch,
+                draft,
+                reviewers.unwrap_or_default(),
+                labels.unwrap_or_default(),
             )
-        };
-
-        // Create octocrab client
-        let octocrab = Octocrab::builder().personal_token(github_token).build()?;
+            .await?;
 
-        // Push branch to remote
-        println!("{} Pushing branch to remote...", "→".cyan());
-        self.push_branch(&workbox_info.path, &session.branch)?;
-
-        // Create PR
-        println!("{} Creating PR...", "→".cyan());
-        let pr = octocrab
-            .pulls(&repo_info.owner, &repo_info.repo)
-            .create(&title, &session.branch, &workbox_info.base_branch)
-            .body(&body)
-            .draft(draft)
-            .send()
-            .await
-            .map_err(|e| Error::Other(format!("Failed to create PR: {}", e)))?;
-
-        let pr_number = pr.number;
-
-        // Add reviewers if specified
-        if let Some(reviewers) = reviewers {
-            println!("{} Requesting reviewers...", "→".cyan());
-            octocrab
-                .pulls(&repo_info.owner, &repo_info.repo)
-                .request_reviews(pr_number, reviewers.clone(), vec![])
-                .await
-                .map_err(|e| Error::Other(format!("Failed to request reviewers: {}", e)))?;
-        }
+        println!();
+        println!("{} PR created successfully!", "✓".green());
+        println!(
+            "  {}: {}",
+            forge.kind().reference(number),
+            session.pr_url.as_deref().unwrap_or_default()
+        );
+        println!();
 
-        // Add labels if specified
-        if let Some(labels) = labels {
-            println!("{} Adding labels...", "→".cyan());
-            octocrab
-                .issues(&repo_info.owner, &repo_info.repo)
-                .add_labels(pr_number, &labels)
-                .await
-                .map_err(|e| Error::Other(format!("Failed to add labels: {}", e)))?;
-        }
+        Ok(number)
+    }
 
-        // Update session with PR info
-        session.pr_number = Some(pr_number);
-        let pr_url = pr
-            .html_url
-            .as_ref()
-            .ok_or_else(|| Error::Other("GitHub PR missing html_url".to_string()))?
-            .to_string();
-        session.pr_url = Some(pr_url.clone());
-        session.pr_status = Some(if draft {
-            PrStatus::Draft
-        } else {
-            PrStatus::Open
-        });
+    /// Create one PR per session in the tree rooted at `session_name`
+    ///
+    /// Each PR targets its parent session's branch; the root targets its
+    /// base branch. Sessions that already have a PR are kept. Every PR gets
+    /// a navigation block listing the stack.
+    pub async fn create_stack(
+        &self,
+        session_name: &str,
+        draft: bool,
+        reviewers: Option<Vec<String>>,
+        labels: Option<Vec<String>>,
+    ) -> Result<()> {
+        let tree = self.session_mgr.get_session_tree(session_name)?;
+        let root_workbox = self.hn_client.get_workbox_info(&tree[0].workbox_name)?;
+        let forge = forge::connect(&self.pr_config(), &root_workbox.path).await?;
+        let kind = forge.kind();
 
-        session.log_activity(
-            ActivityType::PrCreated,
-            format!("Created PR #{}", pr_number),
+        println!(
+            "{} Creating a stack of {} sessions on {}",
+            "→".cyan(),
+            tree.len(),
+            kind.name()
         );
-        self.session_mgr.save_session(&session)?;
 
-        println!();
-        println!("{} PR created successfully!", "✓".green());
-        println!("  PR #{}: {}", pr_number, pr_url);
-        println!();
+        for session in &tree {
+            if let Some(number) = session.pr_number {
+                println!(
+                    "  {} {} already has {}",
+                    "-".dimmed(),
+                    session.name,
+                    kind.reference(number)
+                );
+                continue;
+            }
+
+            // Reload: the parent's PR was just recorded
+            let mut session = self.session_mgr.load_session(&session.name)?;
+            let workbox = self.hn_client.get_workbox_info(&session.workbox_name)?;
+            let base = match stack_parent(&session, &tree) {
+                Some(parent) => parent.branch.clone(),
+                None => workbox.base_branch.clone(),
+            };
 
-        Ok(pr_number)
+            let number = self
+                .open_pr(
+                    forge.as_ref(),
+                    &mut session,
+                    &workbox.path,
+                    &base,
+                    draft,
+                    reviewers.clone().unwrap_or_default(),
+                    labels.clone().unwrap_or_default(),
+                )
+                .await?;
+            println!(
+                "  {} {} {} → {}",
+                "✓".green(),
+                session.name,
+                kind.reference(number),
+                base
+            );
+        }
+
+        let tree = self.session_mgr.get_session_tree(session_name)?;
+        self.restack_tree(forge.as_ref(), &tree, false).await
     }
 
-    /// Sync PR comments to session context
-    pub async fn sync_pr(&self, session_name: &str, create_shepherd_tasks: bool) -> Result<()> {
+    /// Open a draft PR once the session's branch has been pushed
+    ///
+    /// Does nothing unless `hp.pr.auto_create` is set, and for sessions that
+    /// already have a PR or whose branch isn't on the remote yet. A session
+    /// whose parent has a PR is stacked on it.
+    pub async fn auto_create(&self, session_name: &str) -> Result<Option<u64>> {
+        if !self.pr_config().auto_create {
+            return Ok(None);
+        }
         let mut session = self.session_mgr.load_session(session_name)?;
+        if session.pr_number.is_some() {
+            return Ok(None);
+        }
 
-        let pr_number = session
-            .pr_number
-            .ok_or_else(|| Error::Other("Session has no associated PR".to_string()))?;
+        let workbox = self.hn_client.get_workbox_info(&session.workbox_name)?;
+        let remote_ref = format!(
+            "refs/remotes/{}/{}",
+            self.remote(&workbox.path),
+            session.branch
+        );
+        if git::run(
+            Some(&workbox.path),
+            &["rev-parse", "--verify", "--quiet", &remote_ref],
+        )
+        .is_err()
+        {
+            return Ok(None);
+        }
 
-        // Get GitHub token
-        let github_token = env::var("GITHUB_TOKEN")
-            .map_err(|_| Error::Other("GITHUB_TOKEN not set".to_string()))?;
+        let base = match session
+            .parent
+            .as_deref()
+            .and_then(|p| self.session_mgr.load_session(p).ok())
+        {
+            Some(parent) if parent.pr_number.is_some() => parent.branch,
+            _ => workbox.base_branch.clone(),
+        };
 
-        // Get workbox info
-        let workbox_info = self.hn_client.get_workbox_info(&session.workbox_name)?;
+        let forge = forge::connect(&self.pr_config(), &workbox.path).await?;
+        println!(
+            "{} {} was pushed; opening a draft {}",
+            "→".cyan(),
+            session.branch,
+            forge.kind().request_noun()
+        );
+        let number = self
+            .open_pr(
+                forge.as_ref(),
+                &mut session,
+                &workbox.path,
+                &base,
+                true,
+                Vec::new(),
+                Vec::new(),
+            )
+            .await?;
+        println!(
+            "{} Created draft {}: {}",
+            "✓".green(),
+            forge.kind().reference(number),
+            session.pr_url.as_deref().unwrap_or_default()
+        );
 
-        // Parse repo from git remote
-        let repo_info = self.get_repo_info(&workbox_info.path)?;
+        Ok(Some(number))
+    }
 
-        // Create octocrab client
-        let octocrab = Octocrab::builder().personal_token(github_token).build()?;
+    /// Bring the PR stack containing a session up to date
+    ///
+    /// PRs whose parent has merged are retargeted onto the branch the parent
+    /// merged into, and every open PR's navigation block is rewritten. With
+    /// `push`, the branches of open PRs are pushed first.
+    pub async fn restack(&self, session_name: &str, push: bool) -> Result<()> {
+        // The stack starts at the topmost ancestor with a PR
+        let mut root = self.session_mgr.load_session(session_name)?;
+        while let Some(parent) = root
+            .parent
+            .as_deref()
+            .and_then(|p| self.session_mgr.load_session(p).ok())
+            .filter(|p| p.pr_number.is_some())
+        {
+            root = parent;
+        }
 
-        println!("{} Fetching PR comments...", "→".cyan());
+        let tree = self.session_mgr.get_session_tree(&root.name)?;
+        if tree.iter().all(|s| s.pr_number.is_none()) {
+            return Ok(());
+        }
+
+        let workbox = self.hn_client.get_workbox_info(&root.workbox_name)?;
+        let forge = forge::connect(&self.pr_config(), &workbox.path).await?;
+        self.restack_tree(forge.as_ref(), &tree, push).await
+    }
 
-        // Fetch review comments
-        let comments = octocrab
-            .pulls(&repo_info.owner, &repo_info.repo)
-            .list_comments(Some(pr_number))
-            .send()
-            .await
-            .map_err(|e| Error::Other(format!("Failed to fetch comments: {}", e)))?;
+    async fn restack_tree(&self, forge: &dyn Forge, tree: &[Session], push: bool) -> Result<()> {
+        let kind = forge.kind();
 
-        // Filter unresolved comments
-        let unresolved_comments: Vec<ReviewComment> = comments
-            .items
+        let mut details = HashMap::new();
+        for session in tree {
+            if let Some(number) = session.pr_number {
+                details.insert(session.name.clone(), forge.status(number).await?);
+            }
+        }
+        let merged: HashMap<&str, &str> = details
             .iter()
-            .filter(|c| !self.is_comment_resolved(c))
-            .map(|c| self.octocrab_comment_to_review_comment(c))
+            .filter(|(_, d)| d.state == PrStatus::Merged)
+            .map(|(name, d)| (name.as_str(), d.base_ref.as_str()))
             .collect();
 
-        let unresolved_count = unresolved_comments.len();
+        let entries: Vec<StackEntry> = tree
+            .iter()
+            .map(|s| StackEntry {
+                link: PrLink {
+                    session: s.name.clone(),
+                    reference: s.pr_number.map(|n| kind.reference(n)),
+                    url: s.pr_url.clone(),
+                },
+                depth: stack_depth(s, tree),
+                merged: merged.contains_key(s.name.as_str()),
+            })
+            .collect();
+
+        for session in tree {
+            let (Some(number), Some(current)) = (session.pr_number, details.get(&session.name))
+            else {
+                continue;
+            };
+
+            let mut session = self.session_mgr.load_session(&session.name)?;
+            if session.pr_status.as_ref() != Some(&current.state) {
+                session.log_activity(
+                    ActivityType::StatusChanged,
+                    format!("{} is now {:?}", kind.reference(number), current.state),
+                );
+                session.pr_status = Some(current.state.clone());
+            }
+            if current.state == PrStatus::Merged || current.state == PrStatus::Closed {
+                self.session_mgr.save_session(&session)?;
+                continue;
+            }
+
+            if push {
+                let workbox = self.hn_client.get_workbox_info(&session.workbox_name)?;
+                self.push_branch(&workbox.path, &session.branch)?;
+            }
+
+            if let Some(base) = stack_target(&session.name, tree, &merged) {
+                if base != current.base_ref {
+                    forge.retarget(number, &base).await?;
+                    println!(
+                        "  {} Retargeted {} ({}) onto {}",
+                        "↪".cyan(),
+                        kind.reference(number),
+                        session.name,
+                        base
+                    );
+                    session.log_activity(
+                        ActivityType::PrUpdated,
+                        format!("Retargeted {} onto {}", kind.reference(number), base),
+                    );
+                }
+            }
+
+            let body = forge.description(number).await?;
+            let updated =
+                pr_body::replace_stack(&body, &pr_body::render_stack(&entries, &session.name));
+            if updated != body {
+                forge.update_description(number, None, &updated).await?;
+            }
+            self.session_mgr.save_session(&session)?;
+        }
+
+        println!("{} Stack of {} PRs up to date", "✓".green(), details.len());
+        Ok(())
+    }
+
+    /// Sync PR comments to session context
+    pub async fn sync_pr(&self, session_name: &str, create_shepherd_tasks: bool) -> Result<()> {
+        let mut session = self.session_mgr.load_session(session_name)?;
 
+        let client = self.client(&session).await?;
+
+        let previous = session.review_threads.clone();
+        match &session.pr_sync_cursor {
+            Some(cursor) => println!(
+                "{} Fetching review changes since {}...",
+                "→".cyan(),
+                cursor.synced_at.format("%Y-%m-%d %H:%M:%S")
+            ),
+            None => println!("{} Fetching PR review threads...", "→".cyan()),
+        }
+        let threads = client
+            .forge
+            .sync_threads(client.pr_number, &previous, session.pr_sync_cursor.as_ref())
+            .await?;
+
+        let diff = diff_threads(&previous, &threads, session.pr_sync_cursor.as_ref());
+        for comment in &diff.new {
+            println!(
+                "  {} New comment from {} on {}",
+                "+".green(),
+                comment.author.bold(),
+                comment.path
+            );
+            session.log_activity(
+                ActivityType::PrCommentReceived,
+                format!(
+                    "Comment {} from {} on {}",
+                    comment.id, comment.author, comment.path
+                ),
+            );
+        }
+        for comment in &diff.edited {
+            println!(
+                "  {} Comment {} edited by {}",
+                "~".yellow(),
+                comment.id,
+                comment.author
+            );
+        }
+        for comment in &diff.deleted {
+            println!(
+                "  {} Comment {} by {} was deleted",
+                "-".red(),
+                comment.id,
+                comment.author
+            );
+        }
+
+        let unresolved_count = threads.iter().filter(|t| !t.is_resolved).count();
         println!(
-            "{} Found {} unresolved comments",
+            "{} Found {} unresolved of {} review threads",
             "✓".green(),
-            unresolved_count
+            unresolved_count,
+            threads.len()
         );
 
         // Update session
-        session.unresolved_comments = unresolved_comments.clone();
+        if let Some(cursor) = sync_cursor(&threads) {
+            session.pr_sync_cursor = Some(cursor);
+        }
+        session.review_threads = threads;
+        match anchor::reanchor_threads(&client.workbox_path, &mut session.review_threads) {
+            Ok(summary) => print_anchor_summary(&summary),
+            Err(e) => eprintln!("{} Could not re-anchor comments: {}", "⚠".yellow(), e),
+        }
         session.log_activity(
             ActivityType::PrSynced,
-            format!("Synced {} unresolved comments", unresolved_count),
+            format!(
+                "Synced {} unresolved review threads ({} new, {} edited, {} deleted comments)",
+                unresolved_count,
+                diff.new.len(),
+                diff.edited.len(),
+                diff.deleted.len()
+            ),
         );
         self.session_mgr.save_session(&session)?;
 
-        // Write comments to shepherd.md if requested
-        if create_shepherd_tasks && !unresolved_comments.is_empty() {
-            println!("{} Creating shepherd tasks...", "→".cyan());
-            self.write_shepherd_file(&session, &unresolved_comments)?;
+        // Keep an existing shepherd.md current, preserving triage notes
+        let shepherd_path = session.context_dir.join("shepherd.md");
+        if create_shepherd_tasks || shepherd_path.exists() {
+            let existing = std::fs::read_to_string(&shepherd_path).ok();
+            std::fs::write(
+                &shepherd_path,
+                render_shepherd_file(&session, existing.as_deref()),
+            )
+            .map_err(|e| Error::Other(format!("Failed to write shepherd file: {}", e)))?;
             println!(
-                "{} Shepherd file created at .hp/contexts/{}/shepherd.md",
+                "{} Shepherd file updated at .hp/contexts/{}/shepherd.md",
                 "✓".green(),
                 session_name
             );
@@ -205,219 +452,850 @@ impl PrManager {
         Ok(())
     }
 
-    /// Show PR status
-    pub async fn pr_status(&self, session_name: &str) -> Result<()> {
+    /// Regenerate the PR body (and with `title`, the title) from the
+    /// session's current context and commits
+    ///
+    /// Only the generated part of the body is replaced; text added around
+    /// it on the forge is kept. Queued for `hp pr flush` while offline.
+    pub async fn update_pr(&self, session_name: &str, title: bool) -> Result<()> {
+        let result = self.update_pr_now(session_name, title).await;
+        queue_offline(
+            &self.queue(),
+            session_name,
+            QueuedAction::UpdatePr { title },
+            result,
+        )?;
+        Ok(())
+    }
+
+    async fn update_pr_now(&self, session_name: &str, title: bool) -> Result<()> {
+        let mut session = self.session_mgr.load_session(session_name)?;
+
+        let client = self.client(&session).await?;
+        let kind = client.forge.kind();
+        let (new_title, generated) = self.pr_text(&session, &client.workbox_path, kind)?;
+
+        println!("{} Updating {}...", "→".cyan(), client.reference());
+        let existing = client.forge.description(client.pr_number).await?;
+        let body = pr_body::replace_generated(&existing, &generated);
+        client
+            .forge
+            .update_description(client.pr_number, title.then_some(new_title.as_str()), &body)
+            .await?;
+
+        session.log_activity(
+            ActivityType::PrUpdated,
+            format!("Regenerated description of {}", client.reference()),
+        );
+        self.session_mgr.save_session(&session)?;
+
+        println!("{} {} description updated", "✓".green(), client.reference());
+        if title {
+            println!("  Title: {}", new_title);
+        }
+
+        Ok(())
+    }
+
+    /// Add labels to the session's PR, queueing them while offline
+    pub async fn add_labels(&self, session_name: &str, labels: Vec<String>) -> Result<()> {
+        let result = self.add_labels_now(session_name, &labels).await;
+        if let Some(reference) = queue_offline(
+            &self.queue(),
+            session_name,
+            QueuedAction::AddLabels {
+                labels: labels.clone(),
+            },
+            result,
+        )? {
+            println!(
+                "{} Labeled {} with {}",
+                "✓".green(),
+                reference,
+                labels.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Add labels and return the PR reference
+    async fn add_labels_now(&self, session_name: &str, labels: &[String]) -> Result<String> {
         let session = self.session_mgr.load_session(session_name)?;
+        let client = self.client(&session).await?;
+        client.forge.add_labels(client.pr_number, labels).await?;
+        Ok(client.reference())
+    }
+
+    /// Send queued actions in order
+    ///
+    /// Stops at the first network failure and keeps the rest queued. Actions
+    /// the forge rejects stay queued with their error so they can be retried
+    /// or removed by hand; later actions are still sent.
+    pub async fn flush(&self, session_filter: Option<&str>) -> Result<()> {
+        let queue = self.queue();
+        let entries = queue.load()?;
+        if entries.is_empty() {
+            println!("{} Nothing queued", "✓".green());
+            return Ok(());
+        }
+
+        println!("{} Sending queued actions...", "→".cyan());
+        let mut remaining = Vec::new();
+        let mut sent = 0;
+        let mut offline = false;
+        for mut entry in entries {
+            if offline || session_filter.is_some_and(|s| s != entry.session) {
+                remaining.push(entry);
+                continue;
+            }
+            match self.send_queued(&entry).await {
+                Ok(()) => {
+                    sent += 1;
+                    println!(
+                        "  {} {}: {}",
+                        "✓".green(),
+                        entry.session,
+                        entry.action.describe()
+                    );
+                }
+                Err(Error::Network(e)) => {
+                    offline = true;
+                    println!("  {} Still offline: {}", "⏸".yellow(), e);
+                    remaining.push(entry);
+                }
+                Err(e) => {
+                    entry.attempts += 1;
+                    entry.last_error = Some(e.to_string());
+                    println!(
+                        "  {} {}: {} - {}",
+                        "✗".red(),
+                        entry.session,
+                        entry.action.describe(),
+                        e
+                    );
+                    remaining.push(entry);
+                }
+            }
+        }
+        queue.save(&remaining)?;
+
+        println!();
+        println!(
+            "{} Sent {} queued action(s), {} left in {}",
+            "✓".green(),
+            sent,
+            remaining.len(),
+            self.pr_config().queue_file.display()
+        );
+        Ok(())
+    }
+
+    /// Replay one queued action
+    async fn send_queued(&self, entry: &QueueEntry) -> Result<()> {
+        let session_name = entry.session.as_str();
+        match &entry.action {
+            QueuedAction::CreatePr {
+                draft,
+                reviewers,
+                labels,
+            } => {
+                // Created by hand since it was queued
+                if self
+                    .session_mgr
+                    .load_session(session_name)?
+                    .pr_number
+                    .is_some()
+                {
+                    return Ok(());
+                }
+                self.create_pr_now(
+                    session_name,
+                    *draft,
+                    Some(reviewers.clone()),
+                    Some(labels.clone()),
+                )
+                .await
+                .map(|_| ())
+            }
+            QueuedAction::UpdatePr { title } => self.update_pr_now(session_name, *title).await,
+            QueuedAction::AddLabels { labels } => {
+                self.add_labels_now(session_name, labels).await.map(|_| ())
+            }
+            action => {
+                let session = self.session_mgr.load_session(session_name)?;
+                let client = self.client(&session).await?;
+                let number = client.pr_number;
+                match action {
+                    QueuedAction::Reply {
+                        thread_id,
+                        comment_id,
+                        body,
+                    } => {
+                        client
+                            .forge
+                            .reply(number, thread_id, *comment_id, body)
+                            .await
+                    }
+                    QueuedAction::ResolveThread { thread_id } => {
+                        client.forge.resolve_thread(number, thread_id).await
+                    }
+                    QueuedAction::SubmitReview { body } => {
+                        client.forge.submit_review(number, body).await
+                    }
+                    _ => unreachable!("handled above"),
+                }
+            }
+        }
+    }
+
+    /// Show PR status: reviews, checks and merge state
+    ///
+    /// Updates `session.pr_status` from GitHub. With `integrate`, a merged
+    /// PR moves the session to `Integrated`.
+    pub async fn pr_status(&self, session_name: &str, integrate: bool) -> Result<()> {
+        let mut session = self.session_mgr.load_session(session_name)?;
+
+        let client = self.client(&session).await?;
+        let details = client.forge.status(client.pr_number).await?;
+
+        details.print();
+
+        if session.pr_status.as_ref() != Some(&details.state) {
+            session.log_activity(
+                ActivityType::StatusChanged,
+                format!("{} is now {:?}", client.reference(), details.state),
+            );
+            session.pr_status = Some(details.state.clone());
+        }
+
+        if details.state == PrStatus::Merged && session.status != SessionStatus::Integrated {
+            if integrate {
+                session.status = SessionStatus::Integrated;
+                session.log_activity(
+                    ActivityType::Integrated,
+                    format!("{} merged into {}", client.reference(), details.base_ref),
+                );
+                println!(
+                    "{} Session '{}' marked as integrated",
+                    "✓".green(),
+                    session.name
+                );
+            } else {
+                println!(
+                    "{} PR merged. Mark the session integrated with 'hp pr status {} --integrate'",
+                    "→".cyan(),
+                    session.name
+                );
+            }
+            println!();
+        }
 
+        self.session_mgr.save_session(&session)?;
+
+        // Child PRs of a merged parent move onto its base
+        if details.state == PrStatus::Merged && !session.children.is_empty() {
+            self.restack(session_name, false).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Connect to the forge hosting the session's PR
+    pub async fn client(&self, session: &Session) -> Result<PrClient> {
         let pr_number = session
             .pr_number
             .ok_or_else(|| Error::Other("Session has no associated PR".to_string()))?;
-        let pr_url = session
-            .pr_url
-            .as_ref()
-            .ok_or_else(|| Error::Other("Session has no PR URL".to_string()))?;
-
-        // Get GitHub token
-        let github_token = env::var("GITHUB_TOKEN")
-            .map_err(|_| Error::Other("GITHUB_TOKEN not set".to_string()))?;
 
         // Get workbox info
         let workbox_info = self.hn_client.get_workbox_info(&session.workbox_name)?;
 
-        // Parse repo from git remote
-        let repo_info = self.get_repo_info(&workbox_info.path)?;
+        Ok(PrClient {
+            forge: forge::connect(&self.pr_config(), &workbox_info.path).await?,
+            pr_number,
+            workbox_path: workbox_info.path,
+            session: session.name.clone(),
+            queue: self.queue(),
+        })
+    }
 
-        // Create octocrab client
-        let octocrab = Octocrab::builder().personal_token(github_token).build()?;
+    // === Private helper methods ===
 
-        // Fetch PR
-        let pr = octocrab
-            .pulls(&repo_info.owner, &repo_info.repo)
-            .get(pr_number)
-            .await
-            .map_err(|e| Error::Other(format!("Failed to fetch PR: {}", e)))?;
+    /// Push the branch and open its PR, recording it on the session
+    #[allow(clippy::too_many_arguments)]
+    async fn open_pr(
+        &self,
+        forge: &dyn Forge,
+        session: &mut Session,
+        workbox_path: &Path,
+        base: &str,
+        draft: bool,
+        reviewers: Vec<String>,
+        labels: Vec<String>,
+    ) -> Result<u64> {
+        let kind = forge.kind();
+
+        // Build PR title and body
+        let (title, body) = self.pr_text(session, workbox_path, kind)?;
+
+        let (reviewers, labels) = self.review_request(kind, workbox_path, base, reviewers, labels);
+        let (team_reviewers, reviewers): (Vec<String>, Vec<String>) =
+            reviewers.into_iter().partition(|r| r.contains('/'));
+        if kind == ForgeKind::Gitlab && !team_reviewers.is_empty() {
+            eprintln!(
+                "{} GitLab has no team reviewers; skipping {}",
+                "⚠".yellow(),
+                team_reviewers.join(", ")
+            );
+        }
+
+        // Push branch to remote
+        println!("{} Pushing {} to remote...", "→".cyan(), session.branch);
+        self.push_branch(workbox_path, &session.branch)?;
 
-        // Display status
-        println!();
-        println!(
-            "{} PR #{}: {}",
-            "🔗".bold(),
-            pr_number,
-            pr.title.unwrap_or_default()
-        );
-        println!("   URL: {}", pr_url);
-        println!(
-            "   State: {}",
-            format!(
-                "{:?}",
-                pr.state
-                    .ok_or_else(|| Error::Other("PR missing state".to_string()))?
-            )
-            .to_uppercase()
-        );
         println!(
-            "   Created: {}",
-            session.created.format("%Y-%m-%d %H:%M:%S")
+            "{} Creating {} on {}...",
+            "→".cyan(),
+            kind.request_noun(),
+            kind.name()
         );
-        println!(
-            "   Unresolved comments: {}",
-            session.unresolved_comments.len()
+        let created = forge
+            .create(&NewPullRequest {
+                title,
+                body,
+                head: session.branch.clone(),
+                base: base.to_string(),
+                draft,
+            })
+            .await?;
+
+        // Update session with PR info
+        session.pr_number = Some(created.number);
+        session.pr_url = Some(created.url);
+        session.pr_status = Some(if draft {
+            PrStatus::Draft
+        } else {
+            PrStatus::Open
+        });
+        session.log_activity(
+            ActivityType::PrCreated,
+            format!("Created {} into {}", kind.reference(created.number), base),
         );
+        self.session_mgr.save_session(session)?;
 
-        if let Some(mergeable) = pr.mergeable {
-            let mergeable_status = if mergeable {
-                "Yes ✓".green()
-            } else {
-                "No ✗".red()
-            };
-            println!("   Mergeable: {}", mergeable_status);
+        // The PR exists now; a failed review request shouldn't fail creation
+        if !reviewers.is_empty() || !team_reviewers.is_empty() {
+            if let Err(e) = forge
+                .request_reviewers(created.number, &reviewers, &team_reviewers)
+                .await
+            {
+                eprintln!("{} Could not request reviewers: {}", "⚠".yellow(), e);
+            }
+        }
+        if !labels.is_empty() {
+            if let Err(e) = forge.add_labels(created.number, &labels).await {
+                eprintln!("{} Could not add labels: {}", "⚠".yellow(), e);
+            }
         }
 
-        println!();
+        Ok(created.number)
+    }
 
-        Ok(())
+    fn pr_config(&self) -> PrConfig {
+        self.config.hp.pr.clone().unwrap_or_default()
     }
 
-    // === Private helper methods ===
+    /// Outbound actions waiting for the network
+    fn queue(&self) -> ActionQueue {
+        ActionQueue::new(self.pr_config().queue_file)
+    }
+
+    /// Git remote PR branches are pushed to: the forge's remote, or the
+    /// configured one if the workbox doesn't have it
+    fn remote(&self, workbox_path: &Path) -> String {
+        let pr = self.pr_config();
+        match forge::forge_remote(&pr, |remote| git::remote_url(workbox_path, remote)) {
+            Ok(found) => found.name,
+            Err(_) if pr.forge == Some(ForgeKind::Gitlab) => pr.gitlab.remote,
+            Err(_) => pr.github.remote,
+        }
+    }
+
+    fn push_branch(&self, workbox_path: &Path, branch: &str) -> Result<()> {
+        git::run(
+            Some(workbox_path),
+            &["push", "-u", &self.remote(workbox_path), branch],
+        )
+        .map_err(|e| match e {
+            Error::Other(msg) if is_network_failure(&msg) => Error::Network(msg),
+            e => e,
+        })?;
+        Ok(())
+    }
 
-    fn get_repo_info(&self, workbox_path: &std::path::Path) -> Result<RepoInfo> {
-        // Get git remote URL
-        let output = std::process::Command::new("git")
-            .args(["remote", "get-url", "origin"])
-            .current_dir(workbox_path)
-            .output()
-            .map_err(|e| Error::Other(format!("Failed to get git remote: {}", e)))?;
-
-        if !output.status.success() {
-            return Err(Error::Other("Failed to get git remote URL".to_string()));
-        }
-
-        let remote_url = String::from_utf8_lossy(&output.stdout).trim().to_string();
-
-        // Parse GitHub URL (supports both HTTPS and SSH)
-        // Examples:
-        //   https://github.com/owner/repo.git
-        //   git@github.com:owner/repo.git
-        let (owner, repo) = if remote_url.starts_with("https://") {
-            let parts: Vec<&str> = remote_url
-                .trim_start_matches("https://github.com/")
-                .trim_end_matches(".git")
-                .split('/')
-                .collect();
-            if parts.len() != 2 {
-                return Err(Error::Other("Invalid GitHub URL format".to_string()));
+    /// Reviewers and labels for a new PR: the given ones plus the configured
+    /// defaults, and the changed files' code owners with `request_codeowners`
+    fn review_request(
+        &self,
+        kind: ForgeKind,
+        workbox_path: &Path,
+        base: &str,
+        reviewers: Vec<String>,
+        labels: Vec<String>,
+    ) -> (Vec<String>, Vec<String>) {
+        let pr = self.pr_config();
+        let (default_reviewers, default_labels) = match kind {
+            ForgeKind::Github => (pr.github.default_reviewers, pr.github.labels),
+            ForgeKind::Gitlab => (pr.gitlab.default_reviewers, pr.gitlab.labels),
+        };
+        // `@alice` and `alice` name the same reviewer
+        let names = |list: Vec<String>| -> Vec<String> {
+            list.iter()
+                .map(|r| r.trim_start_matches('@').to_string())
+                .collect()
+        };
+        let mut reviewers = merge_unique([names(reviewers), names(default_reviewers)]);
+
+        let owners = CodeOwners::load(workbox_path)
+            .map(|owners| owners.suggest(&self.changed_files(workbox_path, base)))
+            .unwrap_or_default();
+        let suggested: Vec<String> = owners
+            .into_iter()
+            .filter(|owner| !reviewers.contains(owner))
+            .collect();
+        if !suggested.is_empty() {
+            if pr.request_codeowners {
+                reviewers.extend(suggested);
+            } else {
+                println!(
+                    "{} Code owners of the changed files: {}",
+                    "💡".yellow(),
+                    suggested.join(", ")
+                );
             }
-            (parts[0].to_string(), parts[1].to_string())
-        } else if remote_url.starts_with("git@github.com:") {
-            let parts: Vec<&str> = remote_url
-                .trim_start_matches("git@github.com:")
-                .trim_end_matches(".git")
-                .split('/')
-                .collect();
-            if parts.len() != 2 {
-                return Err(Error::Other("Invalid GitHub URL format".to_string()));
+        }
+
+        (reviewers, merge_unique([labels, default_labels]))
+    }
+
+    /// Files the branch changed since it left `base`
+    fn changed_files(&self, workbox_path: &Path, base: &str) -> Vec<String> {
+        [
+            base.to_string(),
+            format!("{}/{}", self.remote(workbox_path), base),
+        ]
+        .iter()
+        .find_map(|base| {
+            let range = format!("{}...HEAD", base);
+            git::run(Some(workbox_path), &["diff", "--name-only", &range]).ok()
+        })
+        .map(|files| files.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+    }
+
+    /// Generate the PR title and body from the session's context, commits
+    /// and related sessions
+    fn pr_text(
+        &self,
+        session: &Session,
+        workbox_path: &Path,
+        kind: ForgeKind,
+    ) -> Result<(String, String)> {
+        let pr = self.pr_config();
+        let context =
+            std::fs::read_to_string(session.context_dir.join("context.md")).unwrap_or_default();
+
+        let link = |name: &str| {
+            let related = self.session_mgr.load_session(name).ok();
+            PrLink {
+                session: name.to_string(),
+                reference: related
+                    .as_ref()
+                    .and_then(|s| s.pr_number)
+                    .map(|n| kind.reference(n)),
+                url: related.and_then(|s| s.pr_url),
             }
-            (parts[0].to_string(), parts[1].to_string())
-        } else {
-            return Err(Error::Other(
-                "Unsupported git remote URL format".to_string(),
-            ));
+        };
+        let parts = PrBodyParts {
+            commits: self.branch_commits(session, workbox_path),
+            parent: session.parent.as_deref().map(link),
+            children: session.children.iter().map(|c| link(c)).collect(),
+            ..PrBodyParts::from_context(&context)
         };
 
-        Ok(RepoInfo { owner, repo })
+        let custom = match &pr.body_template {
+            Some(path) => Some(std::fs::read_to_string(path).map_err(|e| {
+                Error::Other(format!(
+                    "Failed to read body template {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?),
+            None => None,
+        };
+        let title_template = pr
+            .title_template
+            .as_deref()
+            .unwrap_or(pr_body::DEFAULT_TITLE_TEMPLATE);
+
+        Ok((
+            pr_body::render_title(title_template, session, &parts),
+            pr_body::render_body(
+                session,
+                &parts,
+                custom.as_deref(),
+                pr_body::find_repo_template(workbox_path).as_deref(),
+            ),
+        ))
     }
 
-    fn push_branch(&self, workbox_path: &std::path::Path, branch: &str) -> Result<()> {
-        let output = std::process::Command::new("git")
-            .args(["push", "-u", "origin", branch])
-            .current_dir(workbox_path)
-            .output()
-            .map_err(|e| Error::Other(format!("Failed to push branch: {}", e)))?;
+    /// `<short sha> <subject>` of the branch's commits, oldest first
+    fn branch_commits(&self, session: &Session, workbox_path: &Path) -> Vec<String> {
+        let range = format!("{}..HEAD", session.base_branch);
+        git::run(
+            Some(workbox_path),
+            &["log", "--reverse", "--format=%h %s", &range],
+        )
+        .map(|log| log.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+    }
+}
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(Error::Other(format!("Git push failed: {}", stderr)));
-        }
+/// Forge connection bound to a session's PR
+pub struct PrClient {
+    forge: Box<dyn Forge>,
+    pub pr_number: u64,
+    /// Local checkout of the PR branch
+    pub workbox_path: PathBuf,
+    session: String,
+    queue: ActionQueue,
+}
 
-        Ok(())
+impl PrClient {
+    /// "PR #7" or "MR !7"
+    pub fn reference(&self) -> String {
+        self.forge.kind().reference(self.pr_number)
     }
 
-    fn build_pr_from_context(&self, session: &Session) -> Result<(String, String)> {
-        // Read context file
-        let context_path = session.context_dir.join("context.md");
-        let context = std::fs::read_to_string(&context_path)
-            .map_err(|e| Error::Other(format!("Failed to read context: {}", e)))?;
+    pub async fn review_threads(&self) -> Result<Vec<ReviewThread>> {
+        self.forge.review_threads(self.pr_number).await
+    }
 
-        // Parse objectives and deliverables from context
-        let title = format!("{}: {}", session.agent_type.as_str(), session.name);
-        let body = format!("## Session: {}\n\n{}", session.name, context);
+    // Outbound actions are queued for `hp pr flush` while offline
+
+    pub async fn reply(&self, thread_id: &str, comment_id: u64, body: &str) -> Result<()> {
+        let result = self
+            .forge
+            .reply(self.pr_number, thread_id, comment_id, body)
+            .await;
+        let action = QueuedAction::Reply {
+            thread_id: thread_id.to_string(),
+            comment_id,
+            body: body.to_string(),
+        };
+        queue_offline(&self.queue, &self.session, action, result).map(|_| ())
+    }
 
-        Ok((title, body))
+    pub async fn resolve_thread(&self, thread_id: &str) -> Result<()> {
+        let result = self.forge.resolve_thread(self.pr_number, thread_id).await;
+        let action = QueuedAction::ResolveThread {
+            thread_id: thread_id.to_string(),
+        };
+        queue_offline(&self.queue, &self.session, action, result).map(|_| ())
     }
 
-    fn is_comment_resolved(&self, comment: &octocrab::models::pulls::Comment) -> bool {
-        // A comment is considered resolved if it has a reply from the author
-        // or if it's marked as resolved in some way
-        // For simplicity, we'll consider comments without in_reply_to as unresolved
-        comment.in_reply_to_id.is_some()
+    pub async fn submit_review(&self, body: &str) -> Result<()> {
+        let result = self.forge.submit_review(self.pr_number, body).await;
+        let action = QueuedAction::SubmitReview {
+            body: body.to_string(),
+        };
+        queue_offline(&self.queue, &self.session, action, result).map(|_| ())
     }
+}
 
-    fn octocrab_comment_to_review_comment(
-        &self,
-        comment: &octocrab::models::pulls::Comment,
-    ) -> ReviewComment {
-        ReviewComment {
-            id: comment.id.0,
-            path: comment.path.clone(),
-            line: comment.line.map(|l| l as u32),
-            body: comment.body.clone(),
-            author: comment
-                .user
-                .as_ref()
-                .map(|u| u.login.clone())
-                .unwrap_or_default(),
-            created_at: comment.created_at,
-            resolved: comment.in_reply_to_id.is_some(),
-            diff_hunk: Some(comment.diff_hunk.clone()),
+/// Queue `action` if `result` failed for lack of network
+///
+/// Returns `None` when queued; other errors pass through.
+fn queue_offline<T>(
+    queue: &ActionQueue,
+    session: &str,
+    action: QueuedAction,
+    result: Result<T>,
+) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::Network(e)) => {
+            println!(
+                "{} Offline ({}); queued: {}. Send it later with 'hp pr flush'",
+                "⏸".yellow(),
+                e,
+                action.describe()
+            );
+            queue.push(session, action)?;
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether a git error means the remote couldn't be reached
+fn is_network_failure(message: &str) -> bool {
+    [
+        "Could not resolve host",
+        "Failed to connect",
+        "Connection refused",
+        "Connection timed out",
+        "Network is unreachable",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+}
+
+/// Concatenate lists, dropping repeats
+fn merge_unique<const N: usize>(lists: [Vec<String>; N]) -> Vec<String> {
+    let mut merged: Vec<String> = Vec::new();
+    for item in lists.into_iter().flatten() {
+        if !merged.contains(&item) {
+            merged.push(item);
+        }
+    }
+    merged
+}
+
+/// The session's parent, if it is part of `tree`
+fn stack_parent<'a>(session: &Session, tree: &'a [Session]) -> Option<&'a Session> {
+    let parent = session.parent.as_deref()?;
+    tree.iter().find(|s| s.name == parent)
+}
+
+/// Number of ancestors of `session` within `tree`
+fn stack_depth(session: &Session, tree: &[Session]) -> usize {
+    let mut depth = 0;
+    let mut current = session;
+    while let Some(parent) = stack_parent(current, tree) {
+        depth += 1;
+        current = parent;
+    }
+    depth
+}
+
+/// Branch a stacked PR should target
+///
+/// That is the nearest ancestor in the stack that has not merged. Past
+/// merged ancestors, it is the branch the topmost of them merged into.
+/// None for the stack's root, whose base never changes.
+fn stack_target(name: &str, tree: &[Session], merged: &HashMap<&str, &str>) -> Option<String> {
+    let mut current = tree.iter().find(|s| s.name == name)?;
+    let mut target = None;
+    while let Some(parent) = stack_parent(current, tree) {
+        match merged.get(parent.name.as_str()) {
+            Some(base) => target = Some(base.to_string()),
+            None => return Some(parent.branch.clone()),
         }
+        current = parent;
     }
+    target
+}
 
-    fn write_shepherd_file(&self, session: &Session, comments: &[ReviewComment]) -> Result<()> {
-        let mut content = String::new();
-        content.push_str("# PR Comment Resolution\n\n");
-        content.push_str(&format!("Session: {}\n", session.name));
-        content.push_str(&format!("PR: #{}\n\n", session.pr_number.unwrap()));
-        content.push_str("## Unresolved Comments\n\n");
+/// Comment changes between two syncs
+#[derive(Debug, Default)]
+pub struct SyncDiff {
+    pub new: Vec<ReviewComment>,
+    pub edited: Vec<ReviewComment>,
+    pub deleted: Vec<ReviewComment>,
+}
+
+/// Compare comments across syncs
+///
+/// Shepherd's own replies and comments at or below the cursor's last ID
+/// are not reported as new.
+fn diff_threads(
+    previous: &[ReviewThread],
+    current: &[ReviewThread],
+    cursor: Option<&PrSyncCursor>,
+) -> SyncDiff {
+    let before: Vec<&ReviewComment> = previous.iter().flat_map(|t| &t.comments).collect();
+    let after: Vec<&ReviewComment> = current.iter().flat_map(|t| &t.comments).collect();
+    let mut diff = SyncDiff::default();
+
+    for comment in &after {
+        match before.iter().find(|c| c.id == comment.id) {
+            Some(old) if old.body != comment.body => diff.edited.push((*comment).clone()),
+            Some(_) => {}
+            None if is_shepherd_reply(&comment.body) => {}
+            None if cursor.is_some_and(|c| comment.id <= c.last_comment_id) => {}
+            None => diff.new.push((*comment).clone()),
+        }
+    }
+    diff.deleted = before
+        .into_iter()
+        .filter(|old| !after.iter().any(|c| c.id == old.id))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+/// Cursor covering every comment in `threads`
+fn sync_cursor(threads: &[ReviewThread]) -> Option<PrSyncCursor> {
+    let comments = threads.iter().flat_map(|t| &t.comments);
+    Some(PrSyncCursor {
+        last_comment_id: comments.clone().map(|c| c.id).max()?,
+        synced_at: comments
+            .map(|c| c.updated_at.unwrap_or(c.created_at))
+            .max()?,
+    })
+}
+
+const DEFAULT_TRIAGE: &str = "**Suggested Action:** [FIX|CLARIFY|ACKNOWLEDGE|DEFER|DISAGREE]\n\n**Confidence:** [HIGH|MEDIUM|LOW]\n\n**Response:**\n";
+
+/// Render shepherd.md from the session's threads, keeping the triage notes
+/// of an existing file
+///
+/// Threads resolved since keep their notes under "Resolved Comments";
+/// deleted threads are dropped.
+fn render_shepherd_file(session: &Session, existing: Option<&str>) -> String {
+    let notes = existing.map(triage_notes).unwrap_or_default();
+
+    let mut content = String::new();
+    content.push_str("# PR Comment Resolution\n\n");
+    content.push_str(&format!("Session: {}\n", session.name));
+    if let Some(pr_number) = session.pr_number {
+        content.push_str(&format!("PR: #{}\n", pr_number));
+    }
+    content.push('\n');
+
+    let resolved: Vec<&ReviewThread> = session
+        .review_threads
+        .iter()
+        .filter(|t| t.is_resolved && t.root().is_some_and(|c| notes.contains_key(&c.id)))
+        .collect();
+    let sections = [
+        (
+            "Unresolved Comments",
+            session.unresolved_threads().collect(),
+        ),
+        ("Resolved Comments", resolved),
+    ];
+
+    for (title, threads) in sections {
+        if threads.is_empty() && title == "Resolved Comments" {
+            continue;
+        }
+        content.push_str(&format!("## {}\n\n", title));
+
+        for (i, thread) in threads.iter().enumerate() {
+            let comment = match thread.root() {
+                Some(comment) => comment,
+                None => continue,
+            };
 
-        for (i, comment) in comments.iter().enumerate() {
             content.push_str(&format!("### Comment {} (ID: {})\n\n", i + 1, comment.id));
             content.push_str(&format!("**Author:** {}\n", comment.author));
-            content.push_str(&format!("**File:** {}\n", comment.path));
-            if let Some(line) = comment.line {
+            content.push_str(&format!("**File:** {}\n", thread.path));
+            if let Some(line) = thread.line.or(thread.original_line) {
                 content.push_str(&format!("**Line:** {}\n", line));
             }
+            if let Some(anchor) = thread
+                .anchor
+                .as_ref()
+                .filter(|a| matches!(a.status, AnchorStatus::Moved | AnchorStatus::Changed))
+            {
+                content.push_str(&format!(
+                    "**Now at:** {}:{}\n",
+                    anchor.path,
+                    anchor.line.unwrap_or_default()
+                ));
+            }
+            if thread.is_outdated {
+                content.push_str("**Outdated:** yes\n");
+            }
             content.push_str(&format!(
                 "**Created:** {}\n\n",
                 comment.created_at.format("%Y-%m-%d %H:%M:%S")
             ));
             content.push_str(&format!("**Comment:**\n{}\n\n", comment.body));
-            content.push_str("**Suggested Action:** [FIX|CLARIFY|ACKNOWLEDGE|DEFER|DISAGREE]\n\n");
-            content.push_str("**Confidence:** [HIGH|MEDIUM|LOW]\n\n");
-            content.push_str("**Response:**\n\n");
-            content.push_str("---\n\n");
+
+            for reply in thread.replies() {
+                content.push_str(&format!(
+                    "> **{}** ({}):\n",
+                    reply.author,
+                    reply.created_at.format("%Y-%m-%d %H:%M")
+                ));
+                for line in reply.body.lines() {
+                    content.push_str(&format!("> {}\n", line));
+                }
+                content.push('\n');
+            }
+
+            let triage = notes
+                .get(&comment.id)
+                .map(String::as_str)
+                .unwrap_or(DEFAULT_TRIAGE);
+            content.push_str(triage.trim_end());
+            content.push_str("\n\n---\n\n");
         }
+    }
 
-        let shepherd_path = session.context_dir.join("shepherd.md");
-        std::fs::write(&shepherd_path, content)
-            .map_err(|e| Error::Other(format!("Failed to write shepherd file: {}", e)))?;
+    content
+}
 
-        Ok(())
+/// Triage notes from an existing shepherd.md, keyed by comment ID
+///
+/// A comment's block runs from its `### Comment N (ID: …)` header to the
+/// next one, so notes may contain their own headings and rules. Its notes
+/// start at the "Suggested Action" line; the closing `---` and a following
+/// section heading are not part of them.
+fn triage_notes(content: &str) -> HashMap<u64, String> {
+    let mut notes = HashMap::new();
+    let mut current: Option<u64> = None;
+    let mut triage: Option<String> = None;
+
+    for line in content.lines() {
+        if let Some(id) = comment_header_id(line) {
+            if let (Some(id), Some(text)) = (current, triage.take()) {
+                notes.insert(id, strip_block_end(&text));
+            }
+            current = Some(id);
+            continue;
+        }
+
+        if current.is_some() && triage.is_none() && line.starts_with("**Suggested Action:**") {
+            triage = Some(String::new());
+        }
+        if let Some(text) = triage.as_mut() {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    if let (Some(id), Some(text)) = (current, triage) {
+        notes.insert(id, strip_block_end(&text));
     }
+
+    notes
 }
 
-struct RepoInfo {
-    owner: String,
-    repo: String,
+/// Comment ID of a `### Comment N (ID: …)` header line
+fn comment_header_id(line: &str) -> Option<u64> {
+    let rest = line.strip_prefix("### Comment ")?;
+    let (number, id) = rest.split_once(" (ID: ")?;
+    number.parse::<usize>().ok()?;
+    id.strip_suffix(')')?.trim().parse().ok()
+}
+
+/// Triage text without the `---` and section heading rendered after it
+fn strip_block_end(text: &str) -> String {
+    let mut text = text.trim_end();
+    if let Some((rest, last)) = text.rsplit_once('\n') {
+        if matches!(last, "## Unresolved Comments" | "## Resolved Comments") {
+            text = rest.trim_end();
+        }
+    }
+    if let Some(rest) = text.strip_suffix("---") {
+        if rest.is_empty() || rest.ends_with('\n') {
+            text = rest.trim_end();
+        }
+    }
+    format!("{}\n", text)
 }
 
 #[cfg(test)]
@@ -438,27 +1316,208 @@ mod tests {
         }
     }
 
+    fn comment(id: u64, body: &str, at: &str) -> ReviewComment {
+        ReviewComment {
+            id,
+            path: "src/lib.rs".to_string(),
+            line: Some(42),
+            body: body.to_string(),
+            author: "alice".to_string(),
+            created_at: at.parse().unwrap(),
+            updated_at: None,
+            diff_hunk: None,
+        }
+    }
+
+    fn thread(id: &str, resolved: bool, comments: Vec<ReviewComment>) -> ReviewThread {
+        ReviewThread {
+            id: id.to_string(),
+            path: "src/lib.rs".to_string(),
+            line: Some(42),
+            start_line: None,
+            original_line: Some(40),
+            is_resolved: resolved,
+            is_outdated: false,
+            comments,
+            anchor: None,
+        }
+    }
+
     #[test]
-    fn test_parse_github_url_https() {
-        // Test parsing logic without creating PrManager
-        let url = "https://github.com/owner/repo.git";
-        let parts: Vec<&str> = url
-            .trim_start_matches("https://github.com/")
-            .trim_end_matches(".git")
-            .split('/')
-            .collect();
-        assert_eq!(parts, vec!["owner", "repo"]);
+    fn test_incremental_sync_diff() {
+        let previous = vec![
+            thread(
+                "T1",
+                false,
+                vec![comment(1, "Rename", "2024-01-01T00:00:00Z")],
+            ),
+            thread(
+                "T2",
+                false,
+                vec![comment(2, "Typo", "2024-01-01T00:00:00Z")],
+            ),
+        ];
+        let cursor = sync_cursor(&previous).unwrap();
+        assert_eq!(cursor.last_comment_id, 2);
+
+        // Comment 1 edited, a reply and a shepherd reply added, T2 deleted
+        let mut edited = comment(1, "Rename to foo", "2024-01-01T00:00:00Z");
+        edited.updated_at = Some("2024-01-02T00:00:00Z".parse().unwrap());
+        let current = vec![thread(
+            "T1",
+            true,
+            vec![
+                edited,
+                comment(3, "Agreed", "2024-01-02T00:00:00Z"),
+                comment(4, "Done <!-- hp-shepherd:1 -->", "2024-01-03T00:00:00Z"),
+            ],
+        )];
+
+        let diff = diff_threads(&previous, &current, Some(&cursor));
+        let ids = |comments: &[ReviewComment]| comments.iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(ids(&diff.new), vec![3]);
+        assert_eq!(ids(&diff.edited), vec![1]);
+        assert_eq!(ids(&diff.deleted), vec![2]);
+
+        let cursor = sync_cursor(&current).unwrap();
+        assert_eq!(cursor.last_comment_id, 4);
+        assert_eq!(cursor.synced_at.to_rfc3339(), "2024-01-03T00:00:00+00:00");
     }
 
     #[test]
-    fn test_parse_github_url_ssh() {
-        // Test parsing logic without creating PrManager
-        let url = "git@github.com:owner/repo.git";
-        let parts: Vec<&str> = url
-            .trim_start_matches("git@github.com:")
-            .trim_end_matches(".git")
-            .split('/')
-            .collect();
-        assert_eq!(parts, vec!["owner", "repo"]);
+    fn test_render_shepherd_file_keeps_triage_notes() {
+        let mut session = Session::new(
+            "feat".to_string(),
+            crate::models::AgentType::Feature,
+            "feat".to_string(),
+            std::path::PathBuf::from("/tmp/feat"),
+            "feat".to_string(),
+            "main".to_string(),
+            "repo".to_string(),
+            "git".to_string(),
+        );
+        session.pr_number = Some(7);
+        session.review_threads = vec![
+            thread(
+                "T1",
+                false,
+                vec![comment(1, "Rename", "2024-01-01T00:00:00Z")],
+            ),
+            thread(
+                "T2",
+                false,
+                vec![comment(2, "Typo", "2024-01-01T00:00:00Z")],
+            ),
+        ];
+
+        let first = render_shepherd_file(&session, None);
+        assert_eq!(first.matches(DEFAULT_TRIAGE.trim_end()).count(), 2);
+
+        let triaged = first.replacen(
+            "**Suggested Action:** [FIX|CLARIFY|ACKNOWLEDGE|DEFER|DISAGREE]",
+            "**Suggested Action:** FIX\n\nRename to `parse_config`\n\n## Plan\n\n---\n\n### Steps",
+            1,
+        );
+
+        // T1 resolved, T2 deleted, T3 added
+        session.review_threads = vec![
+            thread(
+                "T1",
+                true,
+                vec![comment(1, "Rename", "2024-01-01T00:00:00Z")],
+            ),
+            thread(
+                "T3",
+                false,
+                vec![comment(3, "Docs?", "2024-01-02T00:00:00Z")],
+            ),
+        ];
+        let updated = render_shepherd_file(&session, Some(&triaged));
+
+        assert!(!updated.contains("(ID: 2)"));
+        let resolved = updated.split("## Resolved Comments").nth(1).unwrap();
+        assert!(resolved.contains("(ID: 1)"));
+        assert!(resolved.contains("Rename to `parse_config`\n\n## Plan\n\n---\n\n### Steps"));
+        assert_eq!(updated.matches("---").count(), 3);
+        let unresolved = updated.split("## Resolved Comments").next().unwrap();
+        assert!(unresolved.contains("### Comment 1 (ID: 3)"));
+    }
+
+    #[test]
+    fn test_stack_target() {
+        let session = |name: &str, parent: Option<&str>| {
+            let mut session = Session::new(
+                name.to_string(),
+                crate::models::AgentType::Feature,
+                name.to_string(),
+                std::path::PathBuf::from("/tmp").join(name),
+                format!("hp/{}", name),
+                "main".to_string(),
+                "repo".to_string(),
+                "git".to_string(),
+            );
+            session.parent = parent.map(str::to_string);
+            session
+        };
+        let tree = vec![
+            session("api", None),
+            session("db", Some("api")),
+            session("ui", Some("db")),
+        ];
+
+        let mut merged = HashMap::new();
+        assert_eq!(stack_target("api", &tree, &merged), None);
+        assert_eq!(stack_target("db", &tree, &merged).unwrap(), "hp/api");
+        assert_eq!(stack_target("ui", &tree, &merged).unwrap(), "hp/db");
+
+        // Children of a merged PR move onto the branch it merged into
+        merged.insert("db", "hp/api");
+        assert_eq!(stack_target("ui", &tree, &merged).unwrap(), "hp/api");
+        merged.insert("api", "main");
+        assert_eq!(stack_target("ui", &tree, &merged).unwrap(), "main");
+        assert_eq!(stack_depth(&tree[2], &tree), 2);
+    }
+
+    #[test]
+    fn test_merge_unique_keeps_first_occurrence() {
+        let list = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            merge_unique([list(&["bob", "alice"]), list(&["alice", "acme/core"])]),
+            list(&["bob", "alice", "acme/core"])
+        );
+    }
+
+    #[test]
+    fn test_queue_offline_only_queues_network_errors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let queue = ActionQueue::new(dir.path().join("queue.json"));
+        let action = || QueuedAction::ResolveThread {
+            thread_id: "T1".to_string(),
+        };
+
+        let sent = queue_offline(&queue, "feat", action(), Ok(7)).unwrap();
+        assert_eq!(sent, Some(7));
+        assert!(queue.load().unwrap().is_empty());
+
+        let rejected: Result<()> = Err(Error::Other("422 Unprocessable".to_string()));
+        assert!(queue_offline(&queue, "feat", action(), rejected).is_err());
+        assert!(queue.load().unwrap().is_empty());
+
+        let offline: Result<()> = Err(Error::Network("Could not reach GitHub".to_string()));
+        assert_eq!(
+            queue_offline(&queue, "feat", action(), offline).unwrap(),
+            None
+        );
+        let entries = queue.load().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, action());
+
+        assert!(is_network_failure(
+            "git push failed: fatal: unable to access 'https://github.com/o/r/': \
+             Could not resolve host: github.com"
+        ));
+        assert!(!is_network_failure(
+            "git push failed: ! [rejected] feat -> feat (non-fast-forward)"
+        ));
     }
 }
@@ -0,0 +1,539 @@
+//! PR title and body generation
+//!
+//! Titles and bodies are rendered from the session's context (Objective,
+//! Requirements and task boxes), its commits and the PRs of related
+//! sessions. A repository's own pull request template is filled in rather
+//! than replaced.
+
+use crate::merge;
+use crate::models::Session;
+use std::path::Path;
+
+/// Default `hp.pr.title_template`
+pub const DEFAULT_TITLE_TEMPLATE: &str = "{{agent_type}}: {{summary}}";
+
+/// Markers around the generated part of a PR body, so `hp pr update` can
+/// regenerate it without touching text added on the forge
+const BODY_START: &str = "<!-- hp:pr-body -->";
+const BODY_END: &str = "<!-- /hp:pr-body -->";
+
+/// Markers around the stack navigation block at the top of stacked PRs
+const STACK_START: &str = "<!-- hp:stack -->";
+const STACK_END: &str = "<!-- /hp:stack -->";
+
+/// Pull request templates, in the order forges look for them
+const REPO_TEMPLATES: &[&str] = &[
+    ".github/pull_request_template.md",
+    ".github/PULL_REQUEST_TEMPLATE.md",
+    "docs/pull_request_template.md",
+    "pull_request_template.md",
+    "PULL_REQUEST_TEMPLATE.md",
+    ".gitlab/merge_request_templates/Default.md",
+];
+
+/// Longest generated title summary
+const SUMMARY_MAX_CHARS: usize = 72;
+
+/// A related session and its PR, if it has one
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrLink {
+    pub session: String,
+    /// e.g. "PR #12" or "MR !12"
+    pub reference: Option<String>,
+    pub url: Option<String>,
+}
+
+/// Everything a PR title and body are generated from
+#[derive(Debug, Clone, Default)]
+pub struct PrBodyParts {
+    /// First sentence of the objective
+    pub summary: Option<String>,
+    pub objective: Option<String>,
+    /// Requirements prose; its task boxes go to `checklist`
+    pub requirements: Option<String>,
+    /// Task boxes from anywhere in the context, as written
+    pub checklist: Vec<String>,
+    /// `<short sha> <subject>` lines, oldest first
+    pub commits: Vec<String>,
+    pub parent: Option<PrLink>,
+    pub children: Vec<PrLink>,
+}
+
+impl PrBodyParts {
+    /// Extract the objective, requirements and checklist from a context
+    ///
+    /// Placeholder lines from the built-in templates (`[Describe ...]`) are
+    /// ignored.
+    pub fn from_context(context: &str) -> Self {
+        let sections = merge::split_sections(context);
+        let section = |key: &str| {
+            sections
+                .iter()
+                .find(|s| s.key == key)
+                .and_then(|s| section_body(&s.text))
+        };
+
+        let objective = section("objective");
+        let summary = objective.as_deref().map(summarize);
+        let requirements = section("requirements").and_then(|text| {
+            let prose: Vec<&str> = text.lines().filter(|l| !is_task(l)).collect();
+            non_empty(prose.join("\n"))
+        });
+
+        let mut in_fence = false;
+        let checklist = context
+            .lines()
+            .filter(|line| {
+                if line.trim_start().starts_with("```") {
+                    in_fence = !in_fence;
+                }
+                !in_fence && is_task(line)
+            })
+            .map(|line| line.trim().to_string())
+            .collect();
+
+        Self {
+            summary,
+            objective,
+            requirements,
+            checklist,
+            ..Self::default()
+        }
+    }
+
+    /// Values for `{{name}}` placeholders
+    fn variables(&self, session: &Session) -> Vec<(&'static str, String)> {
+        vec![
+            ("session_name", session.name.clone()),
+            ("agent_type", session.agent_type.as_str().to_string()),
+            ("branch", session.branch.clone()),
+            ("base_branch", session.base_branch.clone()),
+            (
+                "summary",
+                self.summary.clone().unwrap_or_else(|| session.name.clone()),
+            ),
+            ("objective", self.objective.clone().unwrap_or_default()),
+            (
+                "requirements",
+                self.requirements.clone().unwrap_or_default(),
+            ),
+            ("commits", self.commit_list()),
+            ("checklist", self.checklist.join("\n")),
+            ("related", self.related()),
+        ]
+    }
+
+    fn commit_list(&self) -> String {
+        self.commits
+            .iter()
+            .map(|c| format!("- {}", c))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn related(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(parent) = &self.parent {
+            lines.push(format!("- Parent: {}", parent.render()));
+        }
+        for child in &self.children {
+            lines.push(format!("- Child: {}", child.render()));
+        }
+        lines.join("\n")
+    }
+
+    /// Generated sections as (heading, content), skipping empty ones
+    fn sections(&self) -> Vec<(&'static str, String)> {
+        [
+            ("Objective", self.objective.clone().unwrap_or_default()),
+            (
+                "Requirements",
+                self.requirements.clone().unwrap_or_default(),
+            ),
+            ("Changes", self.commit_list()),
+            ("Checklist", self.checklist.join("\n")),
+            ("Related", self.related()),
+        ]
+        .into_iter()
+        .filter(|(_, content)| !content.is_empty())
+        .collect()
+    }
+}
+
+/// A session in a PR stack, in tree order
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackEntry {
+    pub link: PrLink,
+    /// Distance from the stack's root session
+    pub depth: usize,
+    pub merged: bool,
+}
+
+impl PrLink {
+    fn render(&self) -> String {
+        match (&self.reference, &self.url) {
+            (Some(reference), Some(url)) => format!("{} ([{}]({}))", self.session, reference, url),
+            (Some(reference), None) => format!("{} ({})", self.session, reference),
+            _ => format!("{} (no PR yet)", self.session),
+        }
+    }
+}
+
+/// Render a title template, e.g. `{{agent_type}}: {{summary}}`
+pub fn render_title(template: &str, session: &Session, parts: &PrBodyParts) -> String {
+    substitute(template, &parts.variables(session))
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// Render the generated part of a PR body, wrapped in markers
+///
+/// A custom template (`hp.pr.body_template`) is rendered with `{{name}}`
+/// placeholders. Otherwise the repository's pull request template is filled
+/// in, falling back to a section per generated part.
+pub fn render_body(
+    session: &Session,
+    parts: &PrBodyParts,
+    custom_template: Option<&str>,
+    repo_template: Option<&str>,
+) -> String {
+    let body = match (custom_template, repo_template) {
+        (Some(template), _) => substitute(template, &parts.variables(session)),
+        (None, Some(template)) => fill_repo_template(template, &parts.sections()),
+        (None, None) => parts
+            .sections()
+            .iter()
+            .map(|(heading, content)| format!("## {}\n\n{}\n", heading, content))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    format!("{}\n{}\n{}", BODY_START, body.trim(), BODY_END)
+}
+
+/// Replace the generated part of an existing body, keeping text around it
+///
+/// Bodies without markers are replaced entirely.
+pub fn replace_generated(existing: &str, generated: &str) -> String {
+    replace_marked(existing, BODY_START, BODY_END, generated)
+        .unwrap_or_else(|| generated.to_string())
+}
+
+/// Navigation block listing every PR in a stack, marking `current`
+pub fn render_stack(entries: &[StackEntry], current: &str) -> String {
+    let mut block = format!("{}\n**Stack**\n\n", STACK_START);
+    for entry in entries {
+        let mut line = entry.link.render();
+        if entry.link.session == current {
+            line = format!("**{}** ← this PR", line);
+        }
+        if entry.merged {
+            line.push_str(" (merged)");
+        }
+        block.push_str(&format!("{}- {}\n", "  ".repeat(entry.depth), line));
+    }
+    block.push_str(STACK_END);
+    block
+}
+
+/// Replace the stack block of a body, adding it at the top if missing
+pub fn replace_stack(existing: &str, block: &str) -> String {
+    replace_marked(existing, STACK_START, STACK_END, block)
+        .unwrap_or_else(|| format!("{}\n\n{}", block, existing))
+}
+
+/// Replace the text from `start` through `end`, if both markers are present
+fn replace_marked(existing: &str, start: &str, end: &str, replacement: &str) -> Option<String> {
+    let from = existing.find(start)?;
+    let to = existing[from..].find(end)? + from + end.len();
+    Some(format!(
+        "{}{}{}",
+        &existing[..from],
+        replacement,
+        &existing[to..]
+    ))
+}
+
+/// The repository's pull (or merge) request template, if it has one
+pub fn find_repo_template(workbox: &Path) -> Option<String> {
+    REPO_TEMPLATES
+        .iter()
+        .find_map(|path| std::fs::read_to_string(workbox.join(path)).ok())
+}
+
+/// Fill a repository template's sections with generated content
+///
+/// A section whose heading names a generated part ("Summary" or
+/// "Description" for the objective, "Changes", "Checklist", ...) gets that
+/// content in place of its prose; the template's own task boxes are kept.
+/// Other sections stay as written, and unmatched parts are appended.
+fn fill_repo_template(template: &str, sections: &[(&'static str, String)]) -> String {
+    let mut used = vec![false; sections.len()];
+    let mut output = String::new();
+
+    for section in merge::split_sections(template) {
+        let matched = (!section.key.is_empty())
+            .then(|| {
+                sections.iter().position(|(heading, _)| {
+                    part_keywords(heading)
+                        .iter()
+                        .any(|k| section.key.contains(k))
+                })
+            })
+            .flatten()
+            .filter(|i| !used[*i]);
+
+        match matched {
+            Some(i) => {
+                used[i] = true;
+                let heading = section.text.lines().next().unwrap_or_default();
+                let tasks: Vec<&str> = section.text.lines().filter(|l| is_task(l)).collect();
+                output.push_str(&format!("{}\n\n{}\n", heading, sections[i].1));
+                if !tasks.is_empty() {
+                    output.push_str(&format!("{}\n", tasks.join("\n")));
+                }
+                output.push('\n');
+            }
+            None => output.push_str(&section.text),
+        }
+    }
+
+    for (i, (heading, content)) in sections.iter().enumerate() {
+        if !used[i] {
+            output.push_str(&format!("\n## {}\n\n{}\n", heading, content));
+        }
+    }
+
+    output
+}
+
+/// Template headings each generated part can fill
+fn part_keywords(part: &str) -> &'static [&'static str] {
+    match part {
+        "Objective" => &["objective", "summary", "description", "overview", "what"],
+        "Requirements" => &["requirement"],
+        "Changes" => &["change", "commit"],
+        "Checklist" => &["checklist", "task"],
+        "Related" => &["related", "link", "issue"],
+        _ => &[],
+    }
+}
+
+fn substitute(template: &str, variables: &[(&str, String)]) -> String {
+    let mut result = template.to_string();
+    for (key, value) in variables {
+        result = result.replace(&format!("{{{{{}}}}}", key), value);
+    }
+    result
+}
+
+/// Section text without its heading and placeholder lines
+fn section_body(text: &str) -> Option<String> {
+    let body: Vec<&str> = text
+        .lines()
+        .skip(1)
+        .filter(|l| {
+            let l = l.trim();
+            !(l.starts_with('[') && l.ends_with(']'))
+        })
+        .collect();
+    non_empty(body.join("\n"))
+}
+
+/// First sentence of the objective, cut to fit a title
+fn summarize(objective: &str) -> String {
+    let first = objective
+        .lines()
+        .map(|l| l.trim().trim_start_matches(['-', '*']).trim())
+        .find(|l| !l.is_empty())
+        .unwrap_or_default();
+    let sentence = match first.find(". ") {
+        Some(end) => &first[..end],
+        None => first.trim_end_matches('.'),
+    };
+
+    if sentence.chars().count() <= SUMMARY_MAX_CHARS {
+        sentence.to_string()
+    } else {
+        let cut: String = sentence.chars().take(SUMMARY_MAX_CHARS - 1).collect();
+        format!("{}…", cut.trim_end())
+    }
+}
+
+fn is_task(line: &str) -> bool {
+    let line = line.trim_start();
+    ["- [ ]", "- [x]", "- [X]", "* [ ]", "* [x]", "* [X]"]
+        .iter()
+        .any(|p| line.starts_with(p))
+}
+
+fn non_empty(text: String) -> Option<String> {
+    let text = text.trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AgentType;
+
+    const CONTEXT: &str = "# Feature Session: auth\n\n**Type:** Feature\n\n\
+        ## Objective\n\nAdd OAuth login. Tokens are refreshed in the background.\n\n\
+        ## Requirements\n\nMust support GitHub and Google.\n\n- [x] GitHub provider\n- [ ] Google provider\n\n\
+        ## Testing Strategy\n\n```\n- [ ] not a task\n```\n- [ ] Integration test\n\n## Notes\n\n";
+
+    fn session() -> Session {
+        Session::new(
+            "auth".to_string(),
+            AgentType::Feature,
+            "auth".to_string(),
+            std::path::PathBuf::from("/tmp/auth"),
+            "feature/auth".to_string(),
+            "main".to_string(),
+            "repo".to_string(),
+            "git".to_string(),
+        )
+    }
+
+    fn parts() -> PrBodyParts {
+        PrBodyParts {
+            commits: vec!["abc1234 Add OAuth client".to_string()],
+            parent: Some(PrLink {
+                session: "platform".to_string(),
+                reference: Some("PR #10".to_string()),
+                url: Some("https://github.com/o/r/pull/10".to_string()),
+            }),
+            children: vec![PrLink {
+                session: "auth-ui".to_string(),
+                reference: None,
+                url: None,
+            }],
+            ..PrBodyParts::from_context(CONTEXT)
+        }
+    }
+
+    #[test]
+    fn test_parts_from_context() {
+        let parts = PrBodyParts::from_context(CONTEXT);
+        assert_eq!(parts.summary.as_deref(), Some("Add OAuth login"));
+        assert_eq!(
+            parts.requirements.as_deref(),
+            Some("Must support GitHub and Google.")
+        );
+        assert_eq!(
+            parts.checklist,
+            vec![
+                "- [x] GitHub provider",
+                "- [ ] Google provider",
+                "- [ ] Integration test"
+            ]
+        );
+
+        // Unfilled built-in template
+        let empty = PrBodyParts::from_context(
+            "# S\n\n## Objective\n\n[Describe what feature you want to build]\n\n## Notes\n",
+        );
+        assert!(empty.objective.is_none());
+        assert_eq!(
+            render_title(DEFAULT_TITLE_TEMPLATE, &session(), &empty),
+            "feature: auth"
+        );
+    }
+
+    #[test]
+    fn test_render_title_and_default_body() {
+        let parts = parts();
+        assert_eq!(
+            render_title(DEFAULT_TITLE_TEMPLATE, &session(), &parts),
+            "feature: Add OAuth login"
+        );
+        assert_eq!(
+            render_title("[{{branch}}] {{summary}}", &session(), &parts),
+            "[feature/auth] Add OAuth login"
+        );
+
+        let body = render_body(&session(), &parts, None, None);
+        assert!(body.starts_with(BODY_START) && body.ends_with(BODY_END));
+        assert!(body.contains("## Objective\n\nAdd OAuth login."));
+        assert!(body.contains("## Changes\n\n- abc1234 Add OAuth client"));
+        assert!(body.contains("- [ ] Google provider"));
+        assert!(body.contains("- Parent: platform ([PR #10](https://github.com/o/r/pull/10))"));
+        assert!(body.contains("- Child: auth-ui (no PR yet)"));
+
+        let custom = render_body(
+            &session(),
+            &parts,
+            Some("{{summary}}\n\n{{commits}}"),
+            Some("ignored"),
+        );
+        assert!(custom.contains("Add OAuth login\n\n- abc1234"));
+    }
+
+    #[test]
+    fn test_fill_repo_template() {
+        let template = "<!-- Thanks for contributing -->\n\n## Description\n\n<!-- What does this change? -->\n\n\
+            ## Checklist\n\n- [ ] Tests pass\n- [ ] Docs updated\n\n## Screenshots\n\nN/A\n";
+        let body = render_body(&session(), &parts(), None, Some(template));
+
+        assert!(body.contains("<!-- Thanks for contributing -->"));
+        assert!(body.contains("## Description\n\nAdd OAuth login."));
+        assert!(!body.contains("What does this change?"));
+        // Generated tasks first, then the team's own
+        assert!(body.contains("- [ ] Integration test\n- [ ] Tests pass\n- [ ] Docs updated"));
+        assert!(body.contains("## Screenshots\n\nN/A"));
+        // Parts without a matching heading are appended
+        assert!(body.contains("## Changes\n\n- abc1234"));
+        assert!(body.contains("## Related"));
+    }
+
+    #[test]
+    fn test_replace_generated_keeps_surrounding_text() {
+        let old = render_body(&session(), &PrBodyParts::default(), None, None);
+        let existing = format!("Reviewer note: see thread\n\n{}\n\nFixes #3", old);
+        let new = render_body(&session(), &parts(), None, None);
+
+        let updated = replace_generated(&existing, &new);
+        assert!(updated.starts_with("Reviewer note: see thread\n\n"));
+        assert!(updated.ends_with("\n\nFixes #3"));
+        assert!(updated.contains("Add OAuth login"));
+
+        assert_eq!(replace_generated("hand written", &new), new);
+    }
+
+    #[test]
+    fn test_stack_block() {
+        let entry = |session: &str, number: Option<u64>, depth, merged| StackEntry {
+            link: PrLink {
+                session: session.to_string(),
+                reference: number.map(|n| format!("PR #{}", n)),
+                url: None,
+            },
+            depth,
+            merged,
+        };
+        let entries = vec![
+            entry("platform", Some(10), 0, true),
+            entry("auth", Some(11), 1, false),
+            entry("auth-ui", None, 2, false),
+        ];
+
+        let block = render_stack(&entries, "auth");
+        assert!(block.contains("- platform (PR #10) (merged)\n"));
+        assert!(block.contains("  - **auth (PR #11)** ← this PR\n"));
+        assert!(block.contains("    - auth-ui (no PR yet)\n"));
+
+        let body = render_body(&session(), &parts(), None, None);
+        let stacked = replace_stack(&body, &block);
+        assert!(stacked.starts_with(STACK_START));
+        assert!(stacked.ends_with(&body));
+
+        // Replacing keeps a single block and the body's regenerated part
+        let updated = replace_stack(&stacked, &render_stack(&entries[1..], "auth"));
+        assert_eq!(updated.matches(STACK_START).count(), 1);
+        assert!(!updated.contains("platform (PR #10)"));
+        assert!(updated.contains("Add OAuth login"));
+    }
+}
@@ -0,0 +1,521 @@
+//! Registry of running agent processes
+//!
+//! `hp launch` records each AI tool it starts in `hp.sessions.processes_dir`,
+//! one JSON file per process: how to reach it (a PID with its start time,
+//! a tmux session or a screen session), the tool and when it started. The
+//! entry is removed when the tool exits, logging an `AiExited` activity with
+//! the exit code and duration, and cleaning up the context files left in the
+//! workbox for it. Tools running in tmux or screen report their exit through
+//! the hidden `hp agent-exited` command. Entries whose process vanished
+//! without reporting are reaped by `hp ps`.
+
+use crate::adapters::ContextCleanup;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::models::ActivityType;
+use crate::session::SessionManager;
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Where a running agent can be found
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProcessTarget {
+    /// `started` is the process start time reported by `ps`, so a recycled
+    /// PID isn't mistaken for the tool
+    Pid {
+        pid: u32,
+        #[serde(default)]
+        started: Option<String>,
+    },
+    Tmux {
+        session: String,
+    },
+    Screen {
+        session: String,
+    },
+}
+
+impl ProcessTarget {
+    /// Target for a running process, recording when it started
+    pub fn pid(pid: u32) -> Self {
+        ProcessTarget::Pid {
+            pid,
+            started: process_start(pid),
+        }
+    }
+}
+
+impl std::fmt::Display for ProcessTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcessTarget::Pid { pid, .. } => write!(f, "pid {}", pid),
+            ProcessTarget::Tmux { session } => write!(f, "tmux {}", session),
+            ProcessTarget::Screen { session } => write!(f, "screen {}", session),
+        }
+    }
+}
+
+/// One launched AI tool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentProcess {
+    pub id: String,
+    pub session: String,
+    pub tool: String,
+    pub target: ProcessTarget,
+    pub started_at: DateTime<Utc>,
+    /// Context files to remove from the workbox once the tool is gone
+    #[serde(default, skip_serializing_if = "ContextCleanup::is_empty")]
+    pub cleanup: ContextCleanup,
+}
+
+impl AgentProcess {
+    pub fn new(session: &str, tool: &str, target: ProcessTarget) -> Self {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        Self {
+            id: id[..12].to_string(),
+            session: session.to_string(),
+            tool: tool.to_string(),
+            target,
+            started_at: Utc::now(),
+            cleanup: ContextCleanup::default(),
+        }
+    }
+
+    /// Whether the process or terminal session is still there
+    pub fn is_alive(&self) -> bool {
+        let (program, args): (&str, Vec<String>) = match &self.target {
+            ProcessTarget::Pid { pid, started } => {
+                // Entries from before start times were recorded can't be checked
+                return match started {
+                    Some(started) => process_start(*pid).as_ref() == Some(started),
+                    None => signal(*pid, "-0"),
+                };
+            }
+            ProcessTarget::Tmux { session } => (
+                "tmux",
+                vec![
+                    "has-session".to_string(),
+                    "-t".to_string(),
+                    format!("={}", session),
+                ],
+            ),
+            ProcessTarget::Screen { session } => (
+                "screen",
+                vec![
+                    "-S".to_string(),
+                    session.clone(),
+                    "-Q".to_string(),
+                    "select".to_string(),
+                    ".".to_string(),
+                ],
+            ),
+        };
+        Command::new(program)
+            .args(&args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    }
+
+    /// Seconds since the tool started
+    pub fn uptime_secs(&self) -> i64 {
+        (Utc::now() - self.started_at).num_seconds().max(0)
+    }
+
+    /// Terminate the tool
+    fn kill(&self) -> Result<()> {
+        let (program, args): (&str, Vec<String>) = match &self.target {
+            // Never signal a process that merely reuses the PID
+            ProcessTarget::Pid { .. } if !self.is_alive() => return Ok(()),
+            ProcessTarget::Pid { pid, .. } => ("kill", vec!["-TERM".to_string(), pid.to_string()]),
+            ProcessTarget::Tmux { session } => (
+                "tmux",
+                vec![
+                    "kill-session".to_string(),
+                    "-t".to_string(),
+                    format!("={}", session),
+                ],
+            ),
+            ProcessTarget::Screen { session } => (
+                "screen",
+                vec![
+                    "-S".to_string(),
+                    session.clone(),
+                    "-X".to_string(),
+                    "quit".to_string(),
+                ],
+            ),
+        };
+        let status = Command::new(program)
+            .args(&args)
+            .stderr(Stdio::null())
+            .status()?;
+        if !status.success() {
+            return Err(Error::Other(format!(
+                "Failed to stop {} ({})",
+                self.tool, self.target
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Process registry stored as one JSON file per process
+pub struct ProcessRegistry {
+    dir: PathBuf,
+}
+
+impl ProcessRegistry {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn register(&self, process: &AgentProcess) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(
+            self.path(&process.id),
+            serde_json::to_string_pretty(process)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<AgentProcess>> {
+        let path = self.path(id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    /// All registered processes, oldest first
+    pub fn list(&self) -> Result<Vec<AgentProcess>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut processes = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                if let Ok(process) = serde_json::from_str(&fs::read_to_string(&path)?) {
+                    processes.push(process);
+                }
+            }
+        }
+        processes.sort_by_key(|p: &AgentProcess| p.started_at);
+        Ok(processes)
+    }
+
+    /// Remove an entry; false if it was already gone
+    pub fn remove(&self, id: &str) -> Result<bool> {
+        let path = self.path(id);
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(path)?;
+        Ok(true)
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+/// Tracks agents across sessions and logs their exits
+pub struct ProcessManager {
+    registry: ProcessRegistry,
+    session_mgr: SessionManager,
+}
+
+impl ProcessManager {
+    /// Create new process manager
+    pub fn new(config: Config) -> Result<Self> {
+        let registry = ProcessRegistry::new(config.hp.sessions.processes_dir.clone());
+        let session_mgr = SessionManager::new(config)?;
+        Ok(Self {
+            registry,
+            session_mgr,
+        })
+    }
+
+    /// Record a launched tool
+    pub fn register(&self, process: &AgentProcess) -> Result<()> {
+        self.registry.register(process)
+    }
+
+    /// Drop an entry for a tool that failed to start
+    pub fn forget(&self, id: &str) -> Result<()> {
+        if let Some(process) = self.registry.get(id)? {
+            self.registry.remove(id)?;
+            clean_up(process);
+        }
+        Ok(())
+    }
+
+    /// Record that a tool exited with `code` (`None` if unknown or killed
+    /// by a signal). Does nothing if the entry was already removed, e.g.
+    /// by `hp stop`.
+    pub fn finish(&self, id: &str, code: Option<i32>) -> Result<()> {
+        let Some(process) = self.registry.get(id)? else {
+            return Ok(());
+        };
+        self.registry.remove(id)?;
+
+        let outcome = match code {
+            Some(code) => format!("exited {}", code),
+            None => "exited (status unknown)".to_string(),
+        };
+        self.log_exit(&process, &outcome)?;
+        clean_up(process);
+        Ok(())
+    }
+
+    /// Remove entries whose process is gone, returning the live ones
+    pub fn reap(&self) -> Result<Vec<AgentProcess>> {
+        let mut live = Vec::new();
+        for process in self.registry.list()? {
+            if process.is_alive() {
+                live.push(process);
+            } else {
+                self.finish(&process.id, None)?;
+            }
+        }
+        Ok(live)
+    }
+
+    /// Print running agents
+    pub fn show(&self, session_filter: Option<&str>) -> Result<()> {
+        let processes: Vec<AgentProcess> = self
+            .reap()?
+            .into_iter()
+            .filter(|p| session_filter.is_none_or(|s| p.session == s))
+            .collect();
+
+        if processes.is_empty() {
+            println!("{} No agents running", "ℹ".dimmed());
+            return Ok(());
+        }
+
+        println!(
+            "{:20} {:14} {:24} {:20} {:>8}",
+            "SESSION", "TOOL", "TARGET", "STARTED", "UPTIME"
+        );
+        for process in &processes {
+            println!(
+                "{:20} {:14} {:24} {:20} {:>8}",
+                process.session,
+                process.tool,
+                process.target.to_string(),
+                process.started_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                format_duration(process.uptime_secs())
+            );
+        }
+        Ok(())
+    }
+
+    /// Stop every agent running for `session`
+    pub fn stop(&self, session: &str) -> Result<()> {
+        // Make sure the session exists before reporting nothing to stop
+        self.session_mgr.load_session(session)?;
+
+        let processes: Vec<AgentProcess> = self
+            .registry
+            .list()?
+            .into_iter()
+            .filter(|p| p.session == session)
+            .collect();
+        if processes.is_empty() {
+            println!("{} No agents running for '{}'", "ℹ".dimmed(), session);
+            return Ok(());
+        }
+
+        for process in processes {
+            if process.is_alive() {
+                process.kill()?;
+                println!(
+                    "{} Stopped {} ({})",
+                    "✓".green(),
+                    process.tool,
+                    process.target
+                );
+            }
+            // Remove first so the waiting launcher doesn't log it again
+            if self.registry.remove(&process.id)? {
+                self.log_exit(&process, "stopped by hp stop")?;
+                clean_up(process);
+            }
+        }
+        Ok(())
+    }
+
+    /// Liveness of each session's agents for `hp monitor`: true if any is
+    /// running, false if all registered ones are gone
+    pub fn liveness(&self) -> Result<std::collections::HashMap<String, bool>> {
+        let mut status = std::collections::HashMap::new();
+        for process in self.registry.list()? {
+            let alive = process.is_alive();
+            let entry = status.entry(process.session.clone()).or_insert(false);
+            *entry |= alive;
+        }
+        Ok(status)
+    }
+
+    fn log_exit(&self, process: &AgentProcess, outcome: &str) -> Result<()> {
+        // The session may have been closed while the agent ran
+        let Ok(mut session) = self.session_mgr.load_session(&process.session) else {
+            return Ok(());
+        };
+        session.log_activity(
+            ActivityType::AiExited,
+            format!(
+                "{} {} after {}",
+                process.tool,
+                outcome,
+                format_duration(process.uptime_secs())
+            ),
+        );
+        self.session_mgr.save_session(&session)
+    }
+}
+
+/// Remove the context files a finished tool was given
+fn clean_up(mut process: AgentProcess) {
+    if let Err(e) = process.cleanup.run() {
+        eprintln!(
+            "{} Could not clean up context for {}: {}",
+            "⚠".yellow(),
+            process.tool,
+            e
+        );
+    }
+}
+
+/// Start time of a running process, None if there's no such process
+fn process_start(pid: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", "lstart=", "-p", &pid.to_string()])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let started = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !started.is_empty()).then_some(started)
+}
+
+/// Send `signal` to `pid`, returning whether it was delivered
+fn signal(pid: u32, signal: &str) -> bool {
+    Command::new("kill")
+        .args([signal, &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Compact duration, e.g. "45s", "12m", "3h05m"
+fn format_duration(secs: i64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s => format!("{}h{:02}m", s / 3600, (s % 3600) / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_registry_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let registry = ProcessRegistry::new(temp.path().join("processes"));
+        assert!(registry.list().unwrap().is_empty());
+
+        let process = AgentProcess::new(
+            "auth",
+            "claude",
+            ProcessTarget::Tmux {
+                session: "hp-auth".to_string(),
+            },
+        );
+        registry.register(&process).unwrap();
+
+        assert_eq!(registry.list().unwrap(), vec![process.clone()]);
+        assert_eq!(registry.get(&process.id).unwrap(), Some(process.clone()));
+        assert!(registry.remove(&process.id).unwrap());
+        assert!(!registry.remove(&process.id).unwrap());
+        assert!(registry.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_finish_cleans_up_context() {
+        let temp = TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.hp.sessions.metadata_dir = temp.path().join("sessions");
+        config.hp.sessions.context_dir = temp.path().join("contexts");
+        config.hp.sessions.processes_dir = temp.path().join("processes");
+        let manager = ProcessManager::new(config).unwrap();
+
+        let workbox = temp.path().join("wb");
+        fs::create_dir(&workbox).unwrap();
+        let rendered = temp.path().join("rendered.md");
+        fs::write(&rendered, "Session goal\n").unwrap();
+        let context = crate::adapters::for_command("claude")
+            .prepare(
+                &crate::config::ContextStrategy::SlashCommand,
+                &workbox,
+                &rendered,
+                false,
+            )
+            .unwrap();
+
+        let mut process = AgentProcess::new(
+            "auth",
+            "claude",
+            ProcessTarget::Tmux {
+                session: "hp-auth".to_string(),
+            },
+        );
+        process.cleanup = context.persist();
+        manager.register(&process).unwrap();
+        assert!(workbox.join(".claude/commands/hp_context.md").exists());
+
+        // The slash command outlives hp until the registry sees the exit
+        manager.finish(&process.id, Some(0)).unwrap();
+        assert!(!workbox.join(".claude").exists());
+        assert!(manager.registry.list().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pid_liveness() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let process = AgentProcess::new("auth", "sleep", ProcessTarget::pid(child.id()));
+        assert!(process.is_alive());
+
+        // Same PID, different start time: a recycled PID is not the tool
+        let recycled = AgentProcess::new(
+            "auth",
+            "sleep",
+            ProcessTarget::Pid {
+                pid: child.id(),
+                started: Some("Thu Jan  1 00:00:00 1970".to_string()),
+            },
+        );
+        assert!(!recycled.is_alive());
+        recycled.kill().unwrap();
+        assert!(process.is_alive());
+
+        process.kill().unwrap();
+        child.wait().unwrap();
+        assert!(!process.is_alive());
+        assert_eq!(format_duration(3725), "1h02m");
+    }
+}
@@ -126,6 +126,7 @@ mod tests {
                     context_strategy: ContextStrategy::SlashCommand,
                     extra_args: vec![],
                     env: HashMap::new(),
+                    ..AiToolConfig::default()
                 }),
                 pr: None,
                 orchestration: None,
@@ -141,6 +142,7 @@ mod tests {
                     context_strategy: ContextStrategy::File,
                     extra_args: vec![],
                     env: HashMap::new(),
+                    ..AiToolConfig::default()
                 }),
                 pr: None,
                 orchestration: None,
@@ -179,6 +181,7 @@ mod tests {
                     context_strategy: ContextStrategy::Env,
                     extra_args: vec![],
                     env: HashMap::from([("TEST_VAR".to_string(), "test_value".to_string())]),
+                    ..AiToolConfig::default()
                 }),
                 pr: None,
                 orchestration: None,
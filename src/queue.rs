@@ -0,0 +1,161 @@
+//! Offline queue for outbound forge actions
+//!
+//! Actions that fail because the forge can't be reached are appended to
+//! `hp.pr.queue_file` and replayed in order by `hp pr flush`. Entries name
+//! the session rather than a PR number, since a queued PR creation has no
+//! number yet.
+
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// An action to send once the network is back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum QueuedAction {
+    CreatePr {
+        draft: bool,
+        reviewers: Vec<String>,
+        labels: Vec<String>,
+    },
+    /// Regenerate the description (and title) from the session
+    UpdatePr {
+        title: bool,
+    },
+    AddLabels {
+        labels: Vec<String>,
+    },
+    Reply {
+        thread_id: String,
+        comment_id: u64,
+        body: String,
+    },
+    ResolveThread {
+        thread_id: String,
+    },
+    SubmitReview {
+        body: String,
+    },
+}
+
+impl QueuedAction {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::CreatePr { draft: true, .. } => "create draft PR".to_string(),
+            Self::CreatePr { .. } => "create PR".to_string(),
+            Self::UpdatePr { .. } => "update PR description".to_string(),
+            Self::AddLabels { labels } => format!("add labels {}", labels.join(", ")),
+            Self::Reply { comment_id, .. } => format!("reply to comment {}", comment_id),
+            Self::ResolveThread { thread_id } => format!("resolve thread {}", thread_id),
+            Self::SubmitReview { .. } => "submit review summary".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueEntry {
+    pub session: String,
+    pub queued_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub action: QueuedAction,
+    /// Failed flush attempts, for entries the forge rejected
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+/// Queue stored as a JSON array
+pub struct ActionQueue {
+    path: PathBuf,
+}
+
+impl ActionQueue {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn load(&self) -> Result<Vec<QueueEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Replace the queue; an empty queue removes the file
+    pub fn save(&self, entries: &[QueueEntry]) -> Result<()> {
+        if entries.is_empty() {
+            if self.path.exists() {
+                std::fs::remove_file(&self.path)?;
+            }
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(entries)?)?;
+        Ok(())
+    }
+
+    pub fn push(&self, session: &str, action: QueuedAction) -> Result<()> {
+        let mut entries = self.load()?;
+        entries.push(QueueEntry {
+            session: session.to_string(),
+            queued_at: Utc::now(),
+            action,
+            attempts: 0,
+            last_error: None,
+        });
+        self.save(&entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let queue = ActionQueue::new(dir.path().join("queue.json"));
+        assert!(queue.load().unwrap().is_empty());
+
+        queue
+            .push(
+                "feat",
+                QueuedAction::CreatePr {
+                    draft: true,
+                    reviewers: vec!["alice".to_string()],
+                    labels: Vec::new(),
+                },
+            )
+            .unwrap();
+        queue
+            .push(
+                "feat",
+                QueuedAction::Reply {
+                    thread_id: "T1".to_string(),
+                    comment_id: 7,
+                    body: "Done".to_string(),
+                },
+            )
+            .unwrap();
+
+        let entries = queue.load().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action.describe(), "create draft PR");
+        assert_eq!(entries[1].action.describe(), "reply to comment 7");
+
+        // Stored flat, tagged by action
+        let raw: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join("queue.json")).unwrap())
+                .unwrap();
+        assert_eq!(raw[1]["action"], "reply");
+        assert_eq!(raw[1]["session"], "feat");
+
+        queue.save(&[]).unwrap();
+        assert!(!dir.path().join("queue.json").exists());
+    }
+}
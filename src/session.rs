@@ -138,6 +138,7 @@ impl SessionManager {
         }
 
         // Sort by last active (most recent first)
+        #[allow(clippy::unnecessary_sort_by)]
         sessions.sort_by(|a, b| b.last_active.cmp(&a.last_active));
 
         Ok(sessions)
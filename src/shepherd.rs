@@ -2,22 +2,33 @@
 //!
 //! Interactive workflow to address PR review comments with AI assistance.
 
-use crate::config::Config;
+use crate::ai_tool::{self, HeadlessRun};
+use crate::analyzer::{self, meets_threshold, AnalysisRequest};
+use crate::anchor;
+use crate::config::{self, Config, ShepherdConfig};
 use crate::context::ContextManager;
 use crate::error::{Error, Result};
-use crate::models::{ActivityType, ConfidenceLevel, Session, ShepherdAction};
+use crate::fixes::{self, FixResult};
+use crate::git;
+use crate::hn_client::WorkboxOptions;
+use crate::models::{
+    ActivityType, AgentType, AnchorStatus, ConfidenceLevel, ReviewComment, ReviewThread, Session,
+    SessionStatus, ShepherdAction, ShepherdAnalysis,
+};
+use crate::orchestration::Orchestrator;
 use crate::pr::PrManager;
 use crate::session::SessionManager;
 use colored::Colorize;
 use dialoguer::{Confirm, Input, Select};
+use serde::Serialize;
 use std::fs;
+use std::path::Path;
+use std::time::Duration;
 
 /// Shepherd workflow manager
 pub struct Shepherd {
-    #[allow(dead_code)]
     config: Config,
     session_mgr: SessionManager,
-    #[allow(dead_code)]
     context_mgr: ContextManager,
     #[allow(dead_code)]
     pr_mgr: PrManager,
@@ -40,7 +51,9 @@ impl Shepherd {
     }
 
     /// Run interactive shepherd workflow
-    pub fn run_interactive(&self, session_name: &str) -> Result<()> {
+    ///
+    /// Threads already triaged are skipped unless `retriage` is set.
+    pub fn run_interactive(&self, session_name: &str, retriage: bool) -> Result<()> {
         let mut session = self.session_mgr.load_session(session_name)?;
 
         // Check if session has PR
@@ -72,19 +85,49 @@ impl Shepherd {
         } else {
             println!("PR: {}", "None".dimmed());
         }
-        println!("Unresolved comments: {}", session.unresolved_comments.len());
+        println!(
+            "Unresolved threads: {}",
+            session.unresolved_threads().count()
+        );
+        self.reanchor(&mut session)?;
+        let settings = self.shepherd_config();
+        let (threads, skipped) =
+            threads_to_triage(&session, retriage, &settings.confidence_threshold);
+        if skipped > 0 {
+            println!(
+                "{} Skipping {} already triaged thread(s); use --retriage to revisit them",
+                "ℹ".dimmed(),
+                skipped
+            );
+        }
         println!();
 
-        if session.unresolved_comments.is_empty() {
-            println!("{} No unresolved comments!", "✓".green());
+        if threads.is_empty() {
+            println!("{} No comments to triage!", "✓".green());
             return Ok(());
         }
 
+        // Suggestions are optional; without a working analyzer the user decides alone
+        let analyzer = match analyzer::from_config(&settings) {
+            Ok(analyzer) => Some(analyzer),
+            Err(e) => {
+                println!("{} AI suggestions unavailable: {}", "ℹ".dimmed(), e);
+                println!();
+                None
+            }
+        };
+
         let mut resolved_count = 0;
-        let total_comments = session.unresolved_comments.len();
+        let total_comments = threads.len();
+        let decider = user_decider(&session.workbox_path);
+
+        // Process each thread interactively
+        for (i, thread) in threads.iter().enumerate() {
+            let comment = match thread.root() {
+                Some(comment) => comment,
+                None => continue,
+            };
 
-        // Process each comment interactively
-        for (i, comment) in session.unresolved_comments.clone().iter().enumerate() {
             println!("─────────────────────────────────────────────");
             println!(
                 "{} Comment {}/{} (ID: {})",
@@ -95,15 +138,95 @@ impl Shepherd {
             );
             println!();
             println!("{}: {}", "Author".cyan(), comment.author);
-            println!("{}: {}", "File".cyan(), comment.path);
-            if let Some(line) = comment.line {
+            println!("{}: {}", "File".cyan(), thread.path);
+            if let Some(line) = thread.line.or(thread.original_line) {
                 println!("{}: {}", "Line".cyan(), line);
             }
+            match &thread.anchor {
+                Some(anchor) if anchor.status == AnchorStatus::Deleted => println!(
+                    "{}",
+                    "(outdated: the commented code has been deleted)".dimmed()
+                ),
+                Some(anchor) if anchor.status != AnchorStatus::Current => println!(
+                    "{}: {}:{} ({})",
+                    "Now at".cyan(),
+                    anchor.path,
+                    anchor.line.unwrap_or_default(),
+                    if anchor.status == AnchorStatus::Moved {
+                        "moved"
+                    } else {
+                        "edited since the comment"
+                    }
+                ),
+                _ if thread.is_outdated => println!(
+                    "{}",
+                    "(outdated: the code has changed since this comment)".dimmed()
+                ),
+                _ => {}
+            }
             println!();
             println!("{}", "Comment:".yellow().bold());
             println!("{}", comment.body);
+            for reply in thread.replies() {
+                println!();
+                println!("  {} {}:", "↳".dimmed(), reply.author.cyan());
+                for line in reply.body.lines() {
+                    println!("  {}", line);
+                }
+            }
             println!();
 
+            // The code as it is now, not as it was reviewed
+            if let (path, Some(line)) = thread.location() {
+                let excerpt = analyzer::file_excerpt(
+                    &session.workbox_path.join(path),
+                    Some(line),
+                    CURRENT_CODE_LINES,
+                );
+                if let Some(excerpt) = excerpt {
+                    println!("{}", "Current code:".yellow().bold());
+                    let marker = format!("{:>5} |", line);
+                    for excerpt_line in excerpt.lines() {
+                        if excerpt_line.starts_with(&marker) {
+                            println!("{}", excerpt_line.bold());
+                        } else {
+                            println!("{}", excerpt_line.dimmed());
+                        }
+                    }
+                    println!();
+                }
+            }
+
+            let suggestion = analyzer.as_ref().and_then(|analyzer| {
+                let request = AnalysisRequest::from_thread(
+                    thread,
+                    comment,
+                    &session.workbox_path,
+                    settings.analyzer.context_lines,
+                    &settings.analysis_model,
+                );
+                match analyzer.analyze(&request) {
+                    Ok(analysis) => Some(analysis),
+                    Err(e) => {
+                        println!("{} Analysis failed: {}", "⚠".yellow(), e);
+                        None
+                    }
+                }
+            });
+            if let Some(suggestion) = &suggestion {
+                println!(
+                    "{} {:?} ({}) - {}",
+                    "Suggested:".magenta().bold(),
+                    suggestion.action,
+                    suggestion.confidence.as_str(),
+                    suggestion.assessment
+                );
+                if let Some(changes) = &suggestion.changes {
+                    println!("   {}", changes.dimmed());
+                }
+                println!();
+            }
+
             // Ask user for action
             let actions = vec![
                 "FIX - Make code changes to address the comment",
@@ -117,7 +240,13 @@ impl Shepherd {
             let selection = Select::new()
                 .with_prompt("What action would you like to take?")
                 .items(&actions)
-                .default(0)
+                .default(match suggestion.as_ref().map(|a| &a.action) {
+                    Some(ShepherdAction::Clarify) => 1,
+                    Some(ShepherdAction::Acknowledge) => 2,
+                    Some(ShepherdAction::Defer) => 3,
+                    Some(ShepherdAction::Disagree) => 4,
+                    _ => 0,
+                })
                 .interact()
                 .map_err(|e| Error::Other(format!("Selection failed: {}", e)))?;
 
@@ -136,7 +265,11 @@ impl Shepherd {
             let confidence_selection = Select::new()
                 .with_prompt("Confidence level")
                 .items(&confidence_items)
-                .default(1)
+                .default(match suggestion.as_ref().map(|a| a.confidence) {
+                    Some(ConfidenceLevel::High) => 0,
+                    Some(ConfidenceLevel::Low) => 2,
+                    _ => 1,
+                })
                 .interact()
                 .map_err(|e| Error::Other(format!("Selection failed: {}", e)))?;
 
@@ -150,6 +283,12 @@ impl Shepherd {
             // Get response/notes
             let response: String = Input::new()
                 .with_prompt("Response/notes (or press Enter to skip)")
+                .with_initial_text(
+                    suggestion
+                        .as_ref()
+                        .map(|a| a.response.clone())
+                        .unwrap_or_default(),
+                )
                 .allow_empty(true)
                 .interact_text()
                 .map_err(|e| Error::Other(format!("Input failed: {}", e)))?;
@@ -157,10 +296,24 @@ impl Shepherd {
             // Update shepherd analysis
             self.record_shepherd_action(
                 &mut session,
-                comment.id,
-                action.clone(),
-                confidence,
-                response.clone(),
+                ShepherdAnalysis {
+                    comment_id: comment.id,
+                    action: action.clone(),
+                    confidence,
+                    summary: summarize(&comment.body),
+                    assessment: suggestion
+                        .as_ref()
+                        .map(|a| a.assessment.clone())
+                        .unwrap_or_default(),
+                    changes: suggestion.as_ref().and_then(|a| a.changes.clone()),
+                    response: response.clone(),
+                    posted: false,
+                    resolved: false,
+                    applied_commit: None,
+                    decided_by: decider.clone(),
+                    decided_at: None,
+                    history: Vec::new(),
+                },
             )?;
 
             println!();
@@ -216,125 +369,582 @@ impl Shepherd {
     }
 
     /// Run shepherd workflow in batch mode
-    pub fn run_batch(&self, session_name: &str, auto_fix: bool) -> Result<()> {
+    ///
+    /// Each unresolved thread is sent to the configured analyzer. FIX
+    /// analyses are only marked for auto-fix when `auto_fix` (or
+    /// `auto_apply_safe`) is set and they meet `confidence_threshold`.
+    /// Threads already triaged are skipped unless `retriage` is set.
+    pub fn run_batch(&self, session_name: &str, auto_fix: bool, retriage: bool) -> Result<()> {
         let mut session = self.session_mgr.load_session(session_name)?;
 
-        if session.unresolved_comments.is_empty() {
-            println!("{} No unresolved comments!", "✓".green());
+        self.reanchor(&mut session)?;
+        let settings = self.shepherd_config();
+        let (threads, skipped) =
+            threads_to_triage(&session, retriage, &settings.confidence_threshold);
+        if skipped > 0 {
+            println!(
+                "{} Skipping {} already triaged thread(s); use --retriage to revisit them",
+                "ℹ".dimmed(),
+                skipped
+            );
+        }
+
+        if threads.is_empty() {
+            println!("{} No comments to triage!", "✓".green());
             return Ok(());
         }
 
+        let analyzer = analyzer::from_config(&settings)?;
+        let auto_fix = auto_fix || settings.auto_apply_safe;
+
         println!(
-            "{} Processing {} comments in batch mode...",
+            "{} Analyzing {} comments with {} ({})...",
             "→".cyan(),
-            session.unresolved_comments.len()
+            threads.len(),
+            analyzer.name(),
+            settings.analysis_model
         );
+        println!();
+
+        let mut fix_count = 0;
+        let mut review_count = 0;
+        let mut triaged_count = 0;
+        let mut failed_count = 0;
 
-        let mut fixed_count = 0;
-        let mut deferred_count = 0;
-
-        let comments_clone = session.unresolved_comments.clone();
-        for comment in &comments_clone {
-            // Simple heuristic: if comment contains certain keywords, mark as FIX
-            let should_fix = auto_fix
-                && (comment.body.to_lowercase().contains("typo")
-                    || comment.body.to_lowercase().contains("naming")
-                    || comment.body.to_lowercase().contains("formatting"));
-
-            if should_fix {
-                self.record_shepherd_action(
-                    &mut session,
-                    comment.id,
-                    ShepherdAction::Fix,
-                    ConfidenceLevel::Medium,
-                    "Auto-marked for fixing".to_string(),
-                )?;
-                fixed_count += 1;
+        for thread in &threads {
+            let comment = match thread.root() {
+                Some(comment) => comment,
+                None => continue,
+            };
+
+            let request = AnalysisRequest::from_thread(
+                thread,
+                comment,
+                &session.workbox_path,
+                settings.analyzer.context_lines,
+                &settings.analysis_model,
+            );
+            let mut analysis = match analyzer.analyze(&request) {
+                Ok(analysis) => analysis,
+                Err(e) => {
+                    println!("  {} #{}: {}", "⚠".yellow(), comment.id, e);
+                    failed_count += 1;
+                    continue;
+                }
+            };
+            analysis.summary = summarize(&comment.body);
+            analysis.decided_by = format!("{} ({})", analyzer.name(), settings.analysis_model);
+
+            let label = if !meets_threshold(analysis.confidence, &settings.confidence_threshold) {
+                review_count += 1;
+                "needs review".yellow()
+            } else if analysis.action == ShepherdAction::Fix && auto_fix {
+                fix_count += 1;
+                "auto-fix".green()
             } else {
-                self.record_shepherd_action(
-                    &mut session,
-                    comment.id,
-                    ShepherdAction::Defer,
-                    ConfidenceLevel::Low,
-                    "Needs manual review".to_string(),
-                )?;
-                deferred_count += 1;
-            }
+                triaged_count += 1;
+                "triaged".normal()
+            };
+            println!(
+                "  {} #{} {:?} ({}) {} - {}",
+                "•".dimmed(),
+                comment.id,
+                analysis.action,
+                analysis.confidence.as_str(),
+                label,
+                analysis.summary.dimmed()
+            );
+
+            self.record_shepherd_action(&mut session, analysis)?;
         }
 
         session.log_activity(
             ActivityType::ShepherdRun,
             format!(
-                "Batch processed: {} fixed, {} deferred",
-                fixed_count, deferred_count
+                "Batch analyzed: {} auto-fix, {} triaged, {} need review, {} failed",
+                fix_count, triaged_count, review_count, failed_count
             ),
         );
         self.session_mgr.save_session(&session)?;
 
+        println!();
         println!(
-            "{} Batch processing complete: {} marked for fix, {} deferred",
+            "{} Batch analysis complete: {} marked for fix, {} triaged, {} below confidence threshold",
             "✓".green(),
-            fixed_count,
-            deferred_count
+            fix_count,
+            triaged_count,
+            review_count
         );
+        if failed_count > 0 {
+            println!(
+                "{} {} comments could not be analyzed",
+                "⚠".yellow(),
+                failed_count
+            );
+        }
+
+        if fix_count > 0 {
+            println!();
+            self.apply_fixes(session_name)?;
+        }
 
         Ok(())
     }
 
-    /// Show shepherd status
-    pub fn show_status(&self, session_name: &str) -> Result<()> {
+    /// Show shepherd status: thread states and each comment's triage
+    /// history, grouped by action
+    ///
+    /// With `StatusFormat::Json`, prints every comment with its triage record.
+    pub fn show_status(&self, session_name: &str, format: StatusFormat) -> Result<()> {
         let session = self.session_mgr.load_session(session_name)?;
+        let entries = status_entries(&session);
+
+        if format == StatusFormat::Json {
+            let report = serde_json::json!({
+                "session": session.name,
+                "pr_number": session.pr_number,
+                "comments": entries,
+            });
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
 
         println!();
         println!("{} Shepherd Status", "🐕".bold());
         println!();
         println!("Session: {}", session.name);
-        println!("Total comments: {}", session.unresolved_comments.len());
-
-        // Count actions
-        let mut action_counts = std::collections::HashMap::new();
-        for comment in &session.unresolved_comments {
-            if comment.resolved {
-                let action_key = "resolved";
-                *action_counts.entry(action_key).or_insert(0) += 1;
-            } else {
-                *action_counts.entry("pending").or_insert(0) += 1;
+        println!("Total threads: {}", session.review_threads.len());
+
+        // Count threads by GitHub state
+        let resolved = session
+            .review_threads
+            .iter()
+            .filter(|t| t.is_resolved)
+            .count();
+        let outdated = session
+            .unresolved_threads()
+            .filter(|t| t.is_outdated)
+            .count();
+        let pending = session.review_threads.len() - resolved - outdated;
+
+        println!();
+        println!("Thread breakdown:");
+        println!("  resolved: {}", resolved);
+        println!("  outdated: {}", outdated);
+        println!("  pending: {}", pending);
+        println!();
+
+        let actions = [
+            ShepherdAction::Fix,
+            ShepherdAction::Clarify,
+            ShepherdAction::Acknowledge,
+            ShepherdAction::Defer,
+            ShepherdAction::Disagree,
+        ];
+        for action in &actions {
+            let group: Vec<&StatusEntry> = entries
+                .iter()
+                .filter(|e| e.triage.is_some_and(|t| &t.action == action))
+                .collect();
+            if group.is_empty() {
+                continue;
+            }
+
+            println!(
+                "{} ({})",
+                format!("{:?}", action).to_uppercase().bold(),
+                group.len()
+            );
+            println!(
+                "  {:<12} {:<8} {:<28} {:<17} {:<9} SUMMARY",
+                "COMMENT", "CONF", "DECIDED BY", "WHEN", "COMMIT"
+            );
+            for entry in group {
+                let Some(triage) = entry.triage else {
+                    continue;
+                };
+                println!(
+                    "  {:<12} {:<8} {:<28} {:<17} {:<9} {}{}",
+                    entry.comment_id,
+                    triage.confidence.as_str(),
+                    decider_label(&triage.decided_by),
+                    when(triage.decided_at),
+                    short_commit(triage.applied_commit.as_deref()),
+                    entry.summary,
+                    if entry.resolved { " (resolved)" } else { "" }
+                );
+                for earlier in triage.history.iter().rev() {
+                    println!(
+                        "  {:<12} {}",
+                        "",
+                        format!(
+                            "↳ was {:?} ({}) by {} at {}{}",
+                            earlier.action,
+                            earlier.confidence.as_str(),
+                            decider_label(&earlier.decided_by),
+                            when(earlier.decided_at),
+                            earlier
+                                .commit
+                                .as_deref()
+                                .map(|c| format!(", commit {}", short_commit(Some(c))))
+                                .unwrap_or_default()
+                        )
+                        .dimmed()
+                    );
+                }
+            }
+            println!();
+        }
+
+        let untriaged: Vec<&StatusEntry> = entries
+            .iter()
+            .filter(|e| e.triage.is_none() && !e.resolved)
+            .collect();
+        if !untriaged.is_empty() {
+            println!("{} ({})", "UNTRIAGED".yellow().bold(), untriaged.len());
+            for entry in untriaged {
+                println!("  {:<12} {}", entry.comment_id, entry.summary);
+            }
+            println!();
+        }
+
+        Ok(())
+    }
+
+    /// Apply recorded FIX analyses that meet the confidence threshold
+    ///
+    /// Suggestion blocks are applied verbatim; other fixes are made by the AI
+    /// tool running non-interactively in the workbox. Each fix is verified
+    /// with `verify_command` and committed separately.
+    pub fn apply_fixes(&self, session_name: &str) -> Result<()> {
+        let mut session = self.session_mgr.load_session(session_name)?;
+        let settings = self.shepherd_config();
+
+        let candidates: Vec<(ReviewThread, ShepherdAnalysis)> = session
+            .shepherd_analyses
+            .iter()
+            .filter(|a| {
+                a.action == ShepherdAction::Fix
+                    && a.applied_commit.is_none()
+                    && meets_threshold(a.confidence, &settings.confidence_threshold)
+            })
+            .filter_map(|a| {
+                session
+                    .unresolved_threads()
+                    .find(|t| t.root().is_some_and(|c| c.id == a.comment_id))
+                    .map(|t| (t.clone(), a.clone()))
+            })
+            .collect();
+
+        if candidates.is_empty() {
+            println!("{} No fixes meet the confidence threshold", "✓".green());
+            return Ok(());
+        }
+
+        println!(
+            "{} Applying {} fixes in {}",
+            "→".cyan(),
+            candidates.len(),
+            session.workbox_path.display()
+        );
+
+        let mut applied = 0;
+        for (thread, analysis) in &candidates {
+            let comment = match thread.root() {
+                Some(comment) => comment,
+                None => continue,
+            };
+
+            let result = fixes::apply_fix(
+                &session.workbox_path,
+                comment.id,
+                &analysis.summary,
+                settings.verify_command.as_deref(),
+                |dir| {
+                    let run_dir = ai_tool::new_run_dir(
+                        &session.context_dir,
+                        Some(&format!("fix-{}", comment.id)),
+                    );
+                    self.make_fix(dir, thread, comment, analysis, &settings, &run_dir)
+                },
+            );
+
+            match result {
+                Ok(FixResult::Committed(commit)) => {
+                    println!(
+                        "  {} #{} committed {}",
+                        "✓".green(),
+                        comment.id,
+                        &commit[..commit.len().min(8)]
+                    );
+                    session.log_activity(
+                        ActivityType::CommitMade,
+                        format!("Applied fix for review comment {}", comment.id),
+                    );
+                    session.metrics.increment_commits();
+                    if let Some(recorded) = session
+                        .shepherd_analyses
+                        .iter_mut()
+                        .find(|a| a.comment_id == comment.id)
+                    {
+                        recorded.applied_commit = Some(commit);
+                    }
+                    applied += 1;
+                }
+                Ok(FixResult::NoChanges) => {
+                    println!("  {} #{} produced no changes", "-".dimmed(), comment.id);
+                }
+                Ok(FixResult::VerifyFailed(output)) => {
+                    println!(
+                        "  {} #{} failed verification, rolled back",
+                        "✗".red(),
+                        comment.id
+                    );
+                    let lines: Vec<&str> = output.lines().collect();
+                    for line in &lines[lines.len().saturating_sub(5)..] {
+                        println!("    {}", line.dimmed());
+                    }
+                }
+                Err(e) => {
+                    println!("  {} #{}: {}", "⚠".yellow(), comment.id, e);
+                }
             }
         }
 
+        session.log_activity(
+            ActivityType::ShepherdRun,
+            format!("Applied {}/{} fixes", applied, candidates.len()),
+        );
+        self.session_mgr.save_session(&session)?;
+
         println!();
-        println!("Action breakdown:");
-        for (action, count) in action_counts {
-            println!("  {}: {}", action, count);
+        println!(
+            "{} Applied {}/{} fixes. Push them with 'git push'",
+            "✓".green(),
+            applied,
+            candidates.len()
+        );
+
+        Ok(())
+    }
+
+    /// Post recorded responses to the PR, resolving handled threads
+    pub fn post_responses(&self, session_name: &str, dry_run: bool) -> Result<()> {
+        let mut session = self.session_mgr.load_session(session_name)?;
+        let runtime = tokio::runtime::Runtime::new()
+            .map_err(|e| Error::Other(format!("Failed to create async runtime: {}", e)))?;
+        let client = runtime.block_on(self.pr_mgr.client(&session))?;
+
+        // Fresh threads so markers and resolution state are current
+        session.review_threads = runtime.block_on(client.review_threads())?;
+
+        let threshold = self.shepherd_config().confidence_threshold;
+        let workbox = &client.workbox_path;
+        let plan = plan_posts(&session, &threshold, |commit| match commit {
+            Some(commit) => is_pushed(workbox, commit),
+            None => fixes::is_clean(workbox).unwrap_or(false) && is_pushed(workbox, "HEAD"),
+        });
+        if plan.is_empty() {
+            println!("{} Nothing new to post", "✓".green());
+            self.session_mgr.save_session(&session)?;
+            return Ok(());
+        }
+
+        if dry_run {
+            println!("{} Dry run - nothing will be posted", "→".cyan());
         }
         println!();
 
+        let mut replied = 0;
+        let mut resolved = 0;
+        for post in &plan {
+            println!(
+                "{} Comment {} ({}) - {:?}",
+                "📝".bold(),
+                post.comment_id,
+                post.path,
+                post.action
+            );
+            if let Some(reply) = &post.reply {
+                for line in reply.lines().filter(|l| !l.starts_with("<!--")) {
+                    println!("   {} {}", "│".dimmed(), line);
+                }
+            }
+            if post.resolve {
+                println!("   {} resolve thread", "✓".green());
+            }
+
+            if dry_run {
+                continue;
+            }
+
+            if let Some(reply) = &post.reply {
+                runtime.block_on(client.reply(&post.thread_id, post.comment_id, reply))?;
+                replied += 1;
+            }
+            if post.resolve {
+                runtime.block_on(client.resolve_thread(&post.thread_id))?;
+                resolved += 1;
+            }
+
+            if let Some(analysis) = session
+                .shepherd_analyses
+                .iter_mut()
+                .find(|a| a.comment_id == post.comment_id)
+            {
+                analysis.posted |= post.reply.is_some();
+                analysis.resolved |= post.resolve;
+            }
+        }
+
+        if dry_run {
+            println!();
+            println!("Summary review:");
+            println!("{}", summary_body(&plan).dimmed());
+            return Ok(());
+        }
+
+        if replied > 0 {
+            runtime.block_on(client.submit_review(&summary_body(&plan)))?;
+        }
+
+        session.log_activity(
+            ActivityType::ShepherdRun,
+            format!("Posted {} replies, resolved {} threads", replied, resolved),
+        );
+        self.session_mgr.save_session(&session)?;
+
+        println!();
+        println!(
+            "{} Posted {} replies, resolved {} threads",
+            "✓".green(),
+            replied,
+            resolved
+        );
+
         Ok(())
     }
 
     // === Private helper methods ===
 
-    fn record_shepherd_action(
+    /// Make the change for one fix in `dir`, without committing
+    fn make_fix(
         &self,
-        session: &mut Session,
-        comment_id: u64,
-        action: ShepherdAction,
-        confidence: ConfidenceLevel,
-        response: String,
+        dir: &Path,
+        thread: &ReviewThread,
+        comment: &ReviewComment,
+        analysis: &ShepherdAnalysis,
+        settings: &ShepherdConfig,
+        run_dir: &Path,
     ) -> Result<()> {
-        // Update the comment with shepherd analysis
-        let mut found = false;
-        for comment in session.unresolved_comments.iter_mut() {
-            if comment.id == comment_id {
-                // Mark as resolved based on action
-                comment.resolved = matches!(
-                    action,
-                    ShepherdAction::Fix | ShepherdAction::Acknowledge | ShepherdAction::Disagree
-                );
-                found = true;
-                break;
+        // Reviewer suggestions apply verbatim while the lines are unchanged,
+        // wherever they have moved to
+        let unchanged = match &thread.anchor {
+            Some(anchor)
+                if matches!(anchor.status, AnchorStatus::Current | AnchorStatus::Moved) =>
+            {
+                anchor.line.map(|line| (anchor.path.as_str(), line))
+            }
+            Some(_) => None,
+            None => thread.line.map(|line| (thread.path.as_str(), line)),
+        };
+        if let (Some(suggestion), Some((path, line))) =
+            (fixes::extract_suggestion(&comment.body), unchanged)
+        {
+            let path = dir.join(path);
+            let content = fs::read_to_string(&path)?;
+            let span = thread
+                .line
+                .zip(thread.start_line)
+                .map_or(0, |(end, start)| end.saturating_sub(start));
+            let start = line.saturating_sub(span).max(1);
+            fs::write(
+                &path,
+                fixes::apply_suggestion(&content, start, line, &suggestion)?,
+            )?;
+            return Ok(());
+        }
+
+        let mut prompt = AnalysisRequest::from_thread(
+            thread,
+            comment,
+            dir,
+            settings.analyzer.context_lines,
+            &settings.analysis_model,
+        )
+        .prompt();
+        if !analysis.assessment.is_empty() {
+            prompt.push_str(&format!("\nAssessment: {}\n", analysis.assessment));
+        }
+        if let Some(changes) = &analysis.changes {
+            prompt.push_str(&format!("\nPlanned change: {}\n", changes));
+        }
+        prompt.push_str(
+            "\nMake the minimal code change that addresses this review comment. Do not commit.\n",
+        );
+
+        let ai_tool = &self.config.hp.ai_tool;
+        let record = HeadlessRun {
+            command: &ai_tool.command,
+            args: settings
+                .fix_args
+                .clone()
+                .or_else(|| ai_tool.headless_template(&ai_tool.command))
+                .unwrap_or_default(),
+            prompt: &prompt,
+            workdir: dir,
+            run_dir,
+            env: ai_tool.env.clone(),
+            timeout: Duration::from_secs(ai_tool.timeout_secs),
+            echo: false,
+        }
+        .run()?;
+
+        if !record.success() {
+            return Err(Error::AiToolFailed(format!(
+                "{} {} (output in {})",
+                ai_tool.command,
+                record.describe(),
+                record.run_dir.display()
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn shepherd_config(&self) -> ShepherdConfig {
+        self.config
+            .hp
+            .pr
+            .as_ref()
+            .map(|pr| pr.shepherd.clone())
+            .unwrap_or_default()
+    }
+
+    /// Re-anchor the session's threads to the workbox and save the result
+    fn reanchor(&self, session: &mut Session) -> Result<()> {
+        match anchor::reanchor_threads(&session.workbox_path, &mut session.review_threads) {
+            Ok(summary) => {
+                print_anchor_summary(&summary);
+                self.session_mgr.save_session(session)
+            }
+            Err(e) => {
+                println!("{} Could not re-anchor comments: {}", "⚠".yellow(), e);
+                Ok(())
             }
         }
+    }
+
+    fn record_shepherd_action(
+        &self,
+        session: &mut Session,
+        analysis: ShepherdAnalysis,
+    ) -> Result<()> {
+        // Only record actions for threads this session knows about
+        let found = session
+            .review_threads
+            .iter()
+            .any(|t| t.root().is_some_and(|c| c.id == analysis.comment_id));
         if !found {
             return Ok(());
         }
@@ -347,18 +957,470 @@ impl Shepherd {
             format!("# Shepherd Analysis\n\nSession: {}\n\n", session.name)
         };
 
-        content.push_str(&format!("\n## Comment ID: {}\n", comment_id));
-        content.push_str(&format!("Action: {:?}\n", action));
-        content.push_str(&format!("Confidence: {}\n", confidence.as_str()));
-        if !response.is_empty() {
-            content.push_str(&format!("Response: {}\n", response));
+        content.push_str(&format!("\n## Comment ID: {}\n", analysis.comment_id));
+        content.push_str(&format!("Action: {:?}\n", analysis.action));
+        content.push_str(&format!("Confidence: {}\n", analysis.confidence.as_str()));
+        if !analysis.assessment.is_empty() {
+            content.push_str(&format!("Assessment: {}\n", analysis.assessment));
+        }
+        if !analysis.response.is_empty() {
+            content.push_str(&format!("Response: {}\n", analysis.response));
+        }
+        if !analysis.decided_by.is_empty() {
+            content.push_str(&format!("Decided by: {}\n", analysis.decided_by));
         }
-        content.push_str(&format!("Timestamp: {}\n", chrono::Utc::now()));
+        let now = chrono::Utc::now();
+        content.push_str(&format!("Timestamp: {}\n", now));
 
         fs::write(&analysis_file, content)?;
 
+        // Keep the latest decision per comment, with earlier ones as history
+        let mut analysis = analysis;
+        analysis.decided_at.get_or_insert(now);
+        if let Some(i) = session
+            .shepherd_analyses
+            .iter()
+            .position(|a| a.comment_id == analysis.comment_id)
+        {
+            let previous = session.shepherd_analyses.remove(i);
+            analysis.history = previous.history.clone();
+            analysis.history.push(previous.to_record());
+        }
+        session.shepherd_analyses.push(analysis);
+
         Ok(())
     }
+
+    /// Spawn a Shepherd child session to work through the PR's unresolved
+    /// threads
+    ///
+    /// The child's workbox branches off the PR branch, and its context lists
+    /// each thread with any analysis already recorded. Returns the child's
+    /// name.
+    pub fn spawn(&self, session_name: &str, child_name: Option<&str>) -> Result<String> {
+        let parent = self.session_mgr.load_session(session_name)?;
+        if parent.pr_number.is_none() {
+            return Err(Error::Other(
+                "Session has no associated PR. Create one first with 'hp pr create'".to_string(),
+            ));
+        }
+        let unresolved = parent.unresolved_threads().count();
+        if unresolved == 0 {
+            return Err(Error::Other(format!(
+                "No unresolved threads. Run 'hp pr sync {}' first",
+                session_name
+            )));
+        }
+
+        let child_name = match child_name {
+            Some(name) => name.to_string(),
+            None => self.spawn_name(&parent),
+        };
+        println!(
+            "{} Spawning '{}' for {} unresolved thread(s)...",
+            "🐕".bold(),
+            child_name.bold(),
+            unresolved
+        );
+
+        let opts = WorkboxOptions {
+            from: Some(parent.branch.clone()),
+            ..Default::default()
+        };
+        self.session_mgr
+            .create_session(&child_name, AgentType::Shepherd, opts)?;
+        self.session_mgr
+            .link_parent_child(session_name, &child_name)?;
+
+        let child = self.session_mgr.load_session(&child_name)?;
+        self.context_mgr.init_context(&child)?;
+        let template = self.context_mgr.read_context(&child)?;
+        self.context_mgr
+            .write_context(&child, &render_spawn_context(&template, &parent))?;
+
+        println!();
+        println!(
+            "{} Shepherd session '{}' created",
+            "✓".green(),
+            child_name.bold()
+        );
+        println!("  Workbox: {}", child.workbox_path.display());
+        println!("  Branch: {} (from {})", child.branch, parent.branch);
+        println!();
+        println!("Next steps:");
+        println!(
+            "  hp launch {}                # Work through the comments",
+            child_name
+        );
+        println!(
+            "  hp shepherd {} --gather     # Merge the fixes into '{}'",
+            child_name, session_name
+        );
+
+        Ok(child_name)
+    }
+
+    /// Merge a spawned shepherd session's fixes into its parent
+    ///
+    /// Only this child is gathered; the session is then marked integrated.
+    pub fn gather_spawned(&self, session_name: &str) -> Result<()> {
+        let mut session = self.session_mgr.load_session(session_name)?;
+        if session.agent_type != AgentType::Shepherd {
+            return Err(Error::Other(format!(
+                "'{}' is not a shepherd session. Use 'hp gather' for other children",
+                session_name
+            )));
+        }
+        let parent_name = session.parent.clone().ok_or_else(|| {
+            Error::Other(format!("Shepherd session '{}' has no parent", session_name))
+        })?;
+
+        let orchestrator = Orchestrator::new(self.config.clone())?;
+        if !orchestrator.gather_child(&parent_name, session_name, false)? {
+            return Err(Error::Other(format!(
+                "Could not gather '{}' into '{}'",
+                session_name, parent_name
+            )));
+        }
+
+        session.status = SessionStatus::Integrated;
+        session.log_activity(
+            ActivityType::Integrated,
+            format!("Fixes gathered into '{}'", parent_name),
+        );
+        self.session_mgr.save_session(&session)?;
+
+        println!();
+        println!("Next steps:");
+        println!(
+            "  hp shepherd {} --post   # Reply to and resolve the threads",
+            parent_name
+        );
+
+        Ok(())
+    }
+
+    /// `<session>-shepherd`, numbered if taken
+    fn spawn_name(&self, parent: &Session) -> String {
+        let base = format!("{}-shepherd", parent.name);
+        (1..)
+            .map(|n| {
+                if n == 1 {
+                    base.clone()
+                } else {
+                    format!("{}-{}", base, n)
+                }
+            })
+            .find(|name| !self.session_mgr.session_exists(name))
+            .unwrap_or(base)
+    }
+}
+
+/// Context for a spawned shepherd session: the template's header, then the
+/// parent's PR and unresolved threads in place of the template's placeholders
+fn render_spawn_context(template: &str, parent: &Session) -> String {
+    // Keep the header and the Notes section; replace what's between
+    let header_end = template.find("## PR Details").unwrap_or(template.len());
+    let notes = template
+        .find("## Notes")
+        .map(|i| &template[i..])
+        .unwrap_or("## Notes\n\n");
+
+    let mut content = template[..header_end].trim_end().to_string();
+    content.push_str("\n\n## PR Details\n\n");
+    content.push_str(&format!("**Parent Session:** {}\n", parent.name));
+    if let Some(number) = parent.pr_number {
+        content.push_str(&format!("**PR Number:** #{}\n", number));
+    }
+    if let Some(url) = &parent.pr_url {
+        content.push_str(&format!("**PR URL:** {}\n", url));
+    }
+    content.push_str(&format!("**PR Branch:** {}\n\n", parent.branch));
+
+    content.push_str("## Unresolved Comments\n\n");
+    for (i, thread) in parent.unresolved_threads().enumerate() {
+        let Some(comment) = thread.root() else {
+            continue;
+        };
+        content.push_str(&format!("### Comment {} (ID: {})\n", i + 1, comment.id));
+        let (path, line) = thread.location();
+        content.push_str(&format!("**File:** {}\n", path));
+        if let Some(line) = line {
+            content.push_str(&format!("**Line:** {}\n", line));
+        }
+        content.push_str(&format!("**Author:** {}\n", comment.author));
+        content.push_str(&format!("**Comment:**\n{}\n\n", comment.body));
+        for reply in thread.replies() {
+            content.push_str(&format!("> **{}**: {}\n", reply.author, reply.body));
+        }
+        if !thread.replies().is_empty() {
+            content.push('\n');
+        }
+
+        let analysis = parent
+            .shepherd_analyses
+            .iter()
+            .find(|a| a.comment_id == comment.id);
+        content.push_str("**Analysis:**\n");
+        match analysis {
+            Some(analysis) => {
+                content.push_str(&format!("- Action: {:?}\n", analysis.action));
+                content.push_str(&format!("- Confidence: {}\n", analysis.confidence.as_str()));
+                if !analysis.assessment.is_empty() {
+                    content.push_str(&format!("- Assessment: {}\n", analysis.assessment));
+                }
+                content.push_str(&format!("\n**Response:** {}\n", analysis.response));
+            }
+            None => {
+                content.push_str("- Action: [FIX/CLARIFY/ACKNOWLEDGE/DEFER/DISAGREE]\n");
+                content.push_str("- Confidence: [HIGH/MEDIUM/LOW]\n\n**Response:**\n");
+            }
+        }
+        content.push_str("\n---\n\n");
+    }
+
+    content.push_str(notes);
+    content
+}
+
+/// A reply and/or resolution shepherd will perform on a review thread
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedPost {
+    pub comment_id: u64,
+    pub thread_id: String,
+    pub path: String,
+    pub action: ShepherdAction,
+    /// Reply body with marker; None if the reply is already on the PR
+    pub reply: Option<String>,
+    pub resolve: bool,
+}
+
+/// Lines shown around a comment's current position
+const CURRENT_CODE_LINES: usize = 3;
+
+/// Report threads whose code moved, changed or disappeared
+pub fn print_anchor_summary(summary: &anchor::AnchorSummary) {
+    if summary.moved + summary.changed + summary.deleted == 0 {
+        return;
+    }
+    println!(
+        "{} Re-anchored comments to the current code: {} moved, {} edited, {} deleted (outdated)",
+        "↪".cyan(),
+        summary.moved,
+        summary.changed,
+        summary.deleted
+    );
+}
+
+/// Unresolved threads to triage: all of them with `retriage`, otherwise
+/// those without a decision, or whose analysis still needs review. Also
+/// returns how many already triaged threads were skipped.
+fn threads_to_triage(
+    session: &Session,
+    retriage: bool,
+    threshold: &config::ConfidenceLevel,
+) -> (Vec<ReviewThread>, usize) {
+    let (triaged, pending): (Vec<&ReviewThread>, Vec<&ReviewThread>) =
+        session.unresolved_threads().partition(|thread| {
+            thread.root().is_some_and(|comment| {
+                session
+                    .shepherd_analyses
+                    .iter()
+                    .any(|a| a.comment_id == comment.id && !needs_review(a, threshold))
+            })
+        });
+    if retriage {
+        return (session.unresolved_threads().cloned().collect(), 0);
+    }
+    (pending.into_iter().cloned().collect(), triaged.len())
+}
+
+/// Whether an analyzer's decision fell below `confidence_threshold`; it is
+/// kept for a person to confirm, so it is never posted and is triaged again
+fn needs_review(analysis: &ShepherdAnalysis, threshold: &config::ConfidenceLevel) -> bool {
+    !decided_by_user(analysis) && !meets_threshold(analysis.confidence, threshold)
+}
+
+/// Whether a person, rather than the analyzer, made the decision
+fn decided_by_user(analysis: &ShepherdAnalysis) -> bool {
+    analysis.decided_by.starts_with("user")
+}
+
+/// Output format for `hp shepherd status`
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum StatusFormat {
+    /// Human-readable summary
+    #[default]
+    Table,
+    /// Every comment with its triage record
+    Json,
+}
+
+/// A review thread's root comment with its latest triage decision
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusEntry<'a> {
+    pub comment_id: u64,
+    pub thread_id: &'a str,
+    pub path: &'a str,
+    pub resolved: bool,
+    pub outdated: bool,
+    pub summary: String,
+    /// Latest decision, with earlier ones in its `history`
+    pub triage: Option<&'a ShepherdAnalysis>,
+}
+
+/// Every thread of the session, in PR order
+pub fn status_entries(session: &Session) -> Vec<StatusEntry<'_>> {
+    session
+        .review_threads
+        .iter()
+        .filter_map(|thread| {
+            let comment = thread.root()?;
+            Some(StatusEntry {
+                comment_id: comment.id,
+                thread_id: &thread.id,
+                path: &thread.path,
+                resolved: thread.is_resolved,
+                outdated: thread.is_outdated,
+                summary: summarize(&comment.body),
+                triage: session
+                    .shepherd_analyses
+                    .iter()
+                    .find(|a| a.comment_id == comment.id),
+            })
+        })
+        .collect()
+}
+
+fn decider_label(decided_by: &str) -> &str {
+    if decided_by.is_empty() {
+        "unknown"
+    } else {
+        decided_by
+    }
+}
+
+fn when(at: Option<chrono::DateTime<chrono::Utc>>) -> String {
+    at.map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn short_commit(commit: Option<&str>) -> &str {
+    commit.map(|c| &c[..c.len().min(7)]).unwrap_or("-")
+}
+
+/// "user (<git user.name>)", as the decider of interactive triage
+fn user_decider(workbox_path: &Path) -> String {
+    match git::run(Some(workbox_path), &["config", "user.name"]) {
+        Ok(name) if !name.is_empty() => format!("user ({})", name),
+        _ => "user".to_string(),
+    }
+}
+
+/// Hidden marker identifying shepherd's reply to a comment
+pub fn shepherd_marker(comment_id: u64) -> String {
+    format!("<!-- hp-shepherd:{} -->", comment_id)
+}
+
+/// Whether a comment was posted by shepherd
+pub fn is_shepherd_reply(body: &str) -> bool {
+    body.contains("<!-- hp-shepherd:")
+}
+
+/// Work out what to post for each recorded analysis
+///
+/// Replies already carrying the marker are skipped, so re-running never
+/// double-posts. FIX threads are only resolved once their applied commit
+/// is on the upstream, as reported by `is_pushed`. Fixes a user made by
+/// hand have no applied commit; `is_pushed(None)` reports whether the
+/// workbox is clean with HEAD pushed. Analyses below `threshold` are left
+/// out until someone confirms them.
+pub fn plan_posts(
+    session: &Session,
+    threshold: &config::ConfidenceLevel,
+    is_pushed: impl Fn(Option<&str>) -> bool,
+) -> Vec<PlannedPost> {
+    let mut plan = Vec::new();
+
+    for analysis in &session.shepherd_analyses {
+        if needs_review(analysis, threshold) {
+            continue;
+        }
+        let thread = match session
+            .review_threads
+            .iter()
+            .find(|t| t.root().is_some_and(|c| c.id == analysis.comment_id))
+        {
+            Some(thread) if !thread.is_resolved => thread,
+            _ => continue,
+        };
+
+        let marker = shepherd_marker(analysis.comment_id);
+        let already_posted = thread.comments.iter().any(|c| c.body.contains(&marker));
+        let reply = if already_posted || analysis.response.trim().is_empty() {
+            None
+        } else {
+            Some(format!("{}\n\n{}", analysis.response.trim(), marker))
+        };
+
+        let resolve = match analysis.action {
+            ShepherdAction::Acknowledge => true,
+            ShepherdAction::Fix => match analysis.applied_commit.as_deref() {
+                Some(commit) => is_pushed(Some(commit)),
+                None => decided_by_user(analysis) && is_pushed(None),
+            },
+            _ => false,
+        };
+
+        if reply.is_some() || resolve {
+            plan.push(PlannedPost {
+                comment_id: analysis.comment_id,
+                thread_id: thread.id.clone(),
+                path: thread.path.clone(),
+                action: analysis.action.clone(),
+                reply,
+                resolve,
+            });
+        }
+    }
+
+    plan
+}
+
+/// Body of the summary review submitted after posting
+pub fn summary_body(posts: &[PlannedPost]) -> String {
+    let mut body = String::from("### 🐕 Shepherd summary\n\n");
+    body.push_str("| Comment | File | Action | Resolved |\n");
+    body.push_str("|---|---|---|---|\n");
+    for post in posts {
+        body.push_str(&format!(
+            "| {} | `{}` | {:?} | {} |\n",
+            post.comment_id,
+            post.path,
+            post.action,
+            if post.resolve { "yes" } else { "no" }
+        ));
+    }
+    body.push_str("\n<!-- hp-shepherd:summary -->\n");
+    body
+}
+
+/// Whether `commit` is on the workbox branch's upstream
+fn is_pushed(workbox_path: &Path, commit: &str) -> bool {
+    git::run(
+        Some(workbox_path),
+        &["merge-base", "--is-ancestor", commit, "@{u}"],
+    )
+    .is_ok()
+}
+
+fn summarize(body: &str) -> String {
+    let first_line = body.lines().next().unwrap_or_default();
+    if first_line.chars().count() > 80 {
+        format!("{}…", first_line.chars().take(79).collect::<String>())
+    } else {
+        first_line.to_string()
+    }
 }
 
 #[cfg(test)]
@@ -378,4 +1440,246 @@ mod tests {
             }
         }
     }
+
+    fn comment(id: u64, body: &str) -> ReviewComment {
+        ReviewComment {
+            id,
+            path: "src/lib.rs".to_string(),
+            line: Some(1),
+            body: body.to_string(),
+            author: "reviewer".to_string(),
+            created_at: chrono::Utc::now(),
+            updated_at: None,
+            diff_hunk: None,
+        }
+    }
+
+    fn thread(id: &str, comments: Vec<ReviewComment>, resolved: bool) -> ReviewThread {
+        ReviewThread {
+            id: id.to_string(),
+            path: "src/lib.rs".to_string(),
+            line: Some(1),
+            start_line: None,
+            original_line: Some(1),
+            is_resolved: resolved,
+            is_outdated: false,
+            comments,
+            anchor: None,
+        }
+    }
+
+    fn analysis(comment_id: u64, action: ShepherdAction, response: &str) -> ShepherdAnalysis {
+        ShepherdAnalysis {
+            comment_id,
+            action,
+            confidence: ConfidenceLevel::High,
+            summary: String::new(),
+            assessment: String::new(),
+            changes: None,
+            response: response.to_string(),
+            posted: false,
+            resolved: false,
+            applied_commit: None,
+            decided_by: String::new(),
+            decided_at: None,
+            history: Vec::new(),
+        }
+    }
+
+    fn session_with(threads: Vec<ReviewThread>, analyses: Vec<ShepherdAnalysis>) -> Session {
+        let mut session = Session::new(
+            "s".to_string(),
+            crate::models::AgentType::Shepherd,
+            "s".to_string(),
+            std::path::PathBuf::from("/tmp/s"),
+            "b".to_string(),
+            "main".to_string(),
+            "repo".to_string(),
+            "git".to_string(),
+        );
+        session.review_threads = threads;
+        session.shepherd_analyses = analyses;
+        session
+    }
+
+    #[test]
+    fn test_plan_posts() {
+        let session = session_with(
+            vec![
+                thread("T1", vec![comment(1, "Rename")], false),
+                thread("T2", vec![comment(2, "Fix typo")], false),
+                thread("T3", vec![comment(3, "Why?")], false),
+                thread("T4", vec![comment(4, "Old")], true),
+            ],
+            vec![
+                analysis(1, ShepherdAction::Acknowledge, "Good catch"),
+                analysis(2, ShepherdAction::Fix, "Fixed"),
+                analysis(3, ShepherdAction::Defer, ""),
+                analysis(4, ShepherdAction::Acknowledge, "Done"),
+            ],
+        );
+
+        let plan = plan_posts(&session, &config::ConfidenceLevel::High, |_| false);
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[0].thread_id, "T1");
+        assert!(plan[0].resolve);
+        assert!(plan[0]
+            .reply
+            .as_deref()
+            .unwrap()
+            .ends_with(&shepherd_marker(1)));
+
+        // FIX threads stay open until the applied commit is pushed
+        assert_eq!(plan[1].thread_id, "T2");
+        assert!(!plan[1].resolve);
+        assert!(!plan_posts(&session, &config::ConfidenceLevel::High, |_| true)[1].resolve);
+
+        let mut session = session;
+        session.shepherd_analyses[1].applied_commit = Some("abc123".to_string());
+        assert!(
+            !plan_posts(&session, &config::ConfidenceLevel::High, |c| c
+                == Some("def456"))[1]
+                .resolve
+        );
+        assert!(
+            plan_posts(&session, &config::ConfidenceLevel::High, |c| c
+                == Some("abc123"))[1]
+                .resolve
+        );
+
+        // A fix made by hand resolves once the workbox is clean and pushed
+        session.shepherd_analyses[1].applied_commit = None;
+        session.shepherd_analyses[1].decided_by = "user (Ann)".to_string();
+        assert!(plan_posts(&session, &config::ConfidenceLevel::High, |c| c.is_none())[1].resolve);
+        assert!(!plan_posts(&session, &config::ConfidenceLevel::High, |_| false)[1].resolve);
+    }
+
+    #[test]
+    fn test_below_threshold_analyses_need_review() {
+        let mut unsure = analysis(1, ShepherdAction::Acknowledge, "Maybe");
+        unsure.confidence = ConfidenceLevel::Low;
+        unsure.decided_by = "anthropic (claude)".to_string();
+        let mut confirmed = analysis(2, ShepherdAction::Acknowledge, "Sure");
+        confirmed.confidence = ConfidenceLevel::Low;
+        confirmed.decided_by = "user (Ann)".to_string();
+        let session = session_with(
+            vec![
+                thread("T1", vec![comment(1, "Rename")], false),
+                thread("T2", vec![comment(2, "Typo")], false),
+            ],
+            vec![unsure, confirmed],
+        );
+
+        let plan = plan_posts(&session, &config::ConfidenceLevel::High, |_| true);
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].thread_id, "T2");
+
+        let (pending, skipped) = threads_to_triage(&session, false, &config::ConfidenceLevel::High);
+        let pending: Vec<String> = pending.into_iter().map(|t| t.id).collect();
+        assert_eq!(pending, vec!["T1"]);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn test_plan_posts_is_idempotent() {
+        let posted = format!("Good catch\n\n{}", shepherd_marker(1));
+        let session = session_with(
+            vec![thread(
+                "T1",
+                vec![comment(1, "Rename"), comment(10, &posted)],
+                false,
+            )],
+            vec![analysis(1, ShepherdAction::Clarify, "Good catch")],
+        );
+
+        assert!(plan_posts(&session, &config::ConfidenceLevel::High, |_| true).is_empty());
+    }
+
+    #[test]
+    fn test_summary_body() {
+        let session = session_with(
+            vec![thread("T1", vec![comment(1, "Rename")], false)],
+            vec![analysis(1, ShepherdAction::Acknowledge, "Thanks")],
+        );
+        let body = summary_body(&plan_posts(
+            &session,
+            &config::ConfidenceLevel::High,
+            |_| false,
+        ));
+        assert!(body.contains("| 1 | `src/lib.rs` | Acknowledge | yes |"));
+        assert!(body.contains("<!-- hp-shepherd:summary -->"));
+    }
+
+    #[test]
+    fn test_render_spawn_context() {
+        let mut parent = session_with(
+            vec![
+                thread(
+                    "T1",
+                    vec![comment(1, "Rename this"), comment(5, "+1")],
+                    false,
+                ),
+                thread("T2", vec![comment(2, "Add a test")], false),
+                thread("T3", vec![comment(3, "Old")], true),
+            ],
+            vec![analysis(1, ShepherdAction::Fix, "Renamed")],
+        );
+        parent.name = "auth".to_string();
+        parent.pr_number = Some(42);
+
+        let template = crate::context::get_builtin_template(&AgentType::Shepherd)
+            .replace("{{session_name}}", "auth-shepherd");
+        let context = render_spawn_context(&template, &parent);
+
+        assert!(context.starts_with("# Shepherd Session: auth-shepherd"));
+        assert!(context.contains("**Parent Session:** auth\n**PR Number:** #42\n"));
+        assert!(context.contains("### Comment 1 (ID: 1)"));
+        assert!(context.contains("> **reviewer**: +1"));
+        assert!(context.contains("- Action: Fix\n- Confidence: HIGH\n\n**Response:** Renamed"));
+        assert!(context.contains("### Comment 2 (ID: 2)"));
+        assert!(context.contains("- Action: [FIX/CLARIFY/ACKNOWLEDGE/DEFER/DISAGREE]"));
+        assert!(!context.contains("Old"));
+        assert!(!context.contains("**PR Title:**"));
+        assert!(context.trim_end().ends_with("## Notes"));
+    }
+
+    #[test]
+    fn test_triage_skips_decided_threads_and_reports_history() {
+        let mut earlier = analysis(1, ShepherdAction::Acknowledge, "Will do");
+        earlier.decided_by = "user (Ann)".to_string();
+        let mut latest = analysis(1, ShepherdAction::Fix, "Renamed");
+        latest.decided_by = "anthropic (claude)".to_string();
+        latest.applied_commit = Some("abc1234def".to_string());
+        latest.history = vec![earlier.to_record()];
+
+        let session = session_with(
+            vec![
+                thread("T1", vec![comment(1, "Rename this")], false),
+                thread("T2", vec![comment(2, "Add a test")], false),
+                thread("T3", vec![comment(3, "Old")], true),
+            ],
+            vec![latest],
+        );
+
+        let (pending, skipped) = threads_to_triage(&session, false, &config::ConfidenceLevel::High);
+        let pending: Vec<String> = pending.into_iter().map(|t| t.id).collect();
+        assert_eq!(pending, vec!["T2"]);
+        assert_eq!(skipped, 1);
+        let (all, skipped) = threads_to_triage(&session, true, &config::ConfidenceLevel::High);
+        assert_eq!((all.len(), skipped), (2, 0));
+
+        let entries = status_entries(&session);
+        assert_eq!(entries.len(), 3);
+        assert!(entries[1].triage.is_none());
+        assert!(entries[2].resolved);
+
+        let json = serde_json::to_value(&entries).unwrap();
+        let triage = &json[0]["triage"];
+        assert_eq!(triage["action"], "FIX");
+        assert_eq!(triage["decided_by"], "anthropic (claude)");
+        assert_eq!(triage["applied_commit"], "abc1234def");
+        assert_eq!(triage["history"][0]["action"], "ACKNOWLEDGE");
+        assert_eq!(triage["history"][0]["notes"], "Will do");
+        assert_eq!(short_commit(Some("abc1234def")), "abc1234");
+    }
 }
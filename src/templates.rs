@@ -1,14 +1,18 @@
 //! Template marketplace for sharing context templates
 
-use crate::config::Config;
+use crate::config::{Config, MarketplaceConfig, RegistryConfig};
 use crate::error::{Error, Result};
+use crate::git;
 use crate::progress;
 use colored::Colorize;
 use reqwest::blocking::Client;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
-use std::time::{Duration, SystemTime};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Template metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,41 +31,344 @@ struct TemplateSearchResult {
     templates: Vec<TemplateMetadata>,
 }
 
+/// A single published version of a template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TemplateRelease {
+    version: String,
+    #[serde(default)]
+    sha256: Option<String>,
+}
+
+/// Version listing returned by the registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TemplateVersionList {
+    versions: Vec<TemplateRelease>,
+}
+
+/// A template resolved to a concrete version
+#[derive(Debug, Clone)]
+struct ResolvedTemplate {
+    version: Version,
+    content: String,
+    sha256: String,
+    source: String,
+}
+
+/// Lockfile pinning registry templates to exact versions and content hashes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TemplateLock {
+    #[serde(default = "default_lock_version")]
+    pub version: u32,
+    /// Registry templates, keyed by the name they're installed under
+    #[serde(default)]
+    pub templates: BTreeMap<String, LockedTemplate>,
+    /// Git template sources, keyed by `git+<url>#<ref>:<path>` spec
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub git: BTreeMap<String, LockedGitSource>,
+}
+
+/// Lockfile entry for a single installed template
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LockedTemplate {
+    /// Exact installed version
+    pub version: String,
+    /// Version requirement the template was installed with
+    pub requirement: String,
+    /// Registry the template was resolved from
+    pub source: String,
+    /// SHA-256 of the template content
+    pub sha256: String,
+    /// Name in the registry, if the template was installed under another
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry_name: Option<String>,
+}
+
+impl LockedTemplate {
+    /// Registry name of the template installed as `name`
+    pub fn registry_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.registry_name.as_deref().unwrap_or(name)
+    }
+}
+
+/// Lockfile entry for a git template source
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LockedGitSource {
+    pub url: String,
+    /// Branch, tag or commit being tracked (remote HEAD if unset)
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    /// Template file or directory within the repository
+    #[serde(default)]
+    pub path: String,
+    /// Name override when the source is a single template
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Pinned commit
+    pub commit: String,
+    /// Installed template names and their SHA-256
+    #[serde(default)]
+    pub templates: BTreeMap<String, String>,
+}
+
+/// Parsed `git+<url>[#<ref>][:<path>]` template source
+#[derive(Debug, Clone, PartialEq)]
+struct GitTemplateSpec {
+    url: String,
+    reference: Option<String>,
+    path: String,
+}
+
+impl GitTemplateSpec {
+    fn parse(spec: &str) -> Result<Self> {
+        let rest = spec.strip_prefix("git+").ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "Git template source must start with git+: {}",
+                spec
+            ))
+        })?;
+
+        let (url, fragment) = rest.split_once('#').unwrap_or((rest, ""));
+        if url.is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "Missing repository URL in '{}'",
+                spec
+            )));
+        }
+
+        // Git refs can't contain ':', so the first one starts the path
+        let (reference, path) = fragment.split_once(':').unwrap_or((fragment, ""));
+
+        // Git would read these as options
+        if url.starts_with('-') || reference.starts_with('-') {
+            return Err(Error::InvalidInput(format!(
+                "Repository URL and ref must not start with '-': {}",
+                spec
+            )));
+        }
+
+        Ok(Self {
+            url: url.to_string(),
+            reference: (!reference.is_empty()).then(|| reference.to_string()),
+            path: path.trim_matches('/').to_string(),
+        })
+    }
+
+    /// Canonical spec string used as the lockfile key
+    fn key(&self) -> String {
+        let mut key = format!("git+{}", self.url);
+        if self.reference.is_some() || !self.path.is_empty() {
+            key.push('#');
+            key.push_str(self.reference.as_deref().unwrap_or(""));
+        }
+        if !self.path.is_empty() {
+            key.push(':');
+            key.push_str(&self.path);
+        }
+        key
+    }
+}
+
+/// Collect templates under the spec's path at a commit
+fn collect_git_templates(
+    repo: &Path,
+    commit: &str,
+    path: &str,
+    name: Option<&str>,
+) -> Result<BTreeMap<String, String>> {
+    let files: Vec<String> = git::list_files(repo, commit, path)?
+        .into_iter()
+        .filter(|f| f.ends_with(".md"))
+        .filter(|f| {
+            !f.rsplit('/')
+                .next()
+                .unwrap_or(f)
+                .eq_ignore_ascii_case("readme.md")
+        })
+        .collect();
+
+    if files.is_empty() {
+        return Err(Error::Other(format!(
+            "No templates found at '{}' in commit {}",
+            if path.is_empty() { "/" } else { path },
+            &commit[..commit.len().min(7)]
+        )));
+    }
+
+    if name.is_some() && files.len() > 1 {
+        return Err(Error::InvalidInput(
+            "--name can only be used when the source is a single template".to_string(),
+        ));
+    }
+
+    let mut templates = BTreeMap::new();
+    for file in files {
+        let stem = Path::new(&file)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let template_name = name.map(|n| n.to_string()).unwrap_or(stem);
+        let content = git::show_file(repo, commit, &file)?;
+
+        if templates.insert(template_name.clone(), content).is_some() {
+            return Err(Error::Other(format!(
+                "Duplicate template name '{}' in collection",
+                template_name
+            )));
+        }
+    }
+
+    Ok(templates)
+}
+
+fn default_lock_version() -> u32 {
+    1
+}
+
+impl Default for TemplateLock {
+    fn default() -> Self {
+        Self {
+            version: default_lock_version(),
+            templates: BTreeMap::new(),
+            git: BTreeMap::new(),
+        }
+    }
+}
+
+impl TemplateLock {
+    /// Load lockfile, returning an empty lock if it doesn't exist yet
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        let lock: TemplateLock = serde_yaml::from_str(&content)?;
+        Ok(lock)
+    }
+
+    /// Write lockfile to disk
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let header =
+            "# Generated by hupasiya. Commit this file so everyone installs identical templates.\n";
+        let content = serde_yaml::to_string(self)?;
+        fs::write(path, format!("{}{}", header, content))?;
+        Ok(())
+    }
+}
+
+/// Parse a `name[@requirement]` template spec
+fn parse_template_spec(spec: &str) -> Result<(String, Option<VersionReq>)> {
+    match spec.split_once('@') {
+        Some((name, req)) => {
+            if name.is_empty() {
+                return Err(Error::InvalidInput(format!(
+                    "Missing template name in '{}'",
+                    spec
+                )));
+            }
+            let requirement = VersionReq::parse(req).map_err(|e| {
+                Error::InvalidInput(format!("Invalid version requirement '{}': {}", req, e))
+            })?;
+            Ok((name.to_string(), Some(requirement)))
+        }
+        None => Ok((spec.to_string(), None)),
+    }
+}
+
+/// Pick the newest release matching a requirement (any release if none given)
+fn select_version(
+    releases: &[TemplateRelease],
+    requirement: Option<&VersionReq>,
+) -> Option<Version> {
+    releases
+        .iter()
+        .filter_map(|r| Version::parse(&r.version).ok())
+        .filter(|v| match requirement {
+            Some(req) => req.matches(v),
+            None => v.pre.is_empty(),
+        })
+        .max()
+}
+
+/// Hex-encoded SHA-256 of template content
+fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Where a registry's templates live
+#[derive(Debug, Clone, PartialEq)]
+enum RegistryLocation {
+    /// HTTP(S) registry API
+    Remote(String),
+    /// Directory mirror laid out as `<root>/<name>/<version>.md`
+    Local(PathBuf),
+}
+
+impl RegistryLocation {
+    fn parse(url: &str) -> Self {
+        if let Some(path) = url.strip_prefix("file://") {
+            RegistryLocation::Local(PathBuf::from(path))
+        } else if url.starts_with("http://") || url.starts_with("https://") {
+            RegistryLocation::Remote(url.trim_end_matches('/').to_string())
+        } else {
+            RegistryLocation::Local(PathBuf::from(url))
+        }
+    }
+}
+
 /// Template registry client
 struct TemplateRegistry {
-    registry_url: String,
+    name: String,
+    location: RegistryLocation,
+    token: Option<String>,
     client: Client,
-    cache_dir: PathBuf,
 }
 
-#[allow(dead_code)]
 impl TemplateRegistry {
-    /// Create new registry client
-    fn new() -> Result<Self> {
-        let cache_dir = dirs::cache_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("hupasiya")
-            .join("templates");
-        fs::create_dir_all(&cache_dir)?;
+    /// Create registry client from config
+    fn new(config: &RegistryConfig, client: Client) -> Self {
+        Self {
+            name: config.name.clone(),
+            location: RegistryLocation::parse(&config.url),
+            token: config.resolve_token(),
+            client,
+        }
+    }
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(10))
-            .build()
-            .map_err(|e| Error::Other(format!("Failed to create HTTP client: {}", e)))?;
+    /// Registry identifier recorded in the lockfile
+    fn source(&self) -> String {
+        match &self.location {
+            RegistryLocation::Remote(url) => url.clone(),
+            RegistryLocation::Local(root) => format!("file://{}", root.display()),
+        }
+    }
 
-        Ok(Self {
-            registry_url: "https://hp-templates.dev".to_string(),
-            client,
-            cache_dir,
-        })
+    fn get(&self, url: &str) -> reqwest::blocking::RequestBuilder {
+        let request = self.client.get(url);
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
     }
 
     /// Search templates in registry
     fn search(&self, query: &str) -> Result<Vec<TemplateMetadata>> {
-        let url = format!("{}/api/search?q={}", self.registry_url, query);
+        let base = match &self.location {
+            RegistryLocation::Remote(url) => url,
+            RegistryLocation::Local(root) => return Self::search_local(root, query),
+        };
+        let url = format!("{}/api/search?q={}", base, query);
 
-        let spinner = progress::spinner("Searching registry...");
-        let result = match self.client.get(&url).send() {
+        let spinner = progress::spinner(&format!("Searching {}...", self.name));
+        let result = match self.get(&url).send() {
             Ok(response) if response.status().is_success() => {
                 let result: TemplateSearchResult = response
                     .json()
@@ -72,14 +379,20 @@ impl TemplateRegistry {
             Ok(response) => {
                 progress::finish_error(&spinner, "Registry error");
                 Err(Error::Other(format!(
-                    "Registry returned error: {}",
+                    "Registry '{}' returned error: {}",
+                    self.name,
                     response.status()
                 )))
             }
             Err(e) => {
                 // Registry unavailable - use local fallback
                 spinner.finish_and_clear();
-                eprintln!("{} Registry unavailable: {}", "⚠".yellow(), e);
+                eprintln!(
+                    "{} Registry '{}' unavailable: {}",
+                    "⚠".yellow(),
+                    self.name,
+                    e
+                );
                 eprintln!("{}", "  Falling back to local templates only".dimmed());
                 Ok(Vec::new())
             }
@@ -87,25 +400,56 @@ impl TemplateRegistry {
         result
     }
 
-    /// Install template from registry
-    fn install(&self, name: &str, version: Option<&str>) -> Result<String> {
-        let version_str = version.unwrap_or("latest");
-        let url = format!(
-            "{}/api/templates/{}/{}",
-            self.registry_url, name, version_str
-        );
+    /// List published versions of a template
+    fn versions(&self, name: &str) -> Result<Vec<TemplateRelease>> {
+        let base = match &self.location {
+            RegistryLocation::Remote(url) => url,
+            RegistryLocation::Local(root) => return Self::versions_local(root, name),
+        };
+        let url = format!("{}/api/templates/{}/versions", base, name);
+
+        match self.get(&url).send() {
+            Ok(response) if response.status().is_success() => {
+                let list: TemplateVersionList = response
+                    .json()
+                    .map_err(|e| Error::Other(format!("Failed to parse version list: {}", e)))?;
+                Ok(list.versions)
+            }
+            Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => {
+                Err(Error::TemplateNotFound(name.to_string()))
+            }
+            Ok(response) => Err(Error::Other(format!(
+                "Registry '{}' returned error: {}",
+                self.name,
+                response.status()
+            ))),
+            Err(e) => Err(Error::Other(format!(
+                "Registry '{}' unavailable: {}",
+                self.name, e
+            ))),
+        }
+    }
+
+    /// Download template content for an exact version
+    fn download(&self, name: &str, version: &Version) -> Result<String> {
+        let base = match &self.location {
+            RegistryLocation::Remote(url) => url,
+            RegistryLocation::Local(root) => {
+                let path = root.join(name).join(format!("{}.md", version));
+                if !path.exists() {
+                    return Err(Error::TemplateNotFound(format!("{}@{}", name, version)));
+                }
+                return Ok(fs::read_to_string(path)?);
+            }
+        };
+        let url = format!("{}/api/templates/{}/{}", base, name, version);
 
-        let spinner = progress::spinner(&format!("Downloading {} {}...", name, version_str));
-        let result = match self.client.get(&url).send() {
+        let spinner = progress::spinner(&format!("Downloading {} {}...", name, version));
+        let result = match self.get(&url).send() {
             Ok(response) if response.status().is_success() => {
                 let content = response
                     .text()
                     .map_err(|e| Error::Other(format!("Failed to read template: {}", e)))?;
-
-                // Cache the template
-                let cache_path = self.cache_dir.join(format!("{}.md", name));
-                fs::write(&cache_path, &content)?;
-
                 progress::finish_success(&spinner, "Download complete");
                 Ok(content)
             }
@@ -118,7 +462,10 @@ impl TemplateRegistry {
             }
             Err(e) => {
                 progress::finish_error(&spinner, "Registry unavailable");
-                Err(Error::Other(format!("Registry unavailable: {}", e)))
+                Err(Error::Other(format!(
+                    "Registry '{}' unavailable: {}",
+                    self.name, e
+                )))
             }
         };
         result
@@ -126,7 +473,16 @@ impl TemplateRegistry {
 
     /// Publish template to registry
     fn publish(&self, metadata: &TemplateMetadata, content: &str) -> Result<()> {
-        let url = format!("{}/api/publish", self.registry_url);
+        let base = match &self.location {
+            RegistryLocation::Remote(url) => url,
+            RegistryLocation::Local(root) => {
+                let dir = root.join(&metadata.name);
+                fs::create_dir_all(&dir)?;
+                fs::write(dir.join(format!("{}.md", metadata.version)), content)?;
+                return Ok(());
+            }
+        };
+        let url = format!("{}/api/publish", base);
 
         #[derive(Serialize)]
         struct PublishRequest<'a> {
@@ -136,8 +492,13 @@ impl TemplateRegistry {
 
         let payload = PublishRequest { metadata, content };
 
+        let mut request = self.client.post(&url).json(&payload);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
         let spinner = progress::spinner(&format!("Publishing {}...", metadata.name));
-        let result = match self.client.post(&url).json(&payload).send() {
+        let result = match request.send() {
             Ok(response) if response.status().is_success() => {
                 progress::finish_success(&spinner, "Published successfully");
                 Ok(())
@@ -151,32 +512,305 @@ impl TemplateRegistry {
             }
             Err(e) => {
                 progress::finish_error(&spinner, "Registry unavailable");
-                Err(Error::Other(format!("Registry unavailable: {}", e)))
+                Err(Error::Other(format!(
+                    "Registry '{}' unavailable: {}",
+                    self.name, e
+                )))
             }
         };
         result
     }
 
-    /// Check if cache is fresh (less than 1 hour old)
-    fn is_cache_fresh(&self, name: &str) -> bool {
-        let cache_path = self.cache_dir.join(format!("{}.md", name));
+    fn search_local(root: &Path, query: &str) -> Result<Vec<TemplateMetadata>> {
+        let mut results = Vec::new();
+        if !root.exists() {
+            return Ok(results);
+        }
 
-        if let Ok(metadata) = fs::metadata(&cache_path) {
-            if let Ok(modified) = metadata.modified() {
-                if let Ok(elapsed) = SystemTime::now().duration_since(modified) {
-                    return elapsed < Duration::from_secs(3600); // 1 hour
+        let query = query.to_lowercase();
+        for entry in fs::read_dir(root)? {
+            let path = entry?.path();
+            let name = match path.file_name().and_then(|s| s.to_str()) {
+                Some(name) if path.is_dir() => name.to_string(),
+                _ => continue,
+            };
+            if !name.to_lowercase().contains(&query) {
+                continue;
+            }
+
+            let releases = Self::versions_local(root, &name)?;
+            let version = match select_version(&releases, None) {
+                Some(version) => version,
+                None => continue,
+            };
+            let content = fs::read_to_string(path.join(format!("{}.md", version)))?;
+
+            results.push(TemplateMetadata {
+                name,
+                author: "unknown".to_string(),
+                version: version.to_string(),
+                description: content.lines().take(3).collect::<Vec<_>>().join(" "),
+                tags: vec![],
+                agent_types: vec![],
+            });
+        }
+
+        results.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(results)
+    }
+
+    fn versions_local(root: &Path, name: &str) -> Result<Vec<TemplateRelease>> {
+        let dir = root.join(name);
+        if !dir.is_dir() {
+            return Err(Error::TemplateNotFound(name.to_string()));
+        }
+
+        let mut releases = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|s| s.to_str()) != Some("md") {
+                continue;
+            }
+            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                if Version::parse(stem).is_ok() {
+                    releases.push(TemplateRelease {
+                        version: stem.to_string(),
+                        sha256: None,
+                    });
                 }
             }
         }
 
-        false
+        Ok(releases)
+    }
+}
+
+/// Configured registries, consulted in priority order, plus the shared cache
+struct TemplateRegistries {
+    registries: Vec<TemplateRegistry>,
+    cache_dir: PathBuf,
+    offline: bool,
+}
+
+impl TemplateRegistries {
+    /// Build registry clients from marketplace config
+    fn new(config: &MarketplaceConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| Error::Other(format!("Failed to create HTTP client: {}", e)))?;
+
+        // With the marketplace disabled only local mirrors are consulted
+        let offline = !config.enabled;
+        let registries = config
+            .effective_registries()
+            .iter()
+            .map(|r| TemplateRegistry::new(r, client.clone()))
+            .filter(|r| !offline || matches!(r.location, RegistryLocation::Local(_)))
+            .collect();
+
+        Ok(Self {
+            registries,
+            cache_dir: config.cache_dir.clone(),
+            offline,
+        })
     }
 
-    /// Get template from cache
-    fn get_cached(&self, name: &str) -> Result<String> {
-        let cache_path = self.cache_dir.join(format!("{}.md", name));
-        fs::read_to_string(&cache_path)
-            .map_err(|e| Error::Other(format!("Cache read failed: {}", e)))
+    fn ensure_available(&self) -> Result<()> {
+        if self.registries.is_empty() {
+            return Err(Error::Other(if self.offline {
+                "Template marketplace is disabled (offline mode); only cached templates and local registries are available".to_string()
+            } else {
+                "No template registries configured".to_string()
+            }));
+        }
+        Ok(())
+    }
+
+    /// Search all registries, keeping the first hit for each name; a
+    /// failing registry is reported and skipped
+    fn search(&self, query: &str) -> Result<Vec<TemplateMetadata>> {
+        let mut results: Vec<TemplateMetadata> = Vec::new();
+        for registry in &self.registries {
+            let found = match registry.search(query) {
+                Ok(found) => found,
+                Err(e) => {
+                    eprintln!(
+                        "{} Skipping registry '{}': {}",
+                        "⚠".yellow(),
+                        registry.name,
+                        e
+                    );
+                    continue;
+                }
+            };
+            for meta in found {
+                if !results.iter().any(|r| r.name == meta.name) {
+                    results.push(meta);
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Versions from the highest-priority registry that has the template
+    fn versions(&self, name: &str) -> Result<(&TemplateRegistry, Vec<TemplateRelease>)> {
+        self.ensure_available()?;
+
+        let mut last_err = None;
+        for registry in &self.registries {
+            match registry.versions(name) {
+                Ok(releases) => return Ok((registry, releases)),
+                Err(Error::TemplateNotFound(_)) => continue,
+                Err(e) => {
+                    eprintln!("{} {}", "⚠".yellow(), e);
+                    last_err = Some(e);
+                }
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| Error::TemplateNotFound(name.to_string())))
+    }
+
+    /// Resolve the newest version matching `requirement` and fetch it
+    fn resolve(&self, name: &str, requirement: Option<&VersionReq>) -> Result<ResolvedTemplate> {
+        let (registry, releases) = self.versions(name)?;
+        let version = select_version(&releases, requirement).ok_or_else(|| {
+            Error::Other(format!(
+                "No version of '{}' matches '{}'",
+                name,
+                requirement
+                    .map(|r| r.to_string())
+                    .unwrap_or_else(|| "*".to_string())
+            ))
+        })?;
+
+        let expected = releases
+            .iter()
+            .find(|r| Version::parse(&r.version).ok().as_ref() == Some(&version))
+            .and_then(|r| r.sha256.clone());
+
+        self.fetch_from(registry, name, &version, expected.as_deref())
+    }
+
+    /// Fetch an exact locked version, preferring a verified cached copy
+    fn fetch(
+        &self,
+        name: &str,
+        version: &Version,
+        expected_sha256: &str,
+        source: &str,
+    ) -> Result<ResolvedTemplate> {
+        if let Some(content) = self.get_cached(name, version, expected_sha256)? {
+            return Ok(ResolvedTemplate {
+                version: version.clone(),
+                content,
+                sha256: expected_sha256.to_string(),
+                source: source.to_string(),
+            });
+        }
+
+        self.ensure_available()?;
+
+        // Prefer the registry recorded in the lockfile, then fall back by priority
+        let mut ordered: Vec<&TemplateRegistry> = self.registries.iter().collect();
+        ordered.sort_by_key(|r| r.source() != source);
+
+        let mut last_err = None;
+        for registry in ordered {
+            match self.fetch_from(registry, name, version, Some(expected_sha256)) {
+                Ok(resolved) => return Ok(resolved),
+                Err(e @ Error::TemplateIntegrity(..)) => return Err(e),
+                Err(e) => last_err = Some(e),
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| Error::TemplateNotFound(name.to_string())))
+    }
+
+    fn fetch_from(
+        &self,
+        registry: &TemplateRegistry,
+        name: &str,
+        version: &Version,
+        expected_sha256: Option<&str>,
+    ) -> Result<ResolvedTemplate> {
+        if let Some(expected) = expected_sha256 {
+            if let Some(content) = self.get_cached(name, version, expected)? {
+                return Ok(ResolvedTemplate {
+                    version: version.clone(),
+                    content,
+                    sha256: expected.to_string(),
+                    source: registry.source(),
+                });
+            }
+        }
+
+        let content = registry.download(name, version)?;
+        let sha256 = content_hash(&content);
+
+        if let Some(expected) = expected_sha256 {
+            if sha256 != expected {
+                return Err(Error::TemplateIntegrity(
+                    name.to_string(),
+                    expected.to_string(),
+                    sha256,
+                ));
+            }
+        }
+
+        // Versions are immutable, so the cache entry never goes stale
+        let cache_path = self.cache_path(name, version);
+        if let Some(parent) = cache_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&cache_path, &content)?;
+
+        Ok(ResolvedTemplate {
+            version: version.clone(),
+            content,
+            sha256,
+            source: registry.source(),
+        })
+    }
+
+    /// Publish to the highest-priority registry
+    fn publish(&self, metadata: &TemplateMetadata, content: &str) -> Result<()> {
+        self.ensure_available()?;
+        self.registries[0].publish(metadata, content)
+    }
+
+    fn cache_path(&self, name: &str, version: &Version) -> PathBuf {
+        self.cache_dir.join(name).join(format!("{}.md", version))
+    }
+
+    /// Read a cached version, discarding it if its hash doesn't match
+    fn get_cached(
+        &self,
+        name: &str,
+        version: &Version,
+        expected_sha256: &str,
+    ) -> Result<Option<String>> {
+        let cache_path = self.cache_path(name, version);
+        if !cache_path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&cache_path)
+            .map_err(|e| Error::Other(format!("Cache read failed: {}", e)))?;
+
+        if content_hash(&content) != expected_sha256 {
+            eprintln!(
+                "{} Cached copy of {} {} is corrupt, re-downloading",
+                "⚠".yellow(),
+                name,
+                version
+            );
+            fs::remove_file(&cache_path)?;
+            return Ok(None);
+        }
+
+        Ok(Some(content))
     }
 }
 
@@ -185,7 +819,8 @@ pub struct TemplateManager {
     #[allow(dead_code)]
     config: Config,
     templates_dir: PathBuf,
-    registry: TemplateRegistry,
+    lock_path: PathBuf,
+    registry: TemplateRegistries,
 }
 
 #[allow(dead_code)]
@@ -198,11 +833,12 @@ impl TemplateManager {
             .join("templates");
         fs::create_dir_all(&templates_dir)?;
 
-        let registry = TemplateRegistry::new()?;
+        let registry = TemplateRegistries::new(&config.hp.templates.marketplace)?;
 
         Ok(Self {
             config,
             templates_dir,
+            lock_path: PathBuf::from(".hp/templates.lock"),
             registry,
         })
     }
@@ -249,6 +885,23 @@ impl TemplateManager {
             println!();
         }
 
+        // List git template collections
+        let lock = TemplateLock::load(&self.lock_path)?;
+        if !lock.git.is_empty() {
+            println!("{}:", "Git sources".bold());
+            for (spec, source) in &lock.git {
+                println!(
+                    "  {} @ {}",
+                    spec,
+                    source.commit[..source.commit.len().min(7)].dimmed()
+                );
+                for name in source.templates.keys() {
+                    println!("    {}", name.cyan());
+                }
+            }
+            println!();
+        }
+
         Ok(())
     }
 
@@ -256,10 +909,21 @@ impl TemplateManager {
     pub fn install(&self, source: &str, name: Option<String>) -> Result<()> {
         println!("{} Installing template from {}", "→".cyan(), source);
 
+        if source.starts_with("git+") {
+            return self.install_from_git(source, name);
+        }
+
         let (content, template_name) = if source.starts_with("http://")
             || source.starts_with("https://")
         {
             // Install from URL
+            if !self.config.hp.templates.marketplace.enabled {
+                return Err(Error::Other(
+                    "Template marketplace is disabled (offline mode); cannot download from URL"
+                        .to_string(),
+                ));
+            }
+
             let client = Client::builder()
                 .timeout(Duration::from_secs(30))
                 .build()
@@ -304,32 +968,18 @@ impl TemplateManager {
 
             (content, template_name)
         } else {
-            // Install from registry by name
-            println!("{} Checking registry...", "→".dimmed());
+            // Install from registry by name[@requirement]
+            let (registry_name, requirement) = parse_template_spec(source)?;
+            let installed_as = name.filter(|n| *n != registry_name);
+            let content =
+                self.install_from_registry(&registry_name, requirement, installed_as.clone())?;
 
-            let content = if self.registry.is_cache_fresh(source) {
-                println!("{} Using cached version", "→".dimmed());
-                self.registry.get_cached(source)?
-            } else {
-                match self.registry.install(source, None) {
-                    Ok(content) => {
-                        println!("{} Downloaded from registry", "→".dimmed());
-                        content
-                    }
-                    Err(e) => {
-                        eprintln!("{} Registry error: {}", "⚠".yellow(), e);
-                        return Err(e);
-                    }
-                }
-            };
-
-            let template_name = name.unwrap_or_else(|| source.to_string());
+            let template_name = installed_as.unwrap_or(registry_name);
             (content, template_name)
         };
 
         // Install to local templates directory
-        let dest_path = self.templates_dir.join(format!("{}.md", template_name));
-        fs::write(&dest_path, &content)?;
+        let dest_path = self.write_template(&template_name, &content)?;
 
         println!("{} Template installed: {}", "✓".green(), template_name);
         println!("   Path: {}", dest_path.display());
@@ -338,6 +988,222 @@ impl TemplateManager {
         Ok(())
     }
 
+    /// Update locked templates to the newest versions their requirements allow
+    pub fn update(&self, name: Option<&str>) -> Result<()> {
+        let mut lock = TemplateLock::load(&self.lock_path)?;
+
+        // Templates can be named by installed name or by registry name
+        let matches = |key: &str, locked: &LockedTemplate| {
+            name.is_none_or(|n| key == n || locked.registry_name(key) == n)
+        };
+        if let Some(name) = name {
+            if !lock
+                .templates
+                .iter()
+                .any(|(key, locked)| matches(key, locked))
+            {
+                return Err(Error::TemplateNotFound(name.to_string()));
+            }
+        }
+
+        if lock.templates.is_empty() {
+            println!("{}", "No registry templates installed.".yellow());
+            return Ok(());
+        }
+
+        let mut updated = 0;
+        let names: Vec<String> = lock.templates.keys().cloned().collect();
+        for template_name in names {
+            let locked = lock.templates[&template_name].clone();
+            if !matches(&template_name, &locked) {
+                continue;
+            }
+            let registry_name = locked.registry_name(&template_name);
+
+            let requirement = VersionReq::parse(&locked.requirement).map_err(|e| {
+                Error::ParseError(format!(
+                    "Invalid requirement '{}' for '{}' in lockfile: {}",
+                    locked.requirement, template_name, e
+                ))
+            })?;
+
+            let resolved = match self.registry.resolve(registry_name, Some(&requirement)) {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    eprintln!("  {} {}: {}", "✗".red(), template_name, e);
+                    continue;
+                }
+            };
+
+            if resolved.version.to_string() == locked.version && resolved.sha256 == locked.sha256 {
+                println!(
+                    "  {} {} {} (up to date)",
+                    "✓".green(),
+                    template_name,
+                    locked.version.dimmed()
+                );
+                continue;
+            }
+
+            self.write_template(&template_name, &resolved.content)?;
+            println!(
+                "  {} {} {} → {}",
+                "↑".cyan(),
+                template_name,
+                locked.version,
+                resolved.version.to_string().green()
+            );
+
+            lock.templates.insert(
+                template_name,
+                LockedTemplate {
+                    version: resolved.version.to_string(),
+                    requirement: locked.requirement,
+                    source: resolved.source,
+                    sha256: resolved.sha256,
+                    registry_name: locked.registry_name,
+                },
+            );
+            updated += 1;
+        }
+
+        lock.save(&self.lock_path)?;
+
+        println!();
+        println!("{} Updated {} template(s)", "✓".green(), updated);
+        Ok(())
+    }
+
+    /// Show locked templates that have newer versions available
+    pub fn outdated(&self) -> Result<()> {
+        let lock = TemplateLock::load(&self.lock_path)?;
+
+        if lock.templates.is_empty() {
+            println!("{}", "No registry templates installed.".yellow());
+            return Ok(());
+        }
+
+        println!();
+        println!(
+            "{:<24} {:<10} {:<10} {:<10}",
+            "NAME", "CURRENT", "WANTED", "LATEST"
+        );
+        println!("{}", "=".repeat(56));
+
+        let mut outdated = 0;
+        for (name, locked) in &lock.templates {
+            let releases = match self.registry.versions(locked.registry_name(name)) {
+                Ok((_, releases)) => releases,
+                Err(e) => {
+                    eprintln!("  {} {}: {}", "✗".red(), name, e);
+                    continue;
+                }
+            };
+
+            let requirement = VersionReq::parse(&locked.requirement).ok();
+            let wanted = select_version(&releases, requirement.as_ref())
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            let latest = select_version(&releases, None)
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "-".to_string());
+
+            if wanted != locked.version || latest != locked.version {
+                outdated += 1;
+                println!(
+                    "{:<24} {:<10} {:<10} {:<10}",
+                    name, locked.version, wanted, latest
+                );
+            }
+        }
+
+        println!();
+        if outdated == 0 {
+            println!("{} All templates are up to date", "✓".green());
+        } else {
+            println!("Run 'hp template update' to install wanted versions.");
+        }
+        println!();
+
+        Ok(())
+    }
+
+    /// Pull updates for git template sources
+    ///
+    /// With `locked`, templates are restored at their pinned commits instead.
+    pub fn sync(&self, source: Option<&str>, locked: bool) -> Result<()> {
+        let mut lock = TemplateLock::load(&self.lock_path)?;
+
+        let key = source
+            .map(|s| GitTemplateSpec::parse(s).map(|spec| spec.key()))
+            .transpose()?;
+        if let Some(ref key) = key {
+            if !lock.git.contains_key(key) {
+                return Err(Error::TemplateNotFound(key.clone()));
+            }
+        }
+
+        if lock.git.is_empty() {
+            println!("{}", "No git template sources installed.".yellow());
+            return Ok(());
+        }
+
+        let mut synced = 0;
+        let keys: Vec<String> = lock.git.keys().cloned().collect();
+        for spec_key in keys {
+            if key.as_ref().is_some_and(|k| *k != spec_key) {
+                continue;
+            }
+
+            let entry = lock.git[&spec_key].clone();
+            let pinned = locked.then_some(entry.commit.as_str());
+            let updated = match self.checkout_git(&entry, pinned) {
+                Ok(updated) => updated,
+                Err(e) => {
+                    eprintln!("  {} {}: {}", "✗".red(), spec_key, e);
+                    continue;
+                }
+            };
+
+            // Drop templates that were removed from the collection
+            for name in entry.templates.keys() {
+                if !updated.templates.contains_key(name) {
+                    let path = self.templates_dir.join(format!("{}.md", name));
+                    if path.exists() {
+                        fs::remove_file(path)?;
+                    }
+                    println!("    {} {}", "-".red(), name);
+                }
+            }
+
+            if updated.commit == entry.commit {
+                println!(
+                    "  {} {} {} (up to date)",
+                    "✓".green(),
+                    spec_key,
+                    entry.commit[..7.min(entry.commit.len())].dimmed()
+                );
+            } else {
+                println!(
+                    "  {} {} {} → {}",
+                    "↑".cyan(),
+                    spec_key,
+                    &entry.commit[..7.min(entry.commit.len())],
+                    updated.commit[..7].green()
+                );
+                synced += 1;
+            }
+
+            lock.git.insert(spec_key, updated);
+        }
+
+        lock.save(&self.lock_path)?;
+
+        println!();
+        println!("{} Synced {} git source(s)", "✓".green(), synced);
+        Ok(())
+    }
+
     /// Publish template to registry
     pub fn publish(&self, template_name: &str) -> Result<()> {
         let template_path = self.templates_dir.join(format!("{}.md", template_name));
@@ -448,6 +1314,188 @@ impl TemplateManager {
 
     // === Private helper methods ===
 
+    /// Install a registry template, honoring the lockfile
+    ///
+    /// The lock entry is keyed by `installed_as`, or by `name` when the
+    /// template keeps its registry name.
+    fn install_from_registry(
+        &self,
+        name: &str,
+        requirement: Option<VersionReq>,
+        installed_as: Option<String>,
+    ) -> Result<String> {
+        let mut lock = TemplateLock::load(&self.lock_path)?;
+        let key = installed_as.clone().unwrap_or_else(|| name.to_string());
+
+        // Reuse the locked version unless the requirement moved, the name now
+        // refers to another registry template, or auto-update is on
+        if let Some(locked) = lock
+            .templates
+            .get(&key)
+            .filter(|locked| locked.registry_name(&key) == name)
+        {
+            let locked_version = Version::parse(&locked.version).map_err(|e| {
+                Error::ParseError(format!(
+                    "Invalid version '{}' for '{}' in lockfile: {}",
+                    locked.version, name, e
+                ))
+            })?;
+            let satisfies = requirement
+                .as_ref()
+                .map(|req| req.matches(&locked_version))
+                .unwrap_or(true);
+
+            if satisfies && !self.config.hp.templates.marketplace.auto_update {
+                println!("{} Using locked version {}", "→".dimmed(), locked_version);
+                let resolved =
+                    self.registry
+                        .fetch(name, &locked_version, &locked.sha256, &locked.source)?;
+                return Ok(resolved.content);
+            }
+        }
+
+        println!("{} Checking registry...", "→".dimmed());
+        let resolved = self.registry.resolve(name, requirement.as_ref())?;
+        println!("{} Resolved {} {}", "→".dimmed(), name, resolved.version);
+
+        // Without an explicit requirement, allow compatible updates
+        let requirement = requirement
+            .map(|r| r.to_string())
+            .unwrap_or_else(|| format!("^{}", resolved.version));
+
+        lock.templates.insert(
+            key,
+            LockedTemplate {
+                version: resolved.version.to_string(),
+                requirement,
+                source: resolved.source,
+                sha256: resolved.sha256.clone(),
+                registry_name: installed_as.map(|_| name.to_string()),
+            },
+        );
+        lock.save(&self.lock_path)?;
+
+        Ok(resolved.content)
+    }
+
+    /// Install templates from a git source, honoring the pinned commit
+    fn install_from_git(&self, source: &str, name: Option<String>) -> Result<()> {
+        let spec = GitTemplateSpec::parse(source)?;
+        let key = spec.key();
+        let mut lock = TemplateLock::load(&self.lock_path)?;
+
+        let existing = lock.git.get(&key).cloned();
+        let pinned = existing
+            .as_ref()
+            .filter(|e| e.name == name && !self.config.hp.templates.marketplace.auto_update)
+            .map(|e| e.commit.clone());
+        if let Some(ref commit) = pinned {
+            println!("{} Using locked commit {}", "→".dimmed(), &commit[..7]);
+        }
+
+        let entry = existing.unwrap_or(LockedGitSource {
+            url: spec.url.clone(),
+            reference: spec.reference.clone(),
+            path: spec.path.clone(),
+            name: name.clone(),
+            commit: String::new(),
+            templates: BTreeMap::new(),
+        });
+        let entry = LockedGitSource { name, ..entry };
+
+        let updated = self.checkout_git(&entry, pinned.as_deref())?;
+        println!(
+            "{} Installed {} template(s) from {} @ {}",
+            "✓".green(),
+            updated.templates.len(),
+            key,
+            &updated.commit[..7]
+        );
+        println!("   Path: {}", self.templates_dir.display());
+        println!();
+
+        lock.git.insert(key, updated);
+        lock.save(&self.lock_path)?;
+        Ok(())
+    }
+
+    /// Fetch a git source and write its templates at `pinned` or the tracked ref
+    fn checkout_git(
+        &self,
+        entry: &LockedGitSource,
+        pinned: Option<&str>,
+    ) -> Result<LockedGitSource> {
+        let mirror_dir = self.git_mirror_dir(&entry.url);
+
+        if self.config.hp.templates.marketplace.enabled {
+            let spinner = progress::spinner(&format!("Fetching {}...", entry.url));
+            match git::mirror(&entry.url, &mirror_dir) {
+                Ok(()) => spinner.finish_and_clear(),
+                Err(e) => {
+                    progress::finish_error(&spinner, "Fetch failed");
+                    return Err(e);
+                }
+            }
+        } else if !mirror_dir.exists() {
+            return Err(Error::Other(format!(
+                "Template marketplace is disabled (offline mode) and {} has not been fetched",
+                entry.url
+            )));
+        }
+
+        let commit = match pinned {
+            Some(commit) => git::resolve_commit(&mirror_dir, commit)?,
+            None => git::resolve_commit(&mirror_dir, entry.reference.as_deref().unwrap_or("HEAD"))?,
+        };
+
+        let templates =
+            collect_git_templates(&mirror_dir, &commit, &entry.path, entry.name.as_deref())?;
+
+        let mut hashes = BTreeMap::new();
+        for (name, content) in &templates {
+            let sha256 = content_hash(content);
+            if pinned.is_some() {
+                if let Some(expected) = entry.templates.get(name) {
+                    if *expected != sha256 {
+                        return Err(Error::TemplateIntegrity(
+                            name.clone(),
+                            expected.clone(),
+                            sha256,
+                        ));
+                    }
+                }
+            }
+            hashes.insert(name.clone(), sha256);
+        }
+
+        for (name, content) in &templates {
+            self.write_template(name, content)?;
+            println!("    {} {}", "+".green(), name);
+        }
+
+        Ok(LockedGitSource {
+            commit,
+            templates: hashes,
+            ..entry.clone()
+        })
+    }
+
+    fn git_mirror_dir(&self, url: &str) -> PathBuf {
+        self.config
+            .hp
+            .templates
+            .marketplace
+            .cache_dir
+            .join("git")
+            .join(&content_hash(url)[..16])
+    }
+
+    fn write_template(&self, name: &str, content: &str) -> Result<PathBuf> {
+        let dest_path = self.templates_dir.join(format!("{}.md", name));
+        fs::write(&dest_path, content)?;
+        Ok(dest_path)
+    }
+
     fn list_custom_templates(&self) -> Result<Vec<TemplateMetadata>> {
         let mut templates = Vec::new();
 
@@ -581,4 +1629,374 @@ mod tests {
         assert!(result.is_ok());
         // Note: File is installed to ~/.config/hupasiya/templates/ with source filename
     }
+
+    fn release(version: &str) -> TemplateRelease {
+        TemplateRelease {
+            version: version.to_string(),
+            sha256: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_template_spec() {
+        let (name, req) = parse_template_spec("fastapi-api").unwrap();
+        assert_eq!(name, "fastapi-api");
+        assert!(req.is_none());
+
+        let (name, req) = parse_template_spec("fastapi-api@^1.2").unwrap();
+        assert_eq!(name, "fastapi-api");
+        assert!(req.unwrap().matches(&Version::parse("1.9.0").unwrap()));
+
+        assert!(parse_template_spec("fastapi-api@not-a-version").is_err());
+        assert!(parse_template_spec("@1.0").is_err());
+    }
+
+    #[test]
+    fn test_select_version() {
+        let releases = vec![
+            release("1.1.0"),
+            release("1.2.5"),
+            release("1.3.0"),
+            release("2.0.0"),
+            release("2.1.0-beta.1"),
+        ];
+
+        let req = VersionReq::parse("^1.2").unwrap();
+        assert_eq!(
+            select_version(&releases, Some(&req)),
+            Some(Version::parse("1.3.0").unwrap())
+        );
+
+        let req = VersionReq::parse("~1.2").unwrap();
+        assert_eq!(
+            select_version(&releases, Some(&req)),
+            Some(Version::parse("1.2.5").unwrap())
+        );
+
+        // Prereleases are skipped unless explicitly requested
+        assert_eq!(
+            select_version(&releases, None),
+            Some(Version::parse("2.0.0").unwrap())
+        );
+
+        let req = VersionReq::parse("^3").unwrap();
+        assert_eq!(select_version(&releases, Some(&req)), None);
+    }
+
+    #[test]
+    fn test_lockfile_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock_path = temp_dir.path().join(".hp/templates.lock");
+
+        // Missing lockfile loads as empty
+        let mut lock = TemplateLock::load(&lock_path).unwrap();
+        assert!(lock.templates.is_empty());
+
+        lock.templates.insert(
+            "api".to_string(),
+            LockedTemplate {
+                version: "1.2.3".to_string(),
+                requirement: "^1.2".to_string(),
+                source: "https://hp-templates.dev".to_string(),
+                sha256: content_hash("# FastAPI"),
+                registry_name: Some("fastapi-api".to_string()),
+            },
+        );
+        lock.save(&lock_path).unwrap();
+
+        let loaded = TemplateLock::load(&lock_path).unwrap();
+        assert_eq!(loaded, lock);
+    }
+
+    #[test]
+    fn test_cached_template_integrity() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = TemplateRegistries::new(&MarketplaceConfig {
+            cache_dir: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap();
+        let version = Version::parse("1.0.0").unwrap();
+        let content = "# Cached Template";
+
+        let cache_path = registry.cache_path("cached", &version);
+        std::fs::create_dir_all(cache_path.parent().unwrap()).unwrap();
+        std::fs::write(&cache_path, content).unwrap();
+
+        let cached = registry
+            .get_cached("cached", &version, &content_hash(content))
+            .unwrap();
+        assert_eq!(cached.as_deref(), Some(content));
+
+        // A tampered cache entry is discarded
+        std::fs::write(&cache_path, "# Tampered").unwrap();
+        let cached = registry
+            .get_cached("cached", &version, &content_hash(content))
+            .unwrap();
+        assert!(cached.is_none());
+        assert!(!cache_path.exists());
+    }
+
+    fn marketplace(cache_dir: &Path, registries: Vec<(&str, String, u32)>) -> MarketplaceConfig {
+        MarketplaceConfig {
+            cache_dir: cache_dir.to_path_buf(),
+            registries: registries
+                .into_iter()
+                .map(|(name, url, priority)| RegistryConfig {
+                    name: name.to_string(),
+                    url,
+                    priority,
+                    token: None,
+                    token_env: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_registry_location_parse() {
+        assert_eq!(
+            RegistryLocation::parse("https://templates.example.com/"),
+            RegistryLocation::Remote("https://templates.example.com".to_string())
+        );
+        assert_eq!(
+            RegistryLocation::parse("file:///srv/templates"),
+            RegistryLocation::Local(PathBuf::from("/srv/templates"))
+        );
+        assert_eq!(
+            RegistryLocation::parse("/mnt/mirror"),
+            RegistryLocation::Local(PathBuf::from("/mnt/mirror"))
+        );
+    }
+
+    #[test]
+    fn test_local_registry_priority() {
+        let temp_dir = TempDir::new().unwrap();
+        let private = temp_dir.path().join("private");
+        let public = temp_dir.path().join("public");
+        for (root, version, body) in [
+            (&private, "1.0.0", "# Private 1.0"),
+            (&public, "1.0.0", "# Public 1.0"),
+            (&public, "1.1.0", "# Public 1.1"),
+            (&public, "2.0.0", "# Public 2.0"),
+        ] {
+            std::fs::create_dir_all(root.join("api")).unwrap();
+            std::fs::write(root.join("api").join(format!("{}.md", version)), body).unwrap();
+        }
+        std::fs::create_dir_all(public.join("docs")).unwrap();
+        std::fs::write(public.join("docs/0.3.0.md"), "# Docs").unwrap();
+
+        let config = marketplace(
+            &temp_dir.path().join("cache"),
+            vec![
+                ("public", format!("file://{}", public.display()), 20),
+                ("private", private.display().to_string(), 10),
+            ],
+        );
+        let registries = TemplateRegistries::new(&config).unwrap();
+
+        // The private registry shadows the public one
+        let resolved = registries.resolve("api", None).unwrap();
+        assert_eq!(resolved.content, "# Private 1.0");
+        assert_eq!(resolved.source, format!("file://{}", private.display()));
+
+        // Templates only in the lower-priority registry still resolve
+        let resolved = registries.resolve("docs", None).unwrap();
+        assert_eq!(resolved.version, Version::parse("0.3.0").unwrap());
+
+        // Locked versions are fetched from their recorded source
+        let expected = content_hash("# Public 1.1");
+        let source = format!("file://{}", public.display());
+        let resolved = registries
+            .fetch("api", &Version::parse("1.1.0").unwrap(), &expected, &source)
+            .unwrap();
+        assert_eq!(resolved.content, "# Public 1.1");
+
+        let results = registries.search("ap").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].version, "1.0.0");
+
+        // A broken registry doesn't hide the others' results
+        let broken = temp_dir.path().join("broken");
+        std::fs::write(&broken, "not a directory").unwrap();
+        let config = marketplace(
+            &temp_dir.path().join("cache"),
+            vec![
+                ("broken", broken.display().to_string(), 0),
+                ("public", format!("file://{}", public.display()), 20),
+            ],
+        );
+        let registries = TemplateRegistries::new(&config).unwrap();
+        assert_eq!(registries.search("docs").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_update_keeps_installed_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = temp_dir.path().join("registry");
+        std::fs::create_dir_all(registry.join("api")).unwrap();
+        std::fs::write(registry.join("api/1.0.0.md"), "# API 1.0").unwrap();
+
+        let mut config = Config::default();
+        config.hp.templates.marketplace = marketplace(
+            &temp_dir.path().join("cache"),
+            vec![("local", registry.display().to_string(), 0)],
+        );
+        let mgr = TemplateManager {
+            registry: TemplateRegistries::new(&config.hp.templates.marketplace).unwrap(),
+            config,
+            templates_dir: temp_dir.path().join("templates"),
+            lock_path: temp_dir.path().join("templates.lock"),
+        };
+        std::fs::create_dir_all(&mgr.templates_dir).unwrap();
+
+        mgr.install("api", Some("team-api".to_string())).unwrap();
+        let lock = TemplateLock::load(&mgr.lock_path).unwrap();
+        assert_eq!(lock.templates["team-api"].registry_name("team-api"), "api");
+
+        std::fs::write(registry.join("api/1.1.0.md"), "# API 1.1").unwrap();
+        mgr.update(Some("team-api")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(mgr.templates_dir.join("team-api.md")).unwrap(),
+            "# API 1.1"
+        );
+        assert!(!mgr.templates_dir.join("api.md").exists());
+
+        // The same template under a second name gets its own lock entry
+        mgr.install("api@=1.0.0", None).unwrap();
+        let lock = TemplateLock::load(&mgr.lock_path).unwrap();
+        assert_eq!(lock.templates["api"].version, "1.0.0");
+        assert_eq!(lock.templates["api"].registry_name, None);
+        assert_eq!(lock.templates["team-api"].version, "1.1.0");
+        assert_eq!(
+            std::fs::read_to_string(mgr.templates_dir.join("api.md")).unwrap(),
+            "# API 1.0"
+        );
+    }
+
+    #[test]
+    fn test_offline_mode_skips_remote_registries() {
+        let temp_dir = TempDir::new().unwrap();
+        let mirror = temp_dir.path().join("mirror");
+        std::fs::create_dir_all(mirror.join("api")).unwrap();
+        std::fs::write(mirror.join("api/1.0.0.md"), "# Mirror").unwrap();
+
+        let mut config = marketplace(
+            &temp_dir.path().join("cache"),
+            vec![
+                ("public", "https://hp-templates.dev".to_string(), 0),
+                ("mirror", mirror.display().to_string(), 10),
+            ],
+        );
+        config.enabled = false;
+
+        let registries = TemplateRegistries::new(&config).unwrap();
+        assert_eq!(registries.registries.len(), 1);
+        assert_eq!(registries.resolve("api", None).unwrap().content, "# Mirror");
+
+        // With no local registry, only cached content is available
+        config.registries.truncate(1);
+        let registries = TemplateRegistries::new(&config).unwrap();
+        assert!(registries.resolve("api", None).is_err());
+
+        let version = Version::parse("1.0.0").unwrap();
+        let cache_path = registries.cache_path("api", &version);
+        std::fs::create_dir_all(cache_path.parent().unwrap()).unwrap();
+        std::fs::write(&cache_path, "# Mirror").unwrap();
+        let resolved = registries
+            .fetch("api", &version, &content_hash("# Mirror"), "mirror")
+            .unwrap();
+        assert_eq!(resolved.content, "# Mirror");
+    }
+
+    #[test]
+    fn test_parse_git_spec() {
+        let spec =
+            GitTemplateSpec::parse("git+https://github.com/acme/templates.git#v2:agents/api.md")
+                .unwrap();
+        assert_eq!(spec.url, "https://github.com/acme/templates.git");
+        assert_eq!(spec.reference.as_deref(), Some("v2"));
+        assert_eq!(spec.path, "agents/api.md");
+        assert_eq!(
+            spec.key(),
+            "git+https://github.com/acme/templates.git#v2:agents/api.md"
+        );
+
+        let spec = GitTemplateSpec::parse("git+git@github.com:acme/templates.git").unwrap();
+        assert_eq!(spec.url, "git@github.com:acme/templates.git");
+        assert!(spec.reference.is_none());
+        assert_eq!(spec.path, "");
+
+        let spec = GitTemplateSpec::parse("git+/srv/templates#:collection/").unwrap();
+        assert!(spec.reference.is_none());
+        assert_eq!(spec.path, "collection");
+        assert_eq!(spec.key(), "git+/srv/templates#:collection");
+
+        assert!(GitTemplateSpec::parse("git+#main").is_err());
+        assert!(GitTemplateSpec::parse("git+--upload-pack=touch /tmp/x").is_err());
+        assert!(GitTemplateSpec::parse("git+/srv/templates#-main").is_err());
+    }
+
+    #[test]
+    fn test_git_install_and_sync() {
+        let temp_dir = TempDir::new().unwrap();
+        let origin = temp_dir.path().join("origin");
+        std::fs::create_dir_all(origin.join("agents")).unwrap();
+        let git_in = |args: &[&str]| git::run(Some(&origin), args).unwrap();
+        git_in(&["init", "--quiet", "-b", "main"]);
+        git_in(&["config", "user.email", "test@example.com"]);
+        git_in(&["config", "user.name", "Test"]);
+        std::fs::write(origin.join("agents/api.md"), "# API v1").unwrap();
+        std::fs::write(origin.join("agents/docs.md"), "# Docs").unwrap();
+        std::fs::write(origin.join("agents/README.md"), "Collection").unwrap();
+        git_in(&["add", "-A"]);
+        git_in(&["commit", "--quiet", "-m", "init"]);
+
+        let mut config = Config::default();
+        config.hp.templates.marketplace.cache_dir = temp_dir.path().join("cache");
+        let mgr = TemplateManager {
+            config,
+            templates_dir: temp_dir.path().join("templates"),
+            lock_path: temp_dir.path().join("templates.lock"),
+            registry: TemplateRegistries::new(&MarketplaceConfig::default()).unwrap(),
+        };
+        std::fs::create_dir_all(&mgr.templates_dir).unwrap();
+
+        let source = format!("git+{}#main:agents", origin.display());
+        mgr.install(&source, None).unwrap();
+
+        let lock = TemplateLock::load(&mgr.lock_path).unwrap();
+        let entry = &lock.git[&source];
+        let first_commit = entry.commit.clone();
+        assert_eq!(
+            entry.templates.keys().collect::<Vec<_>>(),
+            vec!["api", "docs"]
+        );
+        assert_eq!(
+            std::fs::read_to_string(mgr.templates_dir.join("api.md")).unwrap(),
+            "# API v1"
+        );
+
+        // Upstream changes: api updated, docs removed
+        std::fs::write(origin.join("agents/api.md"), "# API v2").unwrap();
+        git_in(&["rm", "--quiet", "agents/docs.md"]);
+        git_in(&["commit", "--quiet", "-am", "update"]);
+
+        // Reinstalling keeps the pinned commit
+        mgr.install(&source, None).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(mgr.templates_dir.join("api.md")).unwrap(),
+            "# API v1"
+        );
+
+        mgr.sync(None, false).unwrap();
+        let lock = TemplateLock::load(&mgr.lock_path).unwrap();
+        assert_ne!(lock.git[&source].commit, first_commit);
+        assert_eq!(
+            std::fs::read_to_string(mgr.templates_dir.join("api.md")).unwrap(),
+            "# API v2"
+        );
+        assert!(!mgr.templates_dir.join("docs.md").exists());
+    }
 }
@@ -3,19 +3,26 @@
 use crate::config::Config;
 use crate::error::Result;
 use crate::models::SessionStatus;
+use crate::processes::ProcessManager;
 use crate::session::SessionManager;
+use crate::workspace::{self, PaneStatus};
 use colored::Colorize;
 
 /// Utilities manager
 pub struct UtilitiesManager {
     session_mgr: SessionManager,
+    process_mgr: ProcessManager,
 }
 
 impl UtilitiesManager {
     /// Create new utilities manager
     pub fn new(config: Config) -> Result<Self> {
+        let process_mgr = ProcessManager::new(config.clone())?;
         let session_mgr = SessionManager::new(config)?;
-        Ok(Self { session_mgr })
+        Ok(Self {
+            session_mgr,
+            process_mgr,
+        })
     }
 
     /// Monitor all sessions (dashboard view)
@@ -27,20 +34,22 @@ impl UtilitiesManager {
             }
 
             let sessions = self.session_mgr.list_sessions()?;
+            let panes = workspace::pane_status();
+            let agents = self.process_mgr.liveness()?;
 
             println!();
             println!("{} Session Monitor", "📊".bold());
-            println!("─────────────────────────────────────────────");
+            println!("───────────────────────────────────────────────────────────────────");
             println!();
 
             if sessions.is_empty() {
                 println!("  {}", "No sessions found".yellow());
             } else {
                 println!(
-                    "{:20} {:10} {:15} {:8}",
-                    "Name", "Status", "Last Active", "PRs"
+                    "{:20} {:10} {:15} {:8} {:8} {:12}",
+                    "Name", "Status", "Last Active", "PRs", "Agent", "Tmux"
                 );
-                println!("─────────────────────────────────────────────");
+                println!("───────────────────────────────────────────────────────────────────");
 
                 for session in &sessions {
                     let status_str = match session.status {
@@ -70,9 +79,21 @@ impl UtilitiesManager {
                         "-".to_string()
                     };
 
+                    let pane_str = match panes.get(&session.name) {
+                        Some(pane @ PaneStatus::Running(_)) => pane.to_string().green(),
+                        Some(PaneStatus::Exited) => "exited".yellow(),
+                        None => "-".dimmed(),
+                    };
+
+                    let agent_str = match agents.get(&session.name) {
+                        Some(true) => "● live".green(),
+                        Some(false) => "○ dead".red(),
+                        None => "-".dimmed(),
+                    };
+
                     println!(
-                        "{:20} {:10} {:15} {:8}",
-                        session.name, status_str, last_active, pr_str
+                        "{:20} {:10} {:15} {:8} {:8} {:12}",
+                        session.name, status_str, last_active, pr_str, agent_str, pane_str
                     );
                 }
             }
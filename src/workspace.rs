@@ -0,0 +1,347 @@
+//! Managed tmux workspace for a session tree
+//!
+//! `hp workspace up <root>` builds one tmux session holding a window per
+//! session in the tree. Each window runs the AI tool (through `hp launch`)
+//! in one pane, with a shell in the workbox and a live
+//! `hp activity show --follow` beside it. Running `up` again adds windows
+//! for new sessions and reattaches; `down` kills the tmux session.
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::models::{Session, SessionStatus};
+use crate::session::SessionManager;
+use colored::Colorize;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// Pane option marking each pane's role; titles can be changed by the
+/// programs running in them
+const ROLE_OPTION: &str = "@hp_role";
+
+/// Role of the pane running the AI tool
+const AI_PANE: &str = "ai";
+
+/// Workspace manager
+pub struct WorkspaceManager {
+    session_mgr: SessionManager,
+}
+
+/// State of the AI pane in a session's workspace window
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaneStatus {
+    /// The tool is running; holds the pane's foreground command
+    Running(String),
+    /// The tool exited; the pane stays open to show its output
+    Exited,
+}
+
+impl std::fmt::Display for PaneStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaneStatus::Running(command) => write!(f, "{}", command),
+            PaneStatus::Exited => write!(f, "exited"),
+        }
+    }
+}
+
+impl WorkspaceManager {
+    /// Create new workspace manager
+    pub fn new(config: Config) -> Result<Self> {
+        let session_mgr = SessionManager::new(config)?;
+        Ok(Self { session_mgr })
+    }
+
+    /// Create or extend the workspace for `root`'s tree, then attach to it
+    pub fn up(&self, root: &str, detach: bool) -> Result<()> {
+        check_tmux()?;
+        // Closed-out sessions may no longer have a workbox
+        let tree: Vec<Session> = self
+            .session_mgr
+            .get_session_tree(root)?
+            .into_iter()
+            .filter(|s| !matches!(s.status, SessionStatus::Archived | SessionStatus::Abandoned))
+            .collect();
+        let name = workspace_name(root);
+        let hp = env::current_exe()?;
+        let cwd = env::current_dir()?;
+
+        let existing = if has_session(&name) {
+            list_windows(&name)?
+        } else {
+            HashSet::new()
+        };
+        let mut added = 0;
+        for session in tree.iter().filter(|s| !existing.contains(&s.name)) {
+            let first = existing.is_empty() && added == 0;
+            self.add_window(&name, session, &hp, &cwd, first)?;
+            added += 1;
+        }
+
+        if existing.is_empty() {
+            println!(
+                "{} Created workspace '{}' with {} windows",
+                "✓".green(),
+                name,
+                added
+            );
+        } else if added > 0 {
+            println!(
+                "{} Added {} windows to workspace '{}'",
+                "✓".green(),
+                added,
+                name
+            );
+        } else {
+            println!("{} Workspace '{}' is up to date", "✓".green(), name);
+        }
+
+        if detach {
+            println!("  Attach with: tmux attach -t {}", name);
+            return Ok(());
+        }
+        attach(&name)
+    }
+
+    /// Kill the workspace for `root`
+    pub fn down(&self, root: &str) -> Result<()> {
+        check_tmux()?;
+        let name = workspace_name(root);
+        if !has_session(&name) {
+            println!("{} No workspace '{}' is running", "ℹ".dimmed(), name);
+            return Ok(());
+        }
+
+        tmux(&["kill-session", "-t", &format!("={}", name)])?;
+        println!("{} Stopped workspace '{}'", "✓".green(), name);
+        Ok(())
+    }
+
+    /// Add the window for one session: AI tool left, shell and activity right
+    fn add_window(
+        &self,
+        workspace: &str,
+        session: &Session,
+        hp: &Path,
+        cwd: &Path,
+        first: bool,
+    ) -> Result<()> {
+        let hp = shell_quote(&hp.to_string_lossy());
+        let cwd = cwd.to_string_lossy();
+        let workbox = session.workbox_path.to_string_lossy();
+        let env = format!("HP_SESSION={}", session.name);
+        let launch = format!("{} launch {}", hp, shell_quote(&session.name));
+
+        // hp reads sessions relative to the repo, so its panes start here
+        let target = format!("={}", workspace);
+        let mut args = if first {
+            vec!["new-session", "-d", "-s", workspace]
+        } else {
+            vec!["new-window", "-t", &target]
+        };
+        args.extend([
+            "-P",
+            "-F",
+            "#{pane_id}",
+            "-n",
+            &session.name,
+            "-c",
+            &cwd,
+            "-e",
+            &env,
+            &launch,
+        ]);
+        let ai_pane = tmux(&args)?;
+
+        // Keep the pane after the tool exits so its status and output remain
+        tmux(&["set-option", "-p", "-t", &ai_pane, "remain-on-exit", "on"])?;
+        set_role(&ai_pane, AI_PANE)?;
+
+        let shell_pane = tmux(&[
+            "split-window",
+            "-h",
+            "-t",
+            &ai_pane,
+            "-P",
+            "-F",
+            "#{pane_id}",
+            "-c",
+            &workbox,
+            "-e",
+            &env,
+        ])?;
+        set_role(&shell_pane, "shell")?;
+
+        let follow = format!(
+            "{} activity show {} --limit 10 --follow",
+            hp,
+            shell_quote(&session.name)
+        );
+        let activity_pane = tmux(&[
+            "split-window",
+            "-v",
+            "-t",
+            &shell_pane,
+            "-P",
+            "-F",
+            "#{pane_id}",
+            "-c",
+            &cwd,
+            &follow,
+        ])?;
+        set_role(&activity_pane, "activity")?;
+        tmux(&["select-pane", "-t", &ai_pane])?;
+
+        println!("  {} {}", "→".cyan(), session.name);
+        Ok(())
+    }
+}
+
+/// Status of each session's AI pane across running workspaces and
+/// `hp launch` tmux sessions, keyed by session name. Empty without tmux.
+pub fn pane_status() -> HashMap<String, PaneStatus> {
+    let output = Command::new("tmux")
+        .args([
+            "list-panes",
+            "-a",
+            "-F",
+            "#{session_name}\t#{window_name}\t#{@hp_role}\t#{pane_dead}\t#{pane_current_command}",
+        ])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            parse_panes(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => HashMap::new(),
+    }
+}
+
+fn parse_panes(output: &str) -> HashMap<String, PaneStatus> {
+    let mut status = HashMap::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [tmux_session, window, role, dead, command] = fields[..] else {
+            continue;
+        };
+
+        let session = match tmux_session.strip_prefix("hp-") {
+            Some(_) if tmux_session.starts_with("hp-ws-") => {
+                if role != AI_PANE {
+                    continue;
+                }
+                window
+            }
+            // Single-session `hp launch` with the tmux launch method
+            Some(name) => name,
+            None => continue,
+        };
+
+        let pane = if dead == "1" {
+            PaneStatus::Exited
+        } else {
+            PaneStatus::Running(command.to_string())
+        };
+        status.insert(session.to_string(), pane);
+    }
+    status
+}
+
+/// tmux session name for a tree root; tmux doesn't allow '.' or ':'
+pub fn workspace_name(root: &str) -> String {
+    format!("hp-ws-{}", root.replace(['.', ':'], "-"))
+}
+
+/// Tag `pane` with its role, and show it as the pane title
+fn set_role(pane: &str, role: &str) -> Result<()> {
+    tmux(&["set-option", "-p", "-t", pane, ROLE_OPTION, role])?;
+    tmux(&["select-pane", "-t", pane, "-T", role])?;
+    Ok(())
+}
+
+fn check_tmux() -> Result<()> {
+    which::which("tmux")
+        .map(|_| ())
+        .map_err(|_| Error::Other("tmux not found. Install tmux to use workspaces".to_string()))
+}
+
+fn has_session(name: &str) -> bool {
+    Command::new("tmux")
+        .args(["has-session", "-t", &format!("={}", name)])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+fn list_windows(name: &str) -> Result<HashSet<String>> {
+    let output = tmux(&[
+        "list-windows",
+        "-t",
+        &format!("={}", name),
+        "-F",
+        "#{window_name}",
+    ])?;
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+fn attach(name: &str) -> Result<()> {
+    let target = format!("={}", name);
+    // Inside tmux, switch this client rather than nesting
+    let args = if env::var_os("TMUX").is_some() {
+        ["switch-client", "-t", &target]
+    } else {
+        ["attach-session", "-t", &target]
+    };
+    let status = Command::new("tmux").args(args).status()?;
+    if !status.success() {
+        return Err(Error::Other(format!(
+            "tmux {} exited with status: {}",
+            args[0], status
+        )));
+    }
+    Ok(())
+}
+
+/// Run tmux, returning its trimmed stdout
+fn tmux(args: &[&str]) -> Result<String> {
+    let output = Command::new("tmux").args(args).output()?;
+    if !output.status.success() {
+        return Err(Error::Other(format!(
+            "tmux {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Quote `value` as a single word for `sh`, e.g. in tmux pane commands
+pub(crate) fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_name() {
+        assert_eq!(workspace_name("auth"), "hp-ws-auth");
+        assert_eq!(workspace_name("v1.2:fix"), "hp-ws-v1-2-fix");
+    }
+
+    #[test]
+    fn test_parse_panes() {
+        let output = "hp-ws-auth\tauth\tai\t0\tclaude\n\
+                      hp-ws-auth\tauth\tshell\t0\tzsh\n\
+                      hp-ws-auth\tauth-tests\tai\t1\tclaude\n\
+                      hp-docs\tclaude\t\t0\tclaude\n\
+                      work\tmain\t\t0\tvim\n";
+        let status = parse_panes(output);
+
+        assert_eq!(status.len(), 3);
+        assert_eq!(status["auth"], PaneStatus::Running("claude".to_string()));
+        assert_eq!(status["auth-tests"], PaneStatus::Exited);
+        assert_eq!(status["docs"], PaneStatus::Running("claude".to_string()));
+    }
+}